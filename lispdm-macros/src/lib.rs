@@ -0,0 +1,452 @@
+//! Derive macros for conversions between Rust types and LispDM expressions.
+//!
+//! Use them through `lispdm` with its `derive` feature enabled.
+//!
+//! Structs with named fields are converted to association lists, e.g. `((name . "Alice") (age . 30))`,
+//! tuple structs to lists of their values, structs with a single unnamed field to their value
+//! and unit structs to `'()`. Variants of enums are lists tagged with the name of the variant,
+//! e.g. `(circle (radius . 1.0))`, `(point 1 2)` or `(empty)`.
+//! Names of fields and variants are written in kebab case. Named `Option` fields, which are `None`,
+//! are left out, and missing ones are `None`. Elsewhere `None` is `#f`, same as `false`,
+//! so `Option<bool>` round-trips only in named fields.
+//!
+//! `lisp!` reads Scheme code at compile time, enable the `macros` feature of `lispdm` to use it.
+mod lisp;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_macro_input, parse_quote, Data, DataEnum, DeriveInput, Field, Fields,
+    GenericArgument, Generics, Ident, PathArguments, Type,
+};
+
+/// Derives `lispdm::FromExpr`.
+///
+/// Errors of conversions name the field or the variant, which has a wrong value.
+#[proc_macro_derive(FromExpr)]
+pub fn derive_from_expr(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_expr(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives conversion into `lispdm::Expr`, i.e. `From<T> for lispdm::Expr`.
+#[proc_macro_derive(IntoExpr)]
+pub fn derive_into_expr(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_into_expr(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Reads Scheme code at compile time into `lispdm::Exprs`.
+///
+/// Syntax errors are reported as compile errors at the token, where reading failed.
+/// Rust expressions are interpolated with `#{expr}` and converted with `Into<lispdm::Expr>`.
+///
+/// Code is written with Rust tokens, so quotation abbreviations, e.g. `'(1 2)`, characters
+/// and comments, which are not valid Rust tokens, should be written in a raw string literal,
+/// e.g. `lisp!(r"(display '(1 2))")`, which is read as Scheme code.
+///
+/// Symbols written as interpolations, e.g. `|#{0}|`, are reserved:
+/// ```compile_fail
+/// let exprs = lispdm::lisp!(r"(quote |#{3}|)");
+/// ```
+/// ```compile_fail
+/// let x = 1;
+/// let exprs = lispdm::lisp!(r"(list #{x} '|#{0}|)");
+/// ```
+///
+/// Cyclic data, which are read from datum labels referring to their own data, can not be built:
+/// ```compile_fail
+/// let exprs = lispdm::lisp!(r"'#0=(a . #0#)");
+/// ```
+#[proc_macro]
+pub fn lisp(input: TokenStream) -> TokenStream {
+    lisp::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_expr(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::lispdm::FromExpr));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let kind = name.to_string();
+
+    let (from_expr, expected_kind, mismatch_detail) = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            // a single value is not wrapped into a list
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                (
+                    quote! { <#ty as ::lispdm::FromExpr>::from_expr(expr).map(Self) },
+                    quote! { <#ty as ::lispdm::FromExpr>::expected_kind() },
+                    quote! { <#ty as ::lispdm::FromExpr>::mismatch_detail(expr) },
+                )
+            }
+            fields => {
+                let convert = convert_fields(quote!(Self), fields, quote!(expr));
+                let check = check_fields(fields, quote!(expr), None);
+                (
+                    quote! { #convert },
+                    quote! { #kind },
+                    quote! {
+                        #check
+                        None
+                    },
+                )
+            }
+        },
+        Data::Enum(data) => {
+            let (from_expr, mismatch_detail) = enum_from_expr(data);
+            (from_expr, quote! { #kind }, mismatch_detail)
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "FromExpr can not be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::lispdm::FromExpr for #name #ty_generics #where_clause {
+            fn from_expr(expr: ::lispdm::Expr) -> ::lispdm::FromExprResult<Self> {
+                #from_expr
+            }
+
+            fn expected_kind() -> &'static str {
+                #expected_kind
+            }
+
+            fn mismatch_detail(expr: &::lispdm::Expr) -> ::core::option::Option<::std::string::String> {
+                #mismatch_detail
+            }
+        }
+    })
+}
+
+fn enum_from_expr(data: &DataEnum) -> (TokenStream2, TokenStream2) {
+    let mut convert_arms = Vec::new();
+    let mut check_arms = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let tag = variant_name(ident);
+        let convert = convert_fields(quote!(Self::#ident), &variant.fields, quote!(rest));
+        let prefix = format!("in variant {}: ", tag);
+        let check = check_fields(&variant.fields, quote!(rest), Some(&prefix));
+        convert_arms.push(quote! { #tag => { #convert } });
+        check_arms.push(quote! { #tag => { #check None } });
+    }
+
+    let from_expr = quote! {
+        let (tag, rest) = match ::lispdm::__private::split_variant(&expr) {
+            Some(variant) => variant,
+            None => return Err(expr),
+        };
+        match tag.as_str() {
+            #(#convert_arms)*
+            _ => Err(expr),
+        }
+    };
+    let mismatch_detail = quote! {
+        let (tag, rest) = ::lispdm::__private::split_variant(expr)?;
+        match tag.as_str() {
+            #(#check_arms)*
+            _ => Some(::std::format!("unknown variant {}", tag)),
+        }
+    };
+    (from_expr, mismatch_detail)
+}
+
+// converts `src` into `path` built from `fields`, or returns the original `expr`
+fn convert_fields(path: TokenStream2, fields: &Fields, src: TokenStream2) -> TokenStream2 {
+    match fields {
+        Fields::Named(fields) => {
+            let idents: Vec<_> = fields
+                .named
+                .iter()
+                .map(|f| f.ident.clone().unwrap())
+                .collect();
+            let values = fields.named.iter().map(|f| {
+                let value = field_value(f, &src);
+                match option_inner(&f.ty) {
+                    Some(ty) => quote! { ::lispdm::__private::convert_optional::<#ty>(#value) },
+                    None => {
+                        let ty = &f.ty;
+                        quote! { ::lispdm::__private::convert::<#ty>(#value) }
+                    }
+                }
+            });
+            quote! {
+                if !::lispdm::__private::is_alist(&#src) {
+                    return Err(expr);
+                }
+                #(
+                    let #idents = match #values {
+                        Some(value) => value,
+                        None => return Err(expr),
+                    };
+                )*
+                Ok(#path { #(#idents),* })
+            }
+        }
+        Fields::Unnamed(fields) => {
+            let len = fields.unnamed.len();
+            let idents: Vec<_> = (0..len).map(|i| format_ident!("field{}", i)).collect();
+            let types = fields.unnamed.iter().map(|f| &f.ty);
+            quote! {
+                let mut items = match ::lispdm::__private::list_items(&#src, #len) {
+                    Some(items) => items.into_iter(),
+                    None => return Err(expr),
+                };
+                #(
+                    let #idents = match ::lispdm::__private::convert::<#types>(items.next()) {
+                        Some(value) => value,
+                        None => return Err(expr),
+                    };
+                )*
+                Ok(#path(#(#idents),*))
+            }
+        }
+        Fields::Unit => quote! {
+            if ::lispdm::__private::list_items(&#src, 0).is_none() {
+                return Err(expr);
+            }
+            Ok(#path)
+        },
+    }
+}
+
+// returns description of the first field of `src`, which can not be converted;
+// if `src` has a wrong shape, it is described only in variants, which have a `prefix`
+fn check_fields(fields: &Fields, src: TokenStream2, prefix: Option<&str>) -> TokenStream2 {
+    let prefix = prefix.unwrap_or("");
+    if prefix.is_empty() && fields.is_empty() {
+        return TokenStream2::new();
+    }
+    let wrong_shape = |message: String| -> TokenStream2 {
+        if prefix.is_empty() {
+            quote! { return None; }
+        } else {
+            let message = format!("{}{}", prefix, message);
+            quote! { return Some(::std::string::String::from(#message)); }
+        }
+    };
+
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields
+                .named
+                .iter()
+                .map(|f| field_name(f.ident.as_ref().unwrap()));
+            let mismatches = fields.named.iter().zip(names).map(|(f, name)| {
+                let value = field_value(f, &src);
+                match option_inner(&f.ty) {
+                    Some(ty) => quote! {
+                        ::lispdm::__private::optional_field_mismatch::<#ty>(#name, #value)
+                    },
+                    None => {
+                        let ty = &f.ty;
+                        quote! { ::lispdm::__private::field_mismatch::<#ty>(#name, #value) }
+                    }
+                }
+            });
+            let wrong_shape = wrong_shape("expected association list of fields".to_string());
+            quote! {
+                if !::lispdm::__private::is_alist(&#src) {
+                    #wrong_shape
+                }
+                #(
+                    if let Some(detail) = #mismatches {
+                        return Some(::std::format!("{}{}", #prefix, detail));
+                    }
+                )*
+            }
+        }
+        Fields::Unnamed(fields) => {
+            let len = fields.unnamed.len();
+            let positions = 1..=len;
+            let types = fields.unnamed.iter().map(|f| &f.ty);
+            let wrong_shape = wrong_shape(format!("expected {} values", len));
+            quote! {
+                let mut items = match ::lispdm::__private::list_items(&#src, #len) {
+                    Some(items) => items.into_iter(),
+                    None => { #wrong_shape }
+                };
+                #(
+                    if let Some(detail) =
+                        ::lispdm::__private::item_mismatch::<#types>(#positions, items.next().unwrap())
+                    {
+                        return Some(::std::format!("{}{}", #prefix, detail));
+                    }
+                )*
+            }
+        }
+        Fields::Unit => {
+            let wrong_shape = wrong_shape("expected no values".to_string());
+            quote! {
+                if ::lispdm::__private::list_items(&#src, 0).is_none() {
+                    #wrong_shape
+                }
+            }
+        }
+    }
+}
+
+fn expand_into_expr(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = add_bounds(
+        &input.generics,
+        parse_quote!(::core::convert::Into<::lispdm::Expr>),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                quote! { value.0.into() }
+            }
+            fields => {
+                let (pattern, items) = destructure(quote!(#name), fields);
+                quote! {
+                    let #pattern = value;
+                    ::lispdm::Expr::new_proper_list(#items)
+                }
+            }
+        },
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let tag = variant_name(ident);
+                let (pattern, items) = destructure(quote!(#name::#ident), &variant.fields);
+                quote! {
+                    #pattern => ::lispdm::__private::variant(#tag, #items),
+                }
+            });
+            quote! {
+                match value {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "IntoExpr can not be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for ::lispdm::Expr #where_clause {
+            fn from(value: #name #ty_generics) -> Self {
+                #body
+            }
+        }
+    })
+}
+
+// returns pattern, which binds all fields, and expression of `Exprs` of the converted fields
+fn destructure(path: TokenStream2, fields: &Fields) -> (TokenStream2, TokenStream2) {
+    match fields {
+        Fields::Named(fields) => {
+            let idents: Vec<_> = fields
+                .named
+                .iter()
+                .map(|f| f.ident.clone().unwrap())
+                .collect();
+            // `None` fields are left out
+            let items = fields.named.iter().zip(&idents).map(|(f, ident)| {
+                let name = field_name(ident);
+                if option_inner(&f.ty).is_some() {
+                    quote! { ::lispdm::__private::optional_field(#name, #ident) }
+                } else {
+                    quote! {
+                        ::core::option::Option::Some(::lispdm::__private::field(#name, #ident))
+                    }
+                }
+            });
+            (
+                quote! { #path { #(#idents),* } },
+                quote! { [#(#items),*].into_iter().flatten().collect::<::lispdm::Exprs>() },
+            )
+        }
+        Fields::Unnamed(fields) => {
+            let idents: Vec<_> = (0..fields.unnamed.len())
+                .map(|i| format_ident!("field{}", i))
+                .collect();
+            let items = idents
+                .iter()
+                .map(|ident| quote! { ::core::convert::Into::<::lispdm::Expr>::into(#ident) });
+            (
+                quote! { #path(#(#idents),*) },
+                quote! { ::lispdm::Exprs::from([#(#items),*]) },
+            )
+        }
+        Fields::Unit => (quote! { #path }, quote! { ::lispdm::Exprs::new() }),
+    }
+}
+
+fn add_bounds(generics: &Generics, bound: syn::TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+// value of a named field in the association list `src`
+fn field_value(field: &Field, src: &TokenStream2) -> TokenStream2 {
+    let name = field_name(field.ident.as_ref().unwrap());
+    quote! { ::lispdm::__private::alist_get(&#src, #name) }
+}
+
+// `T` of `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args)
+            if segment.ident == "Option" && args.args.len() == 1 =>
+        {
+            match &args.args[0] {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// `first_name` is `first-name`
+fn field_name(ident: &Ident) -> String {
+    ident.unraw().to_string().replace('_', "-")
+}
+
+// `BigCircle` is `big-circle`, `HTTPError` is `http-error`
+fn variant_name(ident: &Ident) -> String {
+    let chars: Vec<char> = ident.unraw().to_string().chars().collect();
+    let mut name = String::new();
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if prev.is_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_uppercase() && next_is_lower)
+            {
+                name.push('-');
+            }
+        }
+        name.extend(ch.to_lowercase());
+    }
+    name.replace('_', "-")
+}
@@ -0,0 +1,354 @@
+//! Expansion of `lisp!`, which reads Scheme code at compile time.
+
+use lispdm_parser::{Datum, ParseError};
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use std::{collections::HashMap, ops::Range};
+use syn::{spanned::Spanned, LitStr};
+
+pub fn expand(input: TokenStream) -> syn::Result<TokenStream2> {
+    let source = match raw_string(&input) {
+        Some(lit) => Source::from_raw_string(lit)?,
+        None => Source::from_tokens(input)?,
+    };
+    let data = lispdm_parser::parse_str(&source.text).map_err(|err| source.error(err))?;
+    source.check_placeholders()?;
+    let mut exprs = data
+        .iter()
+        // datum labels are not shared between top-level data
+        .map(|datum| source.expr_tokens(datum, &mut HashMap::new()))
+        .collect::<syn::Result<Vec<_>>>()?;
+    if exprs.is_empty() {
+        exprs.push(quote!(::lispdm::Expr::Void));
+    }
+    Ok(quote! {
+        <::lispdm::Exprs>::from([#(#exprs),*])
+    })
+}
+
+// Scheme code, which can not be written with Rust tokens, e.g. `'(1 2)`, is passed as a raw string
+fn raw_string(input: &TokenStream) -> Option<LitStr> {
+    let mut tokens = input.clone().into_iter();
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(lit)), None) if lit.to_string().starts_with('r') => {
+            syn::parse(TokenTree::Literal(lit).into()).ok()
+        }
+        _ => None,
+    }
+}
+
+// symbol, which stands for the Rust expression interpolated with `#{expr}`
+fn placeholder(index: usize) -> String {
+    format!("|#{{{}}}|", index)
+}
+
+fn placeholder_index(symbol: &str) -> Option<usize> {
+    symbol.strip_prefix("#{")?.strip_suffix('}')?.parse().ok()
+}
+
+// returns the first symbol in `datum`, which is read as a placeholder
+fn find_placeholder(datum: &Datum) -> Option<&str> {
+    match datum {
+        Datum::Symbol(symbol) if placeholder_index(symbol).is_some() => Some(symbol),
+        Datum::List(list) => list
+            .items
+            .iter()
+            .chain(list.tail.as_deref())
+            .find_map(find_placeholder),
+        Datum::Labeled(_, datum) => find_placeholder(datum),
+        _ => None,
+    }
+}
+
+struct Source {
+    text: String,
+    // line and column of tokens in `text` with their spans in Rust code, in order
+    locations: Vec<((usize, usize), Span)>,
+    interpolations: Vec<syn::Expr>,
+    // ranges of placeholders of interpolations in `text`
+    placeholders: Vec<Range<usize>>,
+}
+
+impl Source {
+    fn from_raw_string(lit: LitStr) -> syn::Result<Self> {
+        let value = lit.value();
+        let mut source = Source {
+            text: String::new(),
+            locations: vec![((1, 1), lit.span())],
+            interpolations: Vec::new(),
+            placeholders: Vec::new(),
+        };
+        let mut chars = value.char_indices().peekable();
+        while let Some((start, ch)) = chars.next() {
+            source.text.push(ch);
+            match ch {
+                // interpolations are not read inside strings, quoted symbols and comments
+                '"' | '|' => {
+                    while let Some((_, next)) = chars.next() {
+                        source.text.push(next);
+                        if next == '\\' {
+                            source.text.extend(chars.next().map(|(_, ch)| ch));
+                        } else if next == ch {
+                            break;
+                        }
+                    }
+                }
+                ';' => {
+                    while let Some((_, next)) = chars.next_if(|&(_, next)| next != '\n') {
+                        source.text.push(next);
+                    }
+                }
+                '#' => match chars.peek() {
+                    Some((_, '\\')) => {
+                        source.text.extend(chars.by_ref().take(2).map(|(_, ch)| ch));
+                    }
+                    Some((_, '{')) => {
+                        source.text.pop();
+                        let mut depth = 0;
+                        let end = chars
+                            .by_ref()
+                            .find(|&(_, ch)| {
+                                match ch {
+                                    '{' => depth += 1,
+                                    '}' => depth -= 1,
+                                    _ => {}
+                                }
+                                depth == 0
+                            })
+                            .map(|(end, _)| end)
+                            .ok_or_else(|| syn::Error::new(lit.span(), "unclosed `#{`"))?;
+                        let expr = syn::parse_str(&value[start + 2..end])
+                            .map_err(|err| syn::Error::new(lit.span(), err))?;
+                        source.interpolate(expr);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(source)
+    }
+
+    fn from_tokens(input: TokenStream) -> syn::Result<Self> {
+        let mut writer = Writer {
+            source: Source {
+                text: String::new(),
+                locations: Vec::new(),
+                interpolations: Vec::new(),
+                placeholders: Vec::new(),
+            },
+            line: 1,
+            column: 1,
+            end: None,
+        };
+        writer.write_stream(input)?;
+        Ok(writer.source)
+    }
+
+    fn interpolate(&mut self, expr: syn::Expr) {
+        let start = self.text.len();
+        self.text.push_str(&placeholder(self.interpolations.len()));
+        self.placeholders.push(start..self.text.len());
+        self.interpolations.push(expr);
+    }
+
+    // symbols, which are written same as placeholders, e.g. `|#{0}|`, would be replaced
+    // with interpolations, so the code is read again with placeholders written differently
+    // to find them
+    fn check_placeholders(&self) -> syn::Result<()> {
+        let mut text = self.text.clone();
+        for range in self.placeholders.iter().rev() {
+            text.replace_range(range.clone(), "|#()|");
+        }
+        let data = lispdm_parser::parse_str(&text).map_err(|err| self.error(err))?;
+        match data.iter().find_map(find_placeholder) {
+            Some(symbol) => Err(syn::Error::new(
+                Span::call_site(),
+                format!("symbol |{}| is reserved for interpolations", symbol),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn error(&self, err: ParseError) -> syn::Error {
+        let (span, err) = match err {
+            ParseError::Located(location, err) => {
+                let location = (location.line(), location.column());
+                let span = self
+                    .locations
+                    .iter()
+                    .take_while(|(start, _)| *start <= location)
+                    .last()
+                    .map(|(_, span)| *span);
+                (span, *err)
+            }
+            err => (None, err),
+        };
+        let span = span
+            .or_else(|| self.locations.last().map(|(_, span)| *span))
+            .unwrap_or_else(Span::call_site);
+        syn::Error::new(span, format!("syntax error: {}", err))
+    }
+
+    // `labels` are tokens of labeled data, references to them are copies
+    fn expr_tokens(
+        &self,
+        datum: &Datum,
+        labels: &mut HashMap<u64, TokenStream2>,
+    ) -> syn::Result<TokenStream2> {
+        Ok(match datum {
+            Datum::Boolean(boolean) => quote!(::lispdm::Expr::Boolean(#boolean)),
+            Datum::Integer(integer) => quote!(::lispdm::Expr::Integer(#integer)),
+            Datum::Float(float) => {
+                let float = match float {
+                    float if float.is_nan() => quote!(f64::NAN),
+                    float if *float == f64::INFINITY => quote!(f64::INFINITY),
+                    float if *float == f64::NEG_INFINITY => quote!(f64::NEG_INFINITY),
+                    float => {
+                        let float = Literal::f64_suffixed(*float);
+                        quote!(#float)
+                    }
+                };
+                quote!(::lispdm::Expr::Float(#float))
+            }
+            Datum::Char(ch) => quote!(::lispdm::Expr::Char(#ch)),
+            Datum::String(string) => quote!(::lispdm::Expr::new_string(#string)),
+            Datum::Symbol(symbol) => match placeholder_index(symbol) {
+                Some(index) => {
+                    let expr = self.interpolations.get(index).ok_or_else(|| {
+                        syn::Error::new(
+                            Span::call_site(),
+                            format!("symbol |{}| is reserved for interpolations", symbol),
+                        )
+                    })?;
+                    quote_spanned!(expr.span()=> ::lispdm::Expr::from(#expr))
+                }
+                None => quote!(::lispdm::Expr::new_symbol(#symbol)),
+            },
+            Datum::List(list) => {
+                let items = list
+                    .items
+                    .iter()
+                    .chain(list.tail.as_deref())
+                    .map(|datum| self.expr_tokens(datum, labels))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                let items = quote!(<::lispdm::Exprs>::from([#(#items),*]));
+                match list.tail {
+                    None => quote!(::lispdm::Expr::new_proper_list(#items)),
+                    Some(_) => quote!(::lispdm::Expr::new_dotted_list(#items)),
+                }
+            }
+            Datum::Labeled(label, datum) => {
+                let tokens = self.expr_tokens(datum, labels)?;
+                labels.insert(*label, tokens.clone());
+                tokens
+            }
+            // the parser checks that references follow their labels, so a label which is not
+            // done yet labels the datum the reference is in
+            Datum::Reference(label) => labels.get(label).cloned().ok_or_else(|| {
+                syn::Error::new(
+                    Span::call_site(),
+                    format!(
+                        "datum label #{}# refers to its own datum, cyclic data can not be built",
+                        label
+                    ),
+                )
+            })?,
+        })
+    }
+}
+
+// writes Rust tokens as Scheme code, tokens are separated same as in Rust code
+struct Writer {
+    source: Source,
+    // line and column of the next char in the text
+    line: usize,
+    column: usize,
+    // line and column in Rust code, where the last token ends
+    end: Option<(usize, usize)>,
+}
+
+impl Writer {
+    fn write_stream(&mut self, stream: TokenStream) -> syn::Result<()> {
+        let mut tokens = stream.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Punct(punct) if punct.as_char() == '#' => match tokens.peek() {
+                    Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                        let expr = syn::parse(group.stream())?;
+                        self.separate(punct.span());
+                        self.record(punct.span());
+                        let start = self.source.text.len();
+                        let text = placeholder(self.source.interpolations.len());
+                        self.push(&text);
+                        self.source.placeholders.push(start..self.source.text.len());
+                        self.source.interpolations.push(expr);
+                        self.end = Some(position(group.span_close().end()));
+                        tokens.next();
+                    }
+                    _ => self.write_token("#", punct.span()),
+                },
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::None => ("", ""),
+                    };
+                    if !open.is_empty() {
+                        self.write_token(open, group.span_open());
+                    }
+                    self.write_stream(group.stream())?;
+                    if !close.is_empty() {
+                        self.write_token(close, group.span_close());
+                    }
+                }
+                TokenTree::Punct(punct) => {
+                    self.write_token(&punct.as_char().to_string(), punct.span())
+                }
+                TokenTree::Ident(ident) => self.write_token(&ident.to_string(), ident.span()),
+                TokenTree::Literal(lit) => self.write_token(&lit.to_string(), lit.span()),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_token(&mut self, text: &str, span: proc_macro::Span) {
+        self.separate(span);
+        self.record(span);
+        self.push(text);
+        self.end = Some(position(span.end()));
+    }
+
+    // tokens, which are adjacent in Rust code, are adjacent in Scheme code,
+    // e.g. `set-car!` is read from tokens `set`, `-`, `car` and `!`
+    fn separate(&mut self, span: proc_macro::Span) {
+        match self.end {
+            Some(end) if end.0 < span.line() => self.push("\n"),
+            Some(end) if end != position(span) => self.push(" "),
+            _ => {}
+        }
+    }
+
+    fn record(&mut self, span: proc_macro::Span) {
+        let location = (self.line, self.column);
+        self.source.locations.push((location, span.into()));
+    }
+
+    fn push(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.source.text.push_str(text);
+    }
+}
+
+fn position(span: proc_macro::Span) -> (usize, usize) {
+    (span.line(), span.column())
+}
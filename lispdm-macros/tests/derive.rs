@@ -0,0 +1,271 @@
+use lispdm::{Engine, ErrorKind, Expr, FromExpr, IntoExpr};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq, FromExpr, IntoExpr)]
+struct Person {
+    name: String,
+    age: i64,
+    email: Option<String>,
+    home_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, FromExpr, IntoExpr)]
+struct Point(i64, i64);
+
+#[derive(Debug, PartialEq, FromExpr, IntoExpr)]
+struct Meters(f64);
+
+#[derive(Debug, PartialEq, FromExpr, IntoExpr)]
+struct Empty;
+
+#[derive(Debug, Clone, PartialEq, FromExpr, IntoExpr)]
+enum Shape {
+    Circle { center: Point, radius: f64 },
+    Polygon(Vec<Point>),
+    BigSquare(Point, f64),
+    Nothing,
+}
+
+#[derive(Debug, Clone, PartialEq, FromExpr, IntoExpr)]
+struct Flags {
+    verbose: Option<bool>,
+    level: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, FromExpr, IntoExpr)]
+struct Tagged<T> {
+    tag: String,
+    value: T,
+}
+
+fn eval<T: FromExpr>(engine: &mut Engine, src: &str) -> T {
+    engine.eval::<T>(src).unwrap().ok().unwrap()
+}
+
+fn alice() -> Person {
+    Person {
+        name: "Alice".to_string(),
+        age: 30,
+        email: None,
+        home_dir: PathBuf::from("/home/alice"),
+    }
+}
+
+#[test]
+fn derive_struct_as_alist() {
+    let mut engine = Engine::default();
+    let expr = Expr::from(alice());
+    assert_eq!(
+        expr.to_string(),
+        r#"((name . "Alice") (age . 30) (home-dir . "/home/alice"))"#
+    );
+    assert_eq!(Person::from_expr(expr), Ok(alice()));
+
+    let person: Person = eval(
+        &mut engine,
+        r#"'((age . 30) (name . Alice) (home-dir . "/home/alice") (extra . 1))"#,
+    );
+    assert_eq!(person, alice());
+    let person: Person = eval(
+        &mut engine,
+        r#"'((name . "Bob") (age . 40) (email . "bob@example.com") (home-dir . "/"))"#,
+    );
+    assert_eq!(person.email.as_deref(), Some("bob@example.com"));
+}
+
+#[test]
+fn derive_optional_fields() {
+    let mut engine = Engine::default();
+    for verbose in [None, Some(false), Some(true)] {
+        let flags = Flags {
+            verbose,
+            level: None,
+        };
+        assert_eq!(Flags::from_expr(Expr::from(flags.clone())), Ok(flags));
+    }
+    let flags = Flags {
+        verbose: Some(false),
+        level: Some(2),
+    };
+    assert_eq!(
+        Expr::from(flags.clone()).to_string(),
+        "((verbose . #f) (level . 2))"
+    );
+    assert_eq!(
+        eval::<Flags>(&mut engine, "'((level . #f))"),
+        Flags {
+            verbose: None,
+            level: None,
+        }
+    );
+
+    let expr = engine
+        .eval::<Flags>("'((level . high))")
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(
+        Flags::describe_mismatch(&expr),
+        "expected Flags, got list: expected integer in field level, got symbol"
+    );
+}
+
+#[test]
+fn derive_tuple_and_unit_structs() {
+    let mut engine = Engine::default();
+    assert_eq!(Expr::from(Point(1, 2)).to_string(), "(1 2)");
+    assert_eq!(eval::<Point>(&mut engine, "'(3 4)"), Point(3, 4));
+    assert_eq!(Expr::from(Meters(1.5)), Expr::Float(1.5));
+    assert_eq!(eval::<Meters>(&mut engine, "2.5"), Meters(2.5));
+    assert_eq!(Expr::from(Empty).to_string(), "()");
+    assert_eq!(eval::<Empty>(&mut engine, "'()"), Empty);
+    assert!(engine.eval::<Point>("'(1 2 3)").unwrap().is_err());
+}
+
+#[test]
+fn derive_enum_as_tagged_list() {
+    let mut engine = Engine::default();
+    let shapes = vec![
+        Shape::Circle {
+            center: Point(0, 0),
+            radius: 1.0,
+        },
+        Shape::Polygon(vec![Point(0, 0), Point(1, 0), Point(0, 1)]),
+        Shape::BigSquare(Point(1, 1), 2.0),
+        Shape::Nothing,
+    ];
+    let expr = Expr::from(shapes.clone());
+    assert_eq!(
+        expr.to_string(),
+        "((circle (center 0 0) (radius . 1.0)) (polygon ((0 0) (1 0) (0 1))) (big-square (1 1) 2.0) (nothing))"
+    );
+    assert_eq!(Vec::<Shape>::from_expr(expr), Ok(shapes));
+
+    let shape: Shape = eval(&mut engine, "'(circle (radius . 2.0) (center 1 1))");
+    assert_eq!(
+        shape,
+        Shape::Circle {
+            center: Point(1, 1),
+            radius: 2.0
+        }
+    );
+    assert!(engine.eval::<Shape>("'(triangle)").unwrap().is_err());
+}
+
+#[test]
+fn derive_generic_struct() {
+    let mut engine = Engine::default();
+    let tagged = Tagged {
+        tag: "size".to_string(),
+        value: Point(1, 2),
+    };
+    assert_eq!(
+        Expr::from(tagged).to_string(),
+        r#"((tag . "size") (value 1 2))"#
+    );
+    let tagged: Tagged<i64> = eval(&mut engine, "'((tag . size) (value . 3))");
+    assert_eq!(tagged.value, 3);
+}
+
+#[test]
+fn derive_errors_name_fields() {
+    let mut engine = Engine::default();
+    engine.register("person-age", |person: Person| person.age);
+    engine.register("area", |shape: Shape| match shape {
+        Shape::Circle { radius, .. } => 3.0 * radius * radius,
+        _ => 0.0,
+    });
+
+    let err = engine
+        .eval::<i64>(r#"(person-age '((name . "Bob") (age . "forty") (home-dir . "/")))"#)
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Person as argument 1 of person-age, got list: \
+             expected integer in field age, got string"
+                .to_string()
+        )
+    );
+    let err = engine
+        .eval::<i64>(r#"(person-age '((name . "Bob") (age . 40)))"#)
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Person as argument 1 of person-age, got list: missing field home-dir"
+                .to_string()
+        )
+    );
+    let err = engine.eval::<i64>("(person-age 1)").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Person as argument 1 of person-age, got integer".to_string()
+        )
+    );
+
+    let err = engine
+        .eval::<f64>("(area '(circle (center 0 zero) (radius . 1.0)))")
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Shape as argument 1 of area, got list: \
+             in variant circle: expected Point in field center, got list: \
+             expected integer as element 2, got symbol"
+                .to_string()
+        )
+    );
+    let err = engine
+        .eval::<f64>("(area '(big-square (0 0)))")
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Shape as argument 1 of area, got list: \
+             in variant big-square: expected 2 values"
+                .to_string()
+        )
+    );
+    let err = engine.eval::<f64>("(area '(triangle))").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Shape as argument 1 of area, got list: unknown variant triangle".to_string()
+        )
+    );
+}
+
+#[test]
+fn std_conversions() {
+    let mut engine = Engine::default();
+    assert_eq!(eval::<Option<i64>>(&mut engine, "#f"), None);
+    assert_eq!(eval::<Option<i64>>(&mut engine, "1"), Some(1));
+    assert_eq!(Expr::from(None::<i64>), Expr::Boolean(false));
+
+    let map: HashMap<String, i64> = eval(&mut engine, "'((a . 1) (b . 2) (a . 3))");
+    assert_eq!(
+        map,
+        HashMap::from([("a".to_string(), 3), ("b".to_string(), 2)])
+    );
+    let map: BTreeMap<i64, Vec<i64>> = eval(&mut engine, "'((2 3 4) (1))");
+    assert_eq!(map, BTreeMap::from([(1, vec![]), (2, vec![3, 4])]));
+    assert_eq!(
+        Expr::from(BTreeMap::from([(1, 2.5), (2, 3.5)])).to_string(),
+        "((1 . 2.5) (2 . 3.5))"
+    );
+    assert!(engine
+        .eval::<HashMap<String, i64>>("'(1 2)")
+        .unwrap()
+        .is_err());
+
+    assert_eq!(
+        eval::<PathBuf>(&mut engine, r#""/tmp/file.txt""#),
+        PathBuf::from("/tmp/file.txt")
+    );
+    assert_eq!(
+        Expr::from(PathBuf::from("a/b")),
+        Expr::new_string("a/b".to_string())
+    );
+}
@@ -0,0 +1,86 @@
+use lispdm::{lisp, Engine, Expr, Exprs};
+
+fn eval(engine: &mut Engine, exprs: Exprs) -> Expr {
+    engine.eval_exprs::<Expr>(exprs).unwrap().unwrap()
+}
+
+fn to_string(exprs: &Exprs) -> String {
+    let exprs: Vec<_> = exprs.iter().map(Expr::to_string).collect();
+    exprs.join(" ")
+}
+
+#[test]
+fn lisp_reads_tokens() {
+    let exprs = lisp! {
+        (define (f x) (* x x))
+        (set-car! pair 1+)
+        (x . y)
+        (#t #f "a \"b\"" -3 1.5 a.b)
+    };
+    assert_eq!(
+        to_string(&exprs),
+        r#"(define (f x) (* x x)) (set-car! pair 1+) (x . y) (#t #f "a \"b\"" -3 1.5 a.b)"#
+    );
+    assert_eq!(
+        exprs,
+        lispdm_parse(
+            "(define (f x) (* x x)) (set-car! pair 1+) (x . y) (#t #f \"a \\\"b\\\"\" -3 1.5 a.b)"
+        )
+    );
+    // same as `Engine::eval`, empty code is read as void
+    assert_eq!(lisp! {}, Exprs::from([Expr::Void]));
+
+    let mut engine = Engine::default();
+    eval(&mut engine, lisp! { (define (f x) (* x x)) });
+    assert_eq!(eval(&mut engine, lisp! { (f 7) }), Expr::Integer(49));
+}
+
+#[test]
+fn lisp_reads_raw_strings() {
+    let exprs = lisp!(
+        r#"
+        ; comment with 'quotes'
+        '(1 #\a "b") `(x ,y ,@z) #;(skipped) (#0=(a) #0#) (x . #1=(y))
+        "#
+    );
+    assert_eq!(
+        to_string(&exprs),
+        r#"(quote (1 #\a "b")) (quasiquote (x (unquote y) (unquote-splicing z))) ((a) (a)) (x y)"#
+    );
+
+    let mut engine = Engine::default();
+    let xs = vec![1, 2, 3];
+    let exprs = lisp!(r"(map (lambda (x) (* x #{xs.len() as i64})) '#{xs})");
+    assert_eq!(eval(&mut engine, exprs).to_string(), "(3 6 9)");
+}
+
+#[test]
+fn lisp_interpolates_rust_values() {
+    let mut engine = Engine::default();
+    let name = "world";
+    let items = vec![1, 2, 3];
+    let exprs = lisp! {
+        (string-append "hello, " #{name})
+        (quote #{items.clone()})
+        (apply + #{items.clone()} (quote (#{1 + 1} . #{vec![3, 4]})))
+    };
+    assert_eq!(
+        to_string(&exprs),
+        r#"(string-append "hello, " "world") (quote (1 2 3)) (apply + (1 2 3) (quote (2 3 4)))"#
+    );
+    assert_eq!(
+        eval(&mut engine, lisp! { (string-append "hello, " #{name}) }),
+        Expr::new_string("hello, world")
+    );
+    assert_eq!(
+        eval(&mut engine, lisp! { (apply + (quote #{items})) }),
+        Expr::Integer(6)
+    );
+}
+
+// reads `src` with the parser of LispDM at runtime
+fn lispdm_parse(src: &str) -> Exprs {
+    let mut engine = Engine::default();
+    let exprs = engine.eval::<Vec<Expr>>(&format!("(quote ({}))", src));
+    exprs.unwrap().unwrap().into()
+}
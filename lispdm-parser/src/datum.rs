@@ -0,0 +1,83 @@
+use crate::span::Span;
+
+/// Value read from Scheme code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Datum {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Char(char),
+    String(String),
+    Symbol(String),
+    List(List),
+    /// Datum labeled with `#n=`, so it can be referred to with `#n#`.
+    Labeled(u64, Box<Datum>),
+    /// Reference to the datum labeled with `#n=` before it.
+    ///
+    /// A reference inside of the labeled datum makes the datum cyclic, e.g. `#0=(a . #0#)`.
+    Reference(u64),
+}
+
+impl Datum {
+    /// Returns the list, if the datum is a list.
+    pub fn as_list(&self) -> Option<&List> {
+        match self {
+            Datum::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// List read from a list form or a quotation, e.g. `(a b . c)` or `'a`.
+#[derive(Debug, Clone)]
+pub struct List {
+    /// Items of the list, without the tail of a dotted list.
+    pub items: Vec<Datum>,
+    /// Tail of a dotted list, e.g. `c` in `(a b . c)`.
+    ///
+    /// A list written as the tail, e.g. `(a . (b))`, is read into items of the list.
+    pub tail: Option<Box<Datum>>,
+    /// Location of the opening paren, lists read from quotations have none.
+    pub span: Option<Span>,
+}
+
+impl List {
+    /// Creates a list, which is not located in source code.
+    pub fn new(items: Vec<Datum>, tail: Option<Datum>) -> Self {
+        List {
+            items,
+            tail: tail.map(Box::new),
+            span: None,
+        }
+    }
+}
+
+// lists are compared by items, their location does not matter
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items && self.tail == other.tail
+    }
+}
+
+// nested lists are dropped one by one instead of recursively,
+// so dropping deeply nested data does not overflow the stack
+impl Drop for List {
+    fn drop(&mut self) {
+        let is_nested = |datum: &Datum| matches!(datum, Datum::List(_) | Datum::Labeled(..));
+        if !self.items.iter().chain(self.tail.as_deref()).any(is_nested) {
+            return;
+        }
+        let mut nested = std::mem::take(&mut self.items);
+        nested.extend(self.tail.take().map(|tail| *tail));
+        while let Some(datum) = nested.pop() {
+            match datum {
+                Datum::List(mut list) => {
+                    nested.append(&mut list.items);
+                    nested.extend(list.tail.take().map(|tail| *tail));
+                }
+                Datum::Labeled(_, datum) => nested.push(*datum),
+                _ => {}
+            }
+        }
+    }
+}
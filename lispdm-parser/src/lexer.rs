@@ -0,0 +1,945 @@
+use super::span::Span;
+use core::fmt;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+#[derive(PartialEq, Debug)]
+pub enum Token {
+    Eof,
+    Comment(String),
+    Integer(i64),
+    Float(f64),
+    Symbol(String),
+    LParen,
+    RParen,
+    Quote,           // '
+    Quasiquote,      // `
+    Unquote,         // ,
+    UnquoteSplicing, // ,@
+    Boolean(bool),
+    String(String),
+    Char(char),
+    Dot,                 // " . "
+    DatumLabel(u64),     // #n=
+    DatumReference(u64), // #n#
+    DatumComment,        // #;
+}
+
+#[derive(PartialEq, Debug)]
+pub enum LexicalError {
+    UnexpectedEOF,
+    UnexpectedRParen,
+    UnclosedString,
+    UnclosedSymbol,
+    UnclosedComment,
+    InvalidEscape,
+    UnexpectedChar,
+}
+
+impl fmt::Display for LexicalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LexicalError::UnexpectedEOF => write!(f, "unexpected EOF"),
+            LexicalError::UnexpectedRParen => write!(f, "unexpected ')'"),
+            LexicalError::UnclosedString => write!(f, "unclosed string"),
+            LexicalError::UnclosedSymbol => write!(f, "unclosed |symbol|"),
+            LexicalError::UnclosedComment => write!(f, "unclosed block comment"),
+            LexicalError::InvalidEscape => write!(f, "invalid escape sequence"),
+            LexicalError::UnexpectedChar => write!(f, "unexpected char"),
+        }
+    }
+}
+
+pub type LexResult = Result<Token, LexicalError>;
+
+/// Token with the location of its first char, if it is known.
+pub type SpannedLexResult = (LexResult, Option<Span>);
+
+// chars of the source code, which keep track of the line and column of the next char
+#[derive(Clone)]
+struct SourceChars<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> SourceChars<'a> {
+    fn new(program: &'a str) -> Self {
+        Self {
+            chars: program.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+}
+
+impl Iterator for SourceChars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+}
+
+pub struct Lexer<'a> {
+    chars: SourceChars<'a>,
+    open_paren_count: i32,
+    has_error: bool,
+    // set by `#!fold-case`, symbols and character names are read in lower case
+    fold_case: bool,
+    file: Option<Rc<str>>,
+    // line and column of the last token
+    token_start: (usize, usize),
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(program: &'a str) -> Self {
+        Lexer {
+            chars: SourceChars::new(program),
+            open_paren_count: 0,
+            has_error: false,
+            fold_case: false,
+            file: None,
+            token_start: (1, 1),
+        }
+    }
+
+    /// Creates a lexer of source code read from `file`, which is included in spans of tokens.
+    pub fn with_file(program: &'a str, file: &str) -> Self {
+        Lexer {
+            file: Some(file.into()),
+            ..Lexer::new(program)
+        }
+    }
+
+    /// Returns location of the last token.
+    pub fn span(&self) -> Span {
+        let (line, column) = self.token_start;
+        Span::new(self.file.clone(), line, column)
+    }
+
+    /// Turns lexer into an iterator of tokens with their locations.
+    pub fn spanned(mut self) -> impl Iterator<Item = SpannedLexResult> + 'a {
+        std::iter::from_fn(move || {
+            let token = self.next()?;
+            Some((token, Some(self.span())))
+        })
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = LexResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.has_error {
+            return None;
+        }
+
+        self.token_start = (self.chars.line, self.chars.column);
+        match self.chars.peek() {
+            Some(&ch) => {
+                let result = match ch {
+                    '(' => {
+                        self.open_paren_count += 1;
+                        self.chars.next();
+                        Ok(Token::LParen)
+                    }
+                    ')' => {
+                        self.open_paren_count -= 1;
+                        self.chars.next();
+                        if self.open_paren_count < 0 {
+                            Err(LexicalError::UnexpectedRParen)
+                        } else {
+                            Ok(Token::RParen)
+                        }
+                    }
+                    '.' => match self.chars.clone().nth(1) {
+                        Some(next_ch) if next_ch.is_whitespace() => {
+                            self.chars.next();
+                            Ok(Token::Dot)
+                        }
+                        _ => finalize_token(&mut self.chars, self.fold_case),
+                    },
+                    '"' => lex_string(&mut self.chars),
+                    '|' => lex_quoted_symbol(&mut self.chars),
+                    '#' => match self.chars.clone().nth(1) {
+                        Some('|') => lex_block_comment(&mut self.chars),
+                        Some(';') => {
+                            self.chars.nth(1);
+                            Ok(Token::DatumComment)
+                        }
+                        Some('\\') => lex_char(&mut self.chars, self.fold_case),
+                        _ => match finalize_token(&mut self.chars, self.fold_case) {
+                            Ok(Token::Symbol(directive)) if directive == "#!fold-case" => {
+                                self.fold_case = true;
+                                return self.next();
+                            }
+                            Ok(Token::Symbol(directive)) if directive == "#!no-fold-case" => {
+                                self.fold_case = false;
+                                return self.next();
+                            }
+                            result => result,
+                        },
+                    },
+                    '\'' => {
+                        self.chars.next();
+                        Ok(Token::Quote)
+                    }
+                    '`' => {
+                        self.chars.next();
+                        Ok(Token::Quasiquote)
+                    }
+                    ',' => {
+                        self.chars.next();
+                        match self.chars.clone().nth(0) {
+                            Some('@') => {
+                                self.chars.next();
+                                Ok(Token::UnquoteSplicing)
+                            }
+                            _ => Ok(Token::Unquote),
+                        }
+                    }
+                    ';' => {
+                        self.chars.next();
+                        let comment = consume_until_newline(&mut self.chars);
+                        Ok(Token::Comment(comment))
+                    }
+                    _ if ch.is_whitespace() => {
+                        self.chars.next();
+                        return self.next();
+                    }
+                    _ => finalize_token(&mut self.chars, self.fold_case),
+                };
+                if result.is_err() {
+                    self.has_error = true;
+                }
+                Some(result)
+            }
+            None => match (self.open_paren_count > 0, self.open_paren_count < 0) {
+                (true, _) => {
+                    self.has_error = true;
+                    Some(Err(LexicalError::UnexpectedEOF))
+                }
+                (_, true) => {
+                    self.has_error = true;
+                    Some(Err(LexicalError::UnexpectedRParen))
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, '(' | ')' | '\'' | '"' | ';')
+}
+
+fn finalize_token(chars: &mut SourceChars, fold_case: bool) -> LexResult {
+    let mut token_string = String::new();
+
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            _ if is_delimiter(ch) => break,
+            // `#n=` ends the token, labeled datum starts right after it
+            '=' if datum_label_number(&token_string).is_some() => {
+                chars.next();
+                return Ok(Token::DatumLabel(
+                    datum_label_number(&token_string).unwrap(),
+                ));
+            }
+            _ => token_string.push(ch),
+        }
+        chars.next();
+    }
+
+    if fold_case {
+        token_string = token_string.to_lowercase();
+    }
+
+    match token_string.as_str() {
+        "#t" | "#true" => Ok(Token::Boolean(true)),
+        "#f" | "#false" => Ok(Token::Boolean(false)),
+        "+inf.0" => Ok(Token::Float(f64::INFINITY)),
+        "-inf.0" => Ok(Token::Float(f64::NEG_INFINITY)),
+        "+nan.0" | "-nan.0" => Ok(Token::Float(f64::NAN)),
+        _ if token_string.ends_with('#')
+            && datum_label_number(&token_string[..token_string.len() - 1]).is_some() =>
+        {
+            let label = &token_string[..token_string.len() - 1];
+            Ok(Token::DatumReference(datum_label_number(label).unwrap()))
+        }
+        // rust also parses `inf` and `nan` as floats, they are symbols in scheme
+        _ if token_string.contains(|ch: char| ch.is_ascii_digit()) => Ok(token_string
+            .parse::<i64>()
+            .map(Token::Integer)
+            .or_else(|_| token_string.parse::<f64>().map(Token::Float))
+            .unwrap_or(Token::Symbol(token_string))),
+        _ => Ok(Token::Symbol(token_string)),
+    }
+}
+
+// returns `n` of the `#n` prefix of datum labels and references
+fn datum_label_number(token_string: &str) -> Option<u64> {
+    let digits = token_string.strip_prefix('#')?;
+    if digits.is_empty() || !digits.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Checks that `text` is read back as the symbol with the same name.
+pub fn reads_as_symbol(text: &str) -> bool {
+    let mut lexer = Lexer::new(text);
+    matches!(lexer.next(), Some(Ok(Token::Symbol(symbol))) if symbol == text)
+        && lexer.next().is_none()
+}
+
+fn consume_until_newline(chars: &mut SourceChars) -> String {
+    let mut comment = String::new();
+    while let Some(&ch) = chars.peek() {
+        if ch == '\n' {
+            break;
+        }
+        comment.push(ch);
+        chars.next();
+    }
+    comment
+}
+
+// lexes `#\\a`, `#\\space` and `#\\x41`, the first char after `#\\` is taken
+// even if it is a delimiter, so `#\\(` and `#\\ ` are chars too
+fn lex_char(chars: &mut SourceChars, fold_case: bool) -> LexResult {
+    chars.nth(1);
+    let first = chars.next().ok_or(LexicalError::UnexpectedEOF)?;
+    let mut name = first.to_string();
+    while let Some(&ch) = chars.peek() {
+        if is_delimiter(ch) {
+            break;
+        }
+        name.push(ch);
+        chars.next();
+    }
+    if name.chars().count() == 1 {
+        return Ok(Token::Char(first));
+    }
+    if fold_case {
+        name = name.to_lowercase();
+    }
+
+    match name.as_str() {
+        "alarm" => Ok(Token::Char('\x07')),
+        "backspace" => Ok(Token::Char('\x08')),
+        "delete" => Ok(Token::Char('\x7F')),
+        "escape" => Ok(Token::Char('\x1B')),
+        "newline" => Ok(Token::Char('\n')),
+        "null" => Ok(Token::Char('\0')),
+        "return" => Ok(Token::Char('\r')),
+        "space" => Ok(Token::Char(' ')),
+        "tab" => Ok(Token::Char('\t')),
+        _ => name
+            .strip_prefix('x')
+            .and_then(parse_hex_char)
+            .map(Token::Char)
+            .ok_or(LexicalError::UnexpectedChar),
+    }
+}
+
+fn parse_hex_char(hex: &str) -> Option<char> {
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+fn lex_string(chars: &mut SourceChars) -> LexResult {
+    chars.next();
+    lex_delimited(chars, '"', LexicalError::UnclosedString).map(Token::String)
+}
+
+fn lex_quoted_symbol(chars: &mut SourceChars) -> LexResult {
+    chars.next();
+    lex_delimited(chars, '|', LexicalError::UnclosedSymbol).map(Token::Symbol)
+}
+
+// reads text up to the closing `delimiter`, which is consumed, and replaces escapes
+fn lex_delimited(
+    chars: &mut SourceChars,
+    delimiter: char,
+    unclosed: LexicalError,
+) -> Result<String, LexicalError> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some(ch) if ch == delimiter => return Ok(text),
+            Some('\\') => {
+                let escaped = match chars.next() {
+                    Some('a') => '\x07',
+                    Some('b') => '\x08',
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some(ch @ ('"' | '\\' | '|')) => ch,
+                    Some('x') => lex_hex_escape(chars)?,
+                    Some(ch) if ch.is_whitespace() => {
+                        skip_line_continuation(chars, ch)?;
+                        continue;
+                    }
+                    Some(_) => return Err(LexicalError::InvalidEscape),
+                    None => return Err(unclosed),
+                };
+                text.push(escaped);
+            }
+            Some(ch) => text.push(ch),
+            None => return Err(unclosed),
+        }
+    }
+}
+
+// reads the hex digits and `;` of a `\\x41;` escape
+fn lex_hex_escape(chars: &mut SourceChars) -> Result<char, LexicalError> {
+    let mut hex = String::new();
+    loop {
+        match chars.next() {
+            Some(';') => return parse_hex_char(&hex).ok_or(LexicalError::InvalidEscape),
+            Some(ch) if ch.is_ascii_hexdigit() => hex.push(ch),
+            _ => return Err(LexicalError::InvalidEscape),
+        }
+    }
+}
+
+// skips `\\<intraline whitespace><newline><intraline whitespace>`, `first` is the char after `\\`
+fn skip_line_continuation(chars: &mut SourceChars, first: char) -> Result<(), LexicalError> {
+    let mut seen_newline = first == '\n';
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            '\n' if !seen_newline => seen_newline = true,
+            _ if ch.is_whitespace() && ch != '\n' => {}
+            _ => break,
+        }
+        chars.next();
+    }
+    if seen_newline {
+        Ok(())
+    } else {
+        Err(LexicalError::InvalidEscape)
+    }
+}
+
+// lexes `#| ... |#`, block comments can be nested
+fn lex_block_comment(chars: &mut SourceChars) -> LexResult {
+    chars.nth(1);
+    let mut comment = String::new();
+    let mut depth = 1;
+    while let Some(ch) = chars.next() {
+        match (ch, chars.peek()) {
+            ('|', Some('#')) => {
+                chars.next();
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(Token::Comment(comment));
+                }
+                comment.push_str("|#");
+            }
+            ('#', Some('|')) => {
+                chars.next();
+                depth += 1;
+                comment.push_str("#|");
+            }
+            _ => comment.push(ch),
+        }
+    }
+    Err(LexicalError::UnclosedComment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lex_complex() {
+        let lexer = Lexer::new("(cos (* 3.14159 1))");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("cos".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("*".to_string())),
+                #[allow(clippy::approx_constant)]
+                Ok(Token::Float(3.14159)),
+                Ok(Token::Integer(1)),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_quote() {
+        let lexer = Lexer::new("'(1 2 3)");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Quote),
+                Ok(Token::LParen),
+                Ok(Token::Integer(1)),
+                Ok(Token::Integer(2)),
+                Ok(Token::Integer(3)),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_long() {
+        let lexer = Lexer::new("(+ (* 3 (+ (* 2 4)  (+ 3 5))) (+ (- 10 7) 6))");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("*".to_string())),
+                Ok(Token::Integer(3)),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("*".to_string())),
+                Ok(Token::Integer(2)),
+                Ok(Token::Integer(4)),
+                Ok(Token::RParen),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::Integer(3)),
+                Ok(Token::Integer(5)),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("-".to_string())),
+                Ok(Token::Integer(10)),
+                Ok(Token::Integer(7)),
+                Ok(Token::RParen),
+                Ok(Token::Integer(6)),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_unexpected_eof() {
+        let lexer = Lexer::new("(+ 1 2");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::Integer(1)),
+                Ok(Token::Integer(2)),
+                Err(LexicalError::UnexpectedEOF)
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_string() {
+        let lexer = Lexer::new(r#"(display "Hello, world!")"#);
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("display".to_string())),
+                Ok(Token::String("Hello, world!".to_string())),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_unclosed_string() {
+        let lexer = Lexer::new(r#"(display "hello)"#);
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("display".to_string())),
+                Err(LexicalError::UnclosedString),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_unexpected_close_paren() {
+        let lexer = Lexer::new("(+ 1 2))");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::Integer(1)),
+                Ok(Token::Integer(2)),
+                Ok(Token::RParen),
+                Err(LexicalError::UnexpectedRParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_void() {
+        let lexer = Lexer::new("");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(tokens, vec![]);
+    }
+
+    #[test]
+    fn lex_two_parens() {
+        let lexer = Lexer::new(
+            "(define pi 314)
+                                                    (+ pi 1)",
+        );
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("define".to_string())),
+                Ok(Token::Symbol("pi".to_string())),
+                Ok(Token::Integer(314)),
+                Ok(Token::RParen),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("+".to_string())),
+                Ok(Token::Symbol("pi".to_string())),
+                Ok(Token::Integer(1)),
+                Ok(Token::RParen)
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_boolean() {
+        let lexer = Lexer::new("(not #f)");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("not".to_string())),
+                Ok(Token::Boolean(false)),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_char() {
+        let lexer = Lexer::new("(not #\\e)");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("not".to_string())),
+                Ok(Token::Char('e')),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_char_literal() {
+        let lexer = Lexer::new("(not #\\alarm)");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("not".to_string())),
+                Ok(Token::Char('\x07')),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_dot() {
+        let lexer = Lexer::new("'(3 . 4)");
+        let tokens: Vec<_> = lexer.collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Quote),
+                Ok(Token::LParen),
+                Ok(Token::Integer(3)),
+                Ok(Token::Dot),
+                Ok(Token::Integer(4)),
+                Ok(Token::RParen)
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_multiple_comments() {
+        let lexer = Lexer::new("; this is a comment\n; this is another comment");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Comment(" this is a comment".to_string())),
+                Ok(Token::Comment(" this is another comment".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_unquote_splicing() {
+        let lexer = Lexer::new(",@(quote (1 2 3))");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::UnquoteSplicing),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("quote".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Integer(1)),
+                Ok(Token::Integer(2)),
+                Ok(Token::Integer(3)),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_unquote() {
+        let lexer = Lexer::new(",(quote (1 2 3))");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Unquote),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("quote".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Integer(1)),
+                Ok(Token::Integer(2)),
+                Ok(Token::Integer(3)),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_quasiquote() {
+        let lexer = Lexer::new("`(lambda (x) (* x x))");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Quasiquote),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("lambda".to_string())),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("x".to_string())),
+                Ok(Token::RParen),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("*".to_string())),
+                Ok(Token::Symbol("x".to_string())),
+                Ok(Token::Symbol("x".to_string())),
+                Ok(Token::RParen),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_datum_labels() {
+        let lexer = Lexer::new("(#0=(a) #0# #12=b #a=)");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::DatumLabel(0)),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("a".to_string())),
+                Ok(Token::RParen),
+                Ok(Token::DatumReference(0)),
+                Ok(Token::DatumLabel(12)),
+                Ok(Token::Symbol("b".to_string())),
+                Ok(Token::Symbol("#a=".to_string())),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_block_comments() {
+        let lexer = Lexer::new("(a #| outer #| inner |# |# b)");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("a".to_string())),
+                Ok(Token::Comment(" outer #| inner |# ".to_string())),
+                Ok(Token::Symbol("b".to_string())),
+                Ok(Token::RParen),
+            ]
+        );
+
+        let tokens: Vec<_> = Lexer::new("#| unclosed").collect();
+        assert_eq!(tokens, vec![Err(LexicalError::UnclosedComment)]);
+    }
+
+    #[test]
+    fn lex_datum_comment() {
+        let lexer = Lexer::new("#;(a) b");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::DatumComment),
+                Ok(Token::LParen),
+                Ok(Token::Symbol("a".to_string())),
+                Ok(Token::RParen),
+                Ok(Token::Symbol("b".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_string_escapes() {
+        let lexer = Lexer::new(
+            r#""a\tb\n\\ \"q\" \x41;\x3bb; \a\|" "line \
+              continued""#,
+        );
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::String("a\tb\n\\ \"q\" A\u{3bb} \x07|".to_string())),
+                Ok(Token::String("line continued".to_string())),
+            ]
+        );
+
+        let tokens: Vec<_> = Lexer::new(r#""\q""#).collect();
+        assert_eq!(tokens, vec![Err(LexicalError::InvalidEscape)]);
+        let tokens: Vec<_> = Lexer::new(r#""\x41""#).collect();
+        assert_eq!(tokens, vec![Err(LexicalError::InvalidEscape)]);
+    }
+
+    #[test]
+    fn lex_quoted_symbols() {
+        let lexer = Lexer::new(r"(|hello world| || |a\|b\x41;|)");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Symbol("hello world".to_string())),
+                Ok(Token::Symbol("".to_string())),
+                Ok(Token::Symbol("a|bA".to_string())),
+                Ok(Token::RParen),
+            ]
+        );
+
+        let tokens: Vec<_> = Lexer::new("|open").collect();
+        assert_eq!(tokens, vec![Err(LexicalError::UnclosedSymbol)]);
+    }
+
+    #[test]
+    fn lex_hex_and_delimiter_chars() {
+        let lexer = Lexer::new(r"(#\x41 #\x #\( #\) #\λ #\x3bb)");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::LParen),
+                Ok(Token::Char('A')),
+                Ok(Token::Char('x')),
+                Ok(Token::Char('(')),
+                Ok(Token::Char(')')),
+                Ok(Token::Char('λ')),
+                Ok(Token::Char('\u{3bb}')),
+                Ok(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_long_booleans() {
+        let lexer = Lexer::new("#true #false");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![Ok(Token::Boolean(true)), Ok(Token::Boolean(false))]
+        );
+    }
+
+    #[test]
+    fn lex_fold_case() {
+        let lexer = Lexer::new("Abc #!fold-case Abc #\\SPACE #\\A |Abc| #!no-fold-case Abc #!eof");
+        let tokens: Vec<_> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Symbol("Abc".to_string())),
+                Ok(Token::Symbol("abc".to_string())),
+                Ok(Token::Char(' ')),
+                Ok(Token::Char('A')),
+                Ok(Token::Symbol("Abc".to_string())),
+                Ok(Token::Symbol("Abc".to_string())),
+                Ok(Token::Symbol("#!eof".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_special_floats() {
+        let tokens: Vec<_> = Lexer::new("+inf.0 -inf.0 inf nan").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Float(f64::INFINITY)),
+                Ok(Token::Float(f64::NEG_INFINITY)),
+                Ok(Token::Symbol("inf".to_string())),
+                Ok(Token::Symbol("nan".to_string())),
+            ]
+        );
+        let tokens: Vec<_> = Lexer::new("+nan.0").collect();
+        assert!(matches!(tokens[..], [Ok(Token::Float(float))] if float.is_nan()));
+    }
+}
@@ -0,0 +1,20 @@
+//! Reader of Scheme code for LispDM.
+//!
+//! Code is read into [`Datum`]s, which are converted into expressions by `lispdm`
+//! at runtime and by `lispdm-macros` at compile time.
+//!
+//! ```
+//! use lispdm_parser::{parse_str, Datum};
+//! let data = parse_str("(+ 1 2)").unwrap();
+//! let list = data[0].as_list().unwrap();
+//! assert_eq!(list.items[1], Datum::Integer(1));
+//! ```
+mod datum;
+mod lexer;
+mod parser;
+mod span;
+
+pub use datum::{Datum, List};
+pub use lexer::{reads_as_symbol, LexResult, Lexer, LexicalError, SpannedLexResult, Token};
+pub use parser::{parse_str, parse_str_with_file, ParseError, ParseResult, Parser};
+pub use span::Span;
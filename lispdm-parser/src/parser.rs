@@ -0,0 +1,633 @@
+use crate::{
+    datum::{Datum, List},
+    lexer::{LexResult, Lexer, LexicalError, SpannedLexResult, Token},
+    span::Span,
+};
+use std::{collections::HashMap, iter::Peekable};
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    LexError(LexicalError),
+    UnexpectedToken(Token),
+    UndefinedDatumLabel(u64),
+    /// Label of only a reference to itself, e.g. `#0=#0#`, which does not label any datum.
+    DatumLabelOfItself(u64),
+    /// Error at the given location in source code.
+    Located(Span, Box<ParseError>),
+}
+
+impl ParseError {
+    fn unexpected_eof() -> Self {
+        ParseError::UnexpectedToken(Token::Eof)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::LexError(err) => write!(f, "lex error: {}", err),
+            ParseError::UnexpectedToken(found) => {
+                write!(f, "unexpected token: {:?}", found)
+            }
+            ParseError::UndefinedDatumLabel(label) => {
+                write!(f, "reference to undefined datum label #{}#", label)
+            }
+            ParseError::DatumLabelOfItself(label) => {
+                write!(
+                    f,
+                    "datum label #{}= labels only a reference to itself",
+                    label
+                )
+            }
+            ParseError::Located(span, err) => write!(f, "{}: {}", span, err),
+        }
+    }
+}
+
+pub type ParseResult = Result<Datum, ParseError>;
+
+// what is read from tokens, comments are read as nothing
+enum Item {
+    Datum(Datum),
+    Comment,
+    Eof,
+}
+
+// when less stack than this is left, parsing continues on a new stack segment
+const STACK_RED_ZONE: usize = 64 * 1024;
+// size of stack segments allocated on the heap
+const STACK_SEGMENT_SIZE: usize = 1024 * 1024;
+
+pub struct Parser<I: Iterator> {
+    tokens: Peekable<I>,
+    // labels of the current top-level datum, `false` while the labeled datum is still being parsed
+    labels: HashMap<u64, bool>,
+    // location of the last token
+    span: Option<Span>,
+}
+
+impl<I: Iterator<Item = SpannedLexResult>> Parser<I> {
+    pub fn new(tokens: I) -> Self {
+        Self {
+            tokens: tokens.peekable(),
+            labels: HashMap::new(),
+            span: None,
+        }
+    }
+
+    /// Parses the next top-level datum, comments before it are skipped
+    /// and datum labels are not shared between them.
+    ///
+    /// Errors are located at the token where parsing failed, if tokens have locations.
+    pub fn parse_datum(&mut self) -> Option<ParseResult> {
+        self.labels.clear();
+        let result = loop {
+            match self.parse_item() {
+                Ok(Item::Datum(datum)) => break Ok(datum),
+                Ok(Item::Comment) => {}
+                Ok(Item::Eof) => return None,
+                Err(err) => break Err(err),
+            }
+        };
+        Some(result.map_err(|err| match self.span.clone() {
+            Some(span) => ParseError::Located(span, Box::new(err)),
+            None => err,
+        }))
+    }
+
+    fn next_token(&mut self) -> Option<LexResult> {
+        let (token, span) = self.tokens.next()?;
+        self.span = span;
+        Some(token)
+    }
+
+    fn parse_item(&mut self) -> Result<Item, ParseError> {
+        let token = match self.next_token() {
+            Some(token) => token.map_err(ParseError::LexError)?,
+            None => return Ok(Item::Eof),
+        };
+        let datum = match token {
+            Token::Comment(_) => return Ok(Item::Comment),
+            // `#;` comments out the next datum, comments before it are skipped too,
+            // so `#; #; a b` comments out both `a` and `b`
+            Token::DatumComment => {
+                self.parse_next()?;
+                return Ok(Item::Comment);
+            }
+            Token::Boolean(boolean) => Datum::Boolean(boolean),
+            Token::String(string) => Datum::String(string),
+            Token::Symbol(symbol) => Datum::Symbol(symbol),
+            Token::Integer(int) => Datum::Integer(int),
+            Token::Float(float) => Datum::Float(float),
+            Token::Char(char) => Datum::Char(char),
+            Token::LParen => {
+                // list forms keep location of their opening paren
+                let span = self.span.clone();
+                // nested lists are parsed recursively, so deep nesting grows the stack on the heap
+                let mut list =
+                    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || self.parse_list())?;
+                list.span = span;
+                Datum::List(list)
+            }
+            // we consume right paren in `parse_list`, so seeing a right paren here is an error
+            Token::RParen => return Err(ParseError::LexError(LexicalError::UnexpectedRParen)),
+            // transform quotation tokens into quotation calls
+            Token::Quote => self.parse_quotation("quote")?,
+            Token::Quasiquote => self.parse_quotation("quasiquote")?,
+            Token::Unquote => self.parse_quotation("unquote")?,
+            Token::UnquoteSplicing => self.parse_quotation("unquote-splicing")?,
+            // we handle dot in `parse_list`, so seeing a dot here is an error
+            Token::Dot => return Err(ParseError::UnexpectedToken(Token::Dot)),
+            Token::DatumLabel(label) => self.parse_labeled(label)?,
+            // labels, which are still being parsed, are referred to by cyclic data
+            Token::DatumReference(label) => match self.labels.contains_key(&label) {
+                true => Datum::Reference(label),
+                false => return Err(ParseError::UndefinedDatumLabel(label)),
+            },
+            Token::Eof => return Ok(Item::Eof),
+        };
+        Ok(Item::Datum(datum))
+    }
+
+    // parses the next datum inside of another one, so it has to be there
+    fn parse_next(&mut self) -> ParseResult {
+        loop {
+            match self.parse_item()? {
+                Item::Datum(datum) => return Ok(datum),
+                Item::Comment => {}
+                Item::Eof => return Err(ParseError::unexpected_eof()),
+            }
+        }
+    }
+
+    fn parse_quotation(&mut self, symbol: &str) -> ParseResult {
+        let quoted = self.parse_next()?;
+        let items = vec![Datum::Symbol(symbol.to_string()), quoted];
+        Ok(Datum::List(List::new(items, None)))
+    }
+
+    fn parse_labeled(&mut self, label: u64) -> ParseResult {
+        self.labels.insert(label, false);
+        let datum = self.parse_next()?;
+        if let Datum::Reference(reference) = datum {
+            if self.labels.get(&reference) == Some(&false) {
+                return Err(ParseError::DatumLabelOfItself(label));
+            }
+        }
+        self.labels.insert(label, true);
+        Ok(Datum::Labeled(label, Box::new(datum)))
+    }
+
+    fn parse_list(&mut self) -> Result<List, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            let peek_result = self
+                .tokens
+                .peek()
+                .map(|(token, _)| token)
+                .ok_or(ParseError::unexpected_eof())?;
+            let tok = match peek_result {
+                Ok(tok) => tok,
+                // if peeked token is an error, get next token and return the error
+                // unwrap is safe because we just checked that peek_result is Some and is an error
+                Err(_) => {
+                    return Err(ParseError::LexError(
+                        self.next_token().unwrap().unwrap_err(),
+                    ))
+                }
+            };
+            match tok {
+                // if we see a right paren, we're done parsing the list
+                Token::RParen => {
+                    // consume the right paren
+                    self.next_token();
+                    break;
+                }
+                // if we see a dot, we're parsing a dotted list
+                // or proper list (dotted list with null (empty list) at the end)
+                Token::Dot => {
+                    // consume the dot
+                    self.next_token();
+                    let tail = self.parse_next()?;
+
+                    if self.next_token() != Some(Ok(Token::RParen)) {
+                        return Err(ParseError::UnexpectedToken(Token::Dot));
+                    }
+
+                    return Ok(match tail {
+                        Datum::List(mut tail) => {
+                            items.append(&mut tail.items);
+                            List::new(items, tail.tail.take().map(|tail| *tail))
+                        }
+                        tail => List::new(items, Some(tail)),
+                    });
+                }
+                // otherwise, parse the next item and add it to the list
+                _ => match self.parse_item()? {
+                    Item::Datum(datum) => items.push(datum),
+                    // skip comments
+                    Item::Comment => {}
+                    // if we hit EOF, we're missing a right paren
+                    Item::Eof => return Err(ParseError::unexpected_eof()),
+                },
+            }
+        }
+
+        Ok(List::new(items, None))
+    }
+}
+
+impl<I: Iterator<Item = SpannedLexResult>> Iterator for Parser<I> {
+    type Item = ParseResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parse_datum()
+    }
+}
+
+/// Parses all data in `string`.
+pub fn parse_str(string: &str) -> Result<Vec<Datum>, ParseError> {
+    Parser::new(Lexer::new(string).spanned()).collect()
+}
+
+/// Parses source code read from `file`, so list forms and errors are located in it.
+pub fn parse_str_with_file(string: &str, file: &str) -> Result<Vec<Datum>, ParseError> {
+    Parser::new(Lexer::with_file(string, file).spanned()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<I: Iterator<Item = LexResult>>(tokens: Peekable<I>) -> Result<Vec<Datum>, ParseError> {
+        Parser::new(tokens.map(|token| (token, None))).collect()
+    }
+
+    fn list(items: Vec<Datum>) -> Datum {
+        Datum::List(List::new(items, None))
+    }
+
+    fn sym(symbol: &str) -> Datum {
+        Datum::Symbol(symbol.to_string())
+    }
+
+    fn string(string: &str) -> Datum {
+        Datum::String(string.to_string())
+    }
+
+    fn located(line: usize, column: usize, err: ParseError) -> ParseError {
+        ParseError::Located(Span::new(None, line, column), Box::new(err))
+    }
+
+    #[test]
+    fn parse_complex() {
+        let lexer = Lexer::new("(cos (* 3.14159 1))");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("cos".to_string()),
+                list(vec![
+                    Datum::Symbol("*".to_string()),
+                    #[allow(clippy::approx_constant)]
+                    Datum::Float(3.14159),
+                    Datum::Integer(1),
+                ]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_quote() {
+        let lexer = Lexer::new("'(1 2 3)");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("quote".to_string()),
+                list(vec![
+                    Datum::Integer(1),
+                    Datum::Integer(2),
+                    Datum::Integer(3)
+                ]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_long() {
+        let lexer = Lexer::new("(+ (* 3 (+ (* 2 4)  (+ 3 5))) (+ (- 10 7) 6))");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("+".to_string()),
+                list(vec![
+                    Datum::Symbol("*".to_string()),
+                    Datum::Integer(3),
+                    list(vec![
+                        Datum::Symbol("+".to_string()),
+                        list(vec![
+                            Datum::Symbol("*".to_string()),
+                            Datum::Integer(2),
+                            Datum::Integer(4),
+                        ]),
+                        list(vec![
+                            Datum::Symbol("+".to_string()),
+                            Datum::Integer(3),
+                            Datum::Integer(5),
+                        ]),
+                    ]),
+                ]),
+                list(vec![
+                    Datum::Symbol("+".to_string()),
+                    list(vec![
+                        Datum::Symbol("-".to_string()),
+                        Datum::Integer(10),
+                        Datum::Integer(7),
+                    ]),
+                    Datum::Integer(6),
+                ]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_unexpected_eof() {
+        let lexer = Lexer::new("(+ 1 2");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer);
+        assert_eq!(
+            parsed,
+            Err(ParseError::LexError(LexicalError::UnexpectedEOF))
+        );
+    }
+
+    #[test]
+    fn parse_string() {
+        let lexer = Lexer::new(r#"(display "Hello, world!")"#);
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("display".to_string()),
+                string("Hello, world!"),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_unclosed_string() {
+        let lexer = Lexer::new(r#"(display "hello)"#);
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer);
+        assert_eq!(
+            parsed,
+            Err(ParseError::LexError(LexicalError::UnclosedString))
+        );
+    }
+
+    #[test]
+    fn parse_unexpected_close_paren() {
+        let lexer = Lexer::new("(+ 1 2))");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer);
+        assert_eq!(
+            parsed,
+            Err(ParseError::LexError(LexicalError::UnexpectedRParen))
+        );
+    }
+
+    #[test]
+    fn parse_void() {
+        let lexer = Lexer::new("");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(parsed, vec![]);
+    }
+
+    #[test]
+    fn parse_two_parens() {
+        let lexer = Lexer::new(
+            "(define pi 314)
+        (+ pi 1)",
+        );
+        let tokens: Vec<_> = lexer.collect();
+        let answer1 = tokens.into_iter().peekable();
+        let parsed1 = parse(answer1).unwrap();
+        assert_eq!(
+            parsed1,
+            vec![
+                list(vec![
+                    Datum::Symbol("define".to_string()),
+                    Datum::Symbol("pi".to_string()),
+                    Datum::Integer(314),
+                ]),
+                list(vec![
+                    Datum::Symbol("+".to_string()),
+                    Datum::Symbol("pi".to_string()),
+                    Datum::Integer(1),
+                ])
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_boolean() {
+        let lexer = Lexer::new("(not #t)");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("not".to_string()),
+                Datum::Boolean(true),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_char() {
+        let lexer = Lexer::new("(not #\\e)");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("not".to_string()),
+                Datum::Char('e'),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_comment() {
+        let lexer = Lexer::new("; this is a comment\n(+ 1 2)");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("+".to_string()),
+                Datum::Integer(1),
+                Datum::Integer(2),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_dotted_list() {
+        let lexer = Lexer::new("(f x . y)");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![Datum::List(List::new(
+                vec![
+                    Datum::Symbol("f".to_string()),
+                    Datum::Symbol("x".to_string()),
+                ],
+                Some(Datum::Symbol("y".to_string())),
+            ))]
+        );
+    }
+
+    #[test]
+    fn parse_quasiquote_with_unquote() {
+        let lexer = Lexer::new("`(list ,(+ 1 2) 4)");
+        let tokens: Vec<_> = lexer.collect();
+        let answer = tokens.into_iter().peekable();
+        let parsed = parse(answer).unwrap();
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("quasiquote".to_string()),
+                list(vec![
+                    Datum::Symbol("list".to_string()),
+                    list(vec![
+                        Datum::Symbol("unquote".to_string()),
+                        list(vec![
+                            Datum::Symbol("+".to_string()),
+                            Datum::Integer(1),
+                            Datum::Integer(2),
+                        ]),
+                    ]),
+                    Datum::Integer(4),
+                ]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_datum_labels() {
+        let parsed = parse_str("'(#0=(a \"b\") #0#)").unwrap();
+        let labeled = list(vec![Datum::Symbol("a".to_string()), string("b")]);
+        assert_eq!(
+            parsed,
+            vec![list(vec![
+                Datum::Symbol("quote".to_string()),
+                list(vec![
+                    Datum::Labeled(0, Box::new(labeled)),
+                    Datum::Reference(0)
+                ]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_dotted_list_with_list_tail() {
+        let parsed = parse_str("(a . (b . (c)))").unwrap();
+        assert_eq!(parsed, vec![list(vec![sym("a"), sym("b"), sym("c")])]);
+        let parsed = parse_str("(a . #0=(b))").unwrap();
+        let tail = Datum::Labeled(0, Box::new(list(vec![sym("b")])));
+        assert_eq!(
+            parsed,
+            vec![Datum::List(List::new(vec![sym("a")], Some(tail)))]
+        );
+    }
+
+    #[test]
+    fn parse_cyclic_datum_label() {
+        let parsed = parse_str("#0=(a . #0#)").unwrap();
+        let list = List::new(vec![sym("a")], Some(Datum::Reference(0)));
+        assert_eq!(parsed, vec![Datum::Labeled(0, Box::new(Datum::List(list)))]);
+        assert_eq!(
+            parse_str("#0=#0#"),
+            Err(located(1, 4, ParseError::DatumLabelOfItself(0)))
+        );
+        assert_eq!(
+            parse_str("(#0=#1=#0#)"),
+            Err(located(1, 8, ParseError::DatumLabelOfItself(1)))
+        );
+    }
+
+    #[test]
+    fn parse_undefined_datum_label() {
+        let parsed = parse_str("(a #1#)");
+        assert_eq!(
+            parsed,
+            Err(located(1, 4, ParseError::UndefinedDatumLabel(1)))
+        );
+    }
+
+    #[test]
+    fn parse_datum_labels_scoped_to_datum() {
+        let parsed = parse_str("#0=a #0#");
+        assert_eq!(
+            parsed,
+            Err(located(1, 6, ParseError::UndefinedDatumLabel(0)))
+        );
+    }
+
+    #[test]
+    fn parse_datum_comments() {
+        let parsed = parse_str("(a #;(b c) d #; #; e f) #;g").unwrap();
+        assert_eq!(parsed, vec![list(vec![sym("a"), sym("d"),])]);
+        assert!(parse_str("(a #;)").is_err());
+    }
+
+    #[test]
+    fn parse_list_spans() {
+        let parsed = parse_str_with_file("(a\n  (b))", "foo.scm").unwrap();
+        let outer = parsed[0].as_list().unwrap();
+        let span = outer.span.as_ref().unwrap();
+        assert_eq!(
+            (span.file(), span.line(), span.column()),
+            (Some("foo.scm"), 1, 1)
+        );
+        let inner = outer.items[1].as_list().unwrap();
+        assert_eq!(inner.span.as_ref().unwrap().to_string(), "foo.scm:2:3");
+        let quoted = parse_str("'a").unwrap();
+        assert_eq!(quoted[0].as_list().unwrap().span, None);
+    }
+
+    #[test]
+    fn parse_comments_before_quoted_datum() {
+        let parsed = parse_str("' ; comment\n #| block |# a").unwrap();
+        assert_eq!(parsed, vec![list(vec![sym("quote"), sym("a")])]);
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let err = parse_str("(a\n \"unclosed)").unwrap_err();
+        assert_eq!(err.to_string(), "2:2: lex error: unclosed string");
+    }
+}
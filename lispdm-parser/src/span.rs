@@ -0,0 +1,42 @@
+use std::{fmt, rc::Rc};
+
+/// Location of a token or a list form in source code.
+///
+/// Displayed as `file:line:column`, or `line:column` for source code that is not read from a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    file: Option<Rc<str>>,
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    /// Creates a new span, `line` and `column` start from 1.
+    pub fn new(file: Option<Rc<str>>, line: usize, column: usize) -> Self {
+        Self { file, line, column }
+    }
+
+    /// Returns name of the file, if source code was read from a file.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Returns line number, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns column number, starting from 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
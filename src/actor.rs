@@ -0,0 +1,560 @@
+//! Actors, i.e. engines running on their own threads, which exchange messages.
+//!
+//! Each actor has a mailbox and evaluates its code in its own [`Engine`].
+//! Messages are deep copies of data values, i.e. numbers, booleans, characters, strings,
+//! symbols, lists of them and actors. Procedures, ports and other foreign objects are not sent.
+//!
+//! Engines of actors define these procedures:
+//! - `(spawn code)` evaluates `code` in a new actor and returns it,
+//!   `code` is a quoted expression or a string of source code;
+//!   if evaluation fails, the actor sends `(exit actor message)` with the message of the error
+//!   to the actor, which spawned it;
+//! - `(send actor message)` sends a copy of `message` to `actor`,
+//!   messages sent to a stopped actor are dropped;
+//! - `(receive)` waits for the next message, `(receive seconds)` returns `#f` after the timeout,
+//!   which is a non-negative number;
+//! - `(self)` returns the actor, which evaluates the code.
+
+use crate::{
+    evaluator::{ErrorKind, LispDMError},
+    expr::{Expr, Exprs, Foreign, ForeignObject, FromExpr, FromExprResult},
+    Arity, Engine, ProcedureKind, ProcedureReturn, SendError,
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    fmt, panic,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+// waiting for a message is interrupted this often to check limits of the evaluation
+const RECEIVE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static NEXT_ACTOR_ID: AtomicU64 = AtomicU64::new(1);
+
+type Init = Arc<dyn Fn() -> Engine + Send + Sync>;
+
+// deep copy of a data value, which is sent between threads
+#[derive(Debug)]
+enum Datum {
+    Void,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Char(char),
+    String(String),
+    Symbol(String),
+    List(Vec<Datum>),
+    // the last element is the tail of the list
+    DottedList(Vec<Datum>),
+    Actor(Actor),
+}
+
+impl Datum {
+    fn copy(expr: &Expr) -> Result<Self, LispDMError> {
+        Ok(match expr {
+            Expr::Void => Datum::Void,
+            Expr::Boolean(boolean) => Datum::Boolean(*boolean),
+            Expr::Integer(integer) => Datum::Integer(*integer),
+            Expr::Float(float) => Datum::Float(*float),
+            Expr::Char(ch) => Datum::Char(*ch),
+            Expr::String(string) => Datum::String(string.borrow().clone()),
+            Expr::Symbol(symbol) => Datum::Symbol(symbol.clone()),
+            Expr::List(list) => {
+                let items = list.iter().map(Datum::copy).collect::<Result<_, _>>()?;
+                match list.is_proper() {
+                    true => Datum::List(items),
+                    false => Datum::DottedList(items),
+                }
+            }
+            Expr::Foreign(foreign) if foreign.is::<Actor>() => {
+                Datum::Actor(foreign.downcast_ref::<Actor>().unwrap().clone())
+            }
+            expr => {
+                return Err(LispDMError::new(ErrorKind::TypeMismatch(format!(
+                    "expected data value as message, got {}",
+                    expr.kind()
+                ))))
+            }
+        })
+    }
+
+    fn into_expr(self) -> Expr {
+        match self {
+            Datum::Void => Expr::Void,
+            Datum::Boolean(boolean) => Expr::Boolean(boolean),
+            Datum::Integer(integer) => Expr::Integer(integer),
+            Datum::Float(float) => Expr::Float(float),
+            Datum::Char(ch) => Expr::Char(ch),
+            Datum::String(string) => Expr::new_string(string),
+            Datum::Symbol(symbol) => Expr::new_symbol(symbol),
+            Datum::List(items) => {
+                Expr::new_proper_list(items.into_iter().map(Datum::into_expr).collect())
+            }
+            Datum::DottedList(items) => {
+                Expr::new_dotted_list(items.into_iter().map(Datum::into_expr).collect())
+            }
+            Datum::Actor(actor) => actor.into(),
+        }
+    }
+}
+
+/// Address of an actor, which is used to send messages to it.
+///
+/// In Scheme code, actors are foreign objects written as `#<actor id>`,
+/// which are `equal?` if they refer to the same actor.
+///
+/// # Examples
+/// ```
+/// use lispdm::{Actor, Engine, Expr, Mailbox};
+///
+/// let mailbox = Mailbox::new();
+/// let worker = Actor::spawn(Engine::default, "
+///     (let ((request (receive)))
+///       (send (car request) (* 2 (cadr request))))
+/// ");
+/// let request = vec![Expr::from(mailbox.actor()), Expr::from(21)];
+/// worker.actor().send(request).unwrap();
+/// assert_eq!(mailbox.receive::<i64>(), Ok(42));
+/// worker.join().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Actor {
+    id: u64,
+    mailbox: mpsc::Sender<Datum>,
+}
+
+impl Actor {
+    /// Evaluates the source code `src` in a new actor and returns a handle to it.
+    ///
+    /// The engine of the actor is created by `init` on the new thread,
+    /// and so are engines of actors, which are spawned by its code.
+    pub fn spawn<F>(init: F, src: &str) -> ActorHandle
+    where
+        F: Fn() -> Engine + Send + Sync + 'static,
+    {
+        spawn_actor(Arc::new(init), Code::Source(src.to_string()), None)
+    }
+
+    /// Returns the identifier of the actor, which is unique in the process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Sends a copy of `message` to the actor.
+    ///
+    /// Fails if `message` is not a data value, or if the actor has stopped.
+    pub fn send<T: Into<Expr>>(&self, message: T) -> Result<(), SendError> {
+        let datum = Datum::copy(&message.into())?;
+        match self.deliver(datum) {
+            true => Ok(()),
+            false => {
+                Err(LispDMError::new(ErrorKind::Runtime(format!("{} has stopped", self))).into())
+            }
+        }
+    }
+
+    // returns `false`, if the actor has stopped
+    fn deliver(&self, datum: Datum) -> bool {
+        self.mailbox.send(datum).is_ok()
+    }
+}
+
+impl PartialEq for Actor {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Actor {}
+
+impl fmt::Debug for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Actor").field(&self.id).finish()
+    }
+}
+
+impl fmt::Display for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#<actor {}>", self.id)
+    }
+}
+
+impl ForeignObject for Actor {
+    fn fmt_foreign(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+
+    fn equal_foreign(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Actor>() == Some(self)
+    }
+}
+
+impl From<Actor> for Expr {
+    fn from(actor: Actor) -> Self {
+        Expr::Foreign(Foreign::with_hooks(actor))
+    }
+}
+
+impl FromExpr for Actor {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        match &expr {
+            Expr::Foreign(foreign) => match foreign.downcast_ref::<Actor>() {
+                Some(actor) => Ok(actor.clone()),
+                None => Err(expr),
+            },
+            _ => Err(expr),
+        }
+    }
+
+    fn expected_kind() -> &'static str {
+        "actor"
+    }
+}
+
+/// Mailbox of the host, which receives messages from actors.
+///
+/// Its [`actor`](#method.actor) is the address, which actors use to send messages to the host.
+pub struct Mailbox {
+    actor: Actor,
+    receiver: mpsc::Receiver<Datum>,
+}
+
+impl Mailbox {
+    /// Creates an empty mailbox.
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let actor = Actor {
+            id: NEXT_ACTOR_ID.fetch_add(1, Ordering::Relaxed),
+            mailbox: sender,
+        };
+        Self { actor, receiver }
+    }
+
+    /// Returns the address of the mailbox.
+    pub fn actor(&self) -> Actor {
+        self.actor.clone()
+    }
+
+    /// Waits for the next message and converts it.
+    pub fn receive<R: FromExpr>(&self) -> FromExprResult<R> {
+        // the mailbox keeps its own sender, so it is never disconnected
+        let datum = self.receiver.recv().expect("mailbox is never disconnected");
+        R::from_expr(datum.into_expr())
+    }
+
+    /// Waits for the next message at most `timeout`, returns `None` if there is none.
+    pub fn receive_timeout<R: FromExpr>(&self, timeout: Duration) -> Option<FromExprResult<R>> {
+        let datum = self.receiver.recv_timeout(timeout).ok()?;
+        Some(R::from_expr(datum.into_expr()))
+    }
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Mailbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("actor", &self.actor)
+            .finish()
+    }
+}
+
+/// Handle to a running actor, which is returned by [`Actor::spawn`].
+///
+/// Dropping the handle does not stop the actor.
+#[derive(Debug)]
+pub struct ActorHandle {
+    actor: Actor,
+    thread: JoinHandle<Result<(), SendError>>,
+}
+
+impl ActorHandle {
+    /// Returns the address of the actor.
+    pub fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    /// Waits for the actor to finish evaluation of its code.
+    ///
+    /// Fails if evaluation failed. A panic in the actor is resumed on the current thread.
+    pub fn join(self) -> Result<(), SendError> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+enum Code {
+    Source(String),
+    Datum(Datum),
+}
+
+// failure of the actor is sent to its `parent`
+fn spawn_actor(init: Init, code: Code, parent: Option<Actor>) -> ActorHandle {
+    let mailbox = Mailbox::new();
+    let actor = mailbox.actor();
+    let this = actor.clone();
+    let thread = thread::Builder::new()
+        .name(format!("lispdm-actor-{}", actor.id))
+        .spawn(move || {
+            let mut engine = init();
+            let mailbox = Rc::new(RefCell::new(Some(mailbox)));
+            register_procedures(&mut engine, Rc::clone(&mailbox), init);
+            let result = match code {
+                Code::Source(src) => engine.eval::<Expr>(&src),
+                Code::Datum(datum) => engine.eval_exprs::<Expr>(Exprs::from([datum.into_expr()])),
+            };
+            // the engine may outlive the thread in reference cycles,
+            // so its mailbox is closed explicitly and later messages fail
+            mailbox.borrow_mut().take();
+            if let (Err(err), Some(parent)) = (&result, parent) {
+                parent.deliver(Datum::List(vec![
+                    Datum::Symbol("exit".to_string()),
+                    Datum::Actor(this),
+                    Datum::String(err.to_string()),
+                ]));
+            }
+            result.map(|_| ()).map_err(SendError::from)
+        })
+        .expect("failed to spawn thread of the actor");
+    ActorHandle { actor, thread }
+}
+
+fn register_procedures(engine: &mut Engine, mailbox: Rc<RefCell<Option<Mailbox>>>, init: Init) {
+    let actor = mailbox.borrow().as_ref().expect("mailbox is open").actor();
+    let parent = actor.clone();
+    engine.register("self", move || actor.clone());
+
+    engine.register(
+        "send",
+        |actor: Actor, message: Expr| -> Result<(), LispDMError> {
+            actor.deliver(Datum::copy(&message)?);
+            Ok(())
+        },
+    );
+
+    engine.register("spawn", move |code: Expr| -> Result<Actor, LispDMError> {
+        let code = match code {
+            Expr::String(src) => Code::Source(src.borrow().clone()),
+            code => Code::Datum(Datum::copy(&code)?),
+        };
+        Ok(spawn_actor(init.clone(), code, Some(parent.clone())).actor)
+    });
+
+    engine.register_closure(
+        "receive",
+        ProcedureKind::Procedure,
+        Arity::Range(0, 1),
+        move |args, env| {
+            let deadline = match args.front() {
+                None => None,
+                Some(Expr::Integer(seconds)) if *seconds >= 0 => {
+                    Some(Duration::from_secs(*seconds as u64))
+                }
+                Some(Expr::Float(seconds)) if *seconds >= 0.0 && seconds.is_finite() => {
+                    Some(Duration::from_secs_f64(*seconds))
+                }
+                Some(expr) => {
+                    return Err(LispDMError::new(ErrorKind::TypeMismatch(format!(
+                        "expected non-negative number as argument 1 of receive, got {}",
+                        expr.kind()
+                    ))))
+                }
+            }
+            .map(|timeout| Instant::now() + timeout);
+            let runtime = env.runtime();
+            loop {
+                let wait = match deadline {
+                    Some(deadline) => deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(RECEIVE_POLL_INTERVAL),
+                    None => RECEIVE_POLL_INTERVAL,
+                };
+                let received = match mailbox.borrow().as_ref() {
+                    Some(mailbox) => mailbox.receiver.recv_timeout(wait),
+                    None => {
+                        return Err(LispDMError::new(ErrorKind::Runtime(
+                            "mailbox of the actor is closed".to_string(),
+                        )))
+                    }
+                };
+                match received {
+                    Ok(datum) => return Ok(ProcedureReturn::Value(datum.into_expr())),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => unreachable!("mailbox keeps its sender"),
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Ok(ProcedureReturn::Value(Expr::Boolean(false)));
+                }
+                // waiting is interrupted same as evaluation
+                runtime.step()?;
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn actors_are_send() {
+        assert_send::<Actor>();
+        assert_send::<ActorHandle>();
+        assert_send::<Mailbox>();
+    }
+
+    #[test]
+    fn actors_exchange_data() {
+        let mailbox = Mailbox::new();
+        let worker = Actor::spawn(
+            Engine::default,
+            r#"
+            (define host (receive))
+            (let loop ()
+              (let ((message (receive)))
+                (if (equal? message 'stop)
+                    (send host (list 'stopped (self)))
+                    (begin
+                      (send host (list message "text" #\a 1.5 '(a . b)))
+                      (loop)))))
+            "#,
+        );
+        let actor = worker.actor().clone();
+        actor.send(mailbox.actor()).unwrap();
+        actor.send(vec![1, 2, 3]).unwrap();
+        assert_eq!(
+            mailbox.receive::<Expr>().unwrap().to_string(),
+            r#"((1 2 3) "text" #\a 1.5 (a . b))"#
+        );
+        actor.send(Expr::new_symbol("stop")).unwrap();
+        let reply = mailbox.receive::<Vec<Expr>>().unwrap();
+        assert_eq!(reply[1].to_string(), format!("#<actor {}>", actor.id()));
+        assert_eq!(Actor::from_expr(reply[1].clone()), Ok(actor.clone()));
+        worker.join().unwrap();
+
+        let err = actor.send(1).unwrap_err();
+        assert_eq!(
+            err.message(),
+            format!("runtime error: #<actor {}> has stopped", actor.id())
+        );
+    }
+
+    #[test]
+    fn actors_spawn_actors() {
+        let mailbox = Mailbox::new();
+        let worker = Actor::spawn(
+            Engine::default,
+            r#"
+            (define host (receive))
+            (define child
+              (spawn '(let ((request (receive)))
+                        (send (car request) (list 'pong (cadr request))))))
+            (send child (list (self) 1))
+            (send host (receive))
+            (define child (spawn "(send (receive) (+ 1 2))"))
+            (send child (self))
+            (send host (receive))
+            (send host (receive 0.01))
+            "#,
+        );
+        worker.actor().send(mailbox.actor()).unwrap();
+        assert_eq!(mailbox.receive::<Expr>().unwrap().to_string(), "(pong 1)");
+        assert_eq!(mailbox.receive::<i64>(), Ok(3));
+        assert_eq!(mailbox.receive::<bool>(), Ok(false));
+        worker.join().unwrap();
+        assert!(mailbox
+            .receive_timeout::<Expr>(Duration::from_millis(10))
+            .is_none());
+    }
+
+    #[test]
+    fn actors_report_failures_to_parent() {
+        let mailbox = Mailbox::new();
+        let worker = Actor::spawn(
+            Engine::default,
+            r#"
+            (define host (receive))
+            (define child (spawn '(car '())))
+            (send host (list child (receive)))
+            "#,
+        );
+        worker.actor().send(mailbox.actor()).unwrap();
+        let reply = mailbox.receive::<Vec<Expr>>().unwrap();
+        assert_eq!(
+            reply[1].to_string(),
+            format!(
+                "(exit {} \"runtime error: expected non-empty list for car\")",
+                reply[0]
+            )
+        );
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn actors_receive_with_non_negative_timeout() {
+        for timeout in ["-1", "-0.5", "+inf.0"] {
+            let worker = Actor::spawn(Engine::default, &format!("(receive {})", timeout));
+            let err = worker.join().unwrap_err();
+            assert!(
+                err.message()
+                    .contains("type error: expected non-negative number as argument 1 of receive"),
+                "{}",
+                err
+            );
+        }
+        let worker = Actor::spawn(Engine::default, "(receive 0)");
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn actors_send_only_data() {
+        let worker = Actor::spawn(Engine::default, "(send (self) (lambda (x) x))");
+        let err = worker.join().unwrap_err();
+        assert!(
+            err.message()
+                .ends_with("type error: expected data value as message, got procedure"),
+            "{}",
+            err
+        );
+
+        let mailbox = Mailbox::new();
+        let err = mailbox
+            .actor()
+            .send(Foreign::new(String::from("host value")))
+            .unwrap_err();
+        assert_eq!(
+            err.message(),
+            "type error: expected data value as message, got foreign"
+        );
+    }
+
+    #[test]
+    fn actors_are_limited() {
+        let worker = Actor::spawn(
+            || {
+                let mut engine = Engine::default();
+                engine.set_max_depth(100);
+                engine
+            },
+            "(receive 0.01) (define (f x) (+ 1 (f x))) (f 1)",
+        );
+        let err = worker.join().unwrap_err();
+        assert!(err.message().contains("recursion"), "{}", err);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::parser::Span;
+use std::{collections::VecDeque, fmt, rc::Rc};
+
+// innermost frames are kept, so the backtrace of a deep recursion stays small
+const MAX_FRAMES: usize = 64;
+// tail calls replace each other, only the most recent ones of each call are kept
+const MAX_TAIL_CALL_FRAMES: usize = 8;
+
+/// Call of a procedure, which was active when an error was raised.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    // shared with the procedure, so recording a tail call does not allocate
+    name: Option<Rc<str>>,
+    span: Option<Span>,
+    is_tail_call: bool,
+    skipped_tail_calls: usize,
+}
+
+impl Frame {
+    pub(crate) fn new(name: Option<Rc<str>>, span: Option<Span>) -> Self {
+        Self {
+            name,
+            span,
+            is_tail_call: false,
+            skipped_tail_calls: 0,
+        }
+    }
+
+    /// Returns name of the called procedure.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("anon")
+    }
+
+    /// Returns location of the call, if the call was parsed from source code.
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
+    /// Checks if the procedure was called in a tail position.
+    pub fn is_tail_call(&self) -> bool {
+        self.is_tail_call
+    }
+
+    /// Returns number of tail calls made after this call, which are not in the backtrace.
+    pub fn skipped_tail_calls(&self) -> usize {
+        self.skipped_tail_calls
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        if let Some(span) = &self.span {
+            write!(f, " at {}", span)?;
+        }
+        if self.is_tail_call {
+            write!(f, " (tail call)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Procedure calls that led to an error, innermost first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Backtrace {
+    frames: Vec<Frame>,
+    skipped_frames: usize,
+}
+
+impl Backtrace {
+    /// Returns recorded frames, innermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Returns number of outermost frames, which are not recorded.
+    pub fn skipped_frames(&self) -> usize {
+        self.skipped_frames
+    }
+
+    /// Checks if backtrace has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty() && self.skipped_frames == 0
+    }
+
+    pub(crate) fn push(&mut self, frame: Frame) {
+        if self.frames.len() < MAX_FRAMES {
+            self.frames.push(frame);
+        } else {
+            self.skipped_frames += 1 + frame.skipped_tail_calls;
+        }
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "backtrace (innermost call first):")?;
+        for frame in &self.frames {
+            if frame.skipped_tail_calls > 0 {
+                writeln!(f, "  ... {} tail calls", frame.skipped_tail_calls)?;
+            }
+            writeln!(f, "  {}", frame)?;
+        }
+        if self.skipped_frames > 0 {
+            writeln!(f, "  ... {} more calls", self.skipped_frames)?;
+        }
+        Ok(())
+    }
+}
+
+/// Procedure calls made by a single evaluation, the first call and then its tail calls.
+#[derive(Default)]
+pub(crate) struct CallChain {
+    first: Option<Frame>,
+    tail_calls: VecDeque<Frame>,
+    skipped_tail_calls: usize,
+}
+
+impl CallChain {
+    pub fn push(&mut self, mut frame: Frame) {
+        if self.first.is_none() {
+            self.first = Some(frame);
+            return;
+        }
+        frame.is_tail_call = true;
+        if self.tail_calls.len() == MAX_TAIL_CALL_FRAMES {
+            self.tail_calls.pop_front();
+            self.skipped_tail_calls += 1;
+        }
+        self.tail_calls.push_back(frame);
+    }
+
+    /// Adds calls of the chain to `backtrace`, the most recent first.
+    pub fn unwind_into(self, backtrace: &mut Backtrace) {
+        for frame in self.tail_calls.into_iter().rev() {
+            backtrace.push(frame);
+        }
+        if let Some(mut first) = self.first {
+            first.skipped_tail_calls = self.skipped_tail_calls;
+            backtrace.push(first);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_chain_keeps_recent_tail_calls() {
+        let mut calls = CallChain::default();
+        calls.push(Frame::new(Some("first".into()), None));
+        for i in 0..MAX_TAIL_CALL_FRAMES + 3 {
+            calls.push(Frame::new(Some(format!("tail{}", i).into()), None));
+        }
+        let mut backtrace = Backtrace::default();
+        calls.unwind_into(&mut backtrace);
+
+        let frames = backtrace.frames();
+        assert_eq!(frames.len(), MAX_TAIL_CALL_FRAMES + 1);
+        assert_eq!(
+            frames[0].name(),
+            format!("tail{}", MAX_TAIL_CALL_FRAMES + 2)
+        );
+        assert!(frames[0].is_tail_call());
+        let first = frames.last().unwrap();
+        assert_eq!(first.name(), "first");
+        assert!(!first.is_tail_call());
+        assert_eq!(first.skipped_tail_calls(), 3);
+    }
+
+    #[test]
+    fn backtrace_is_bounded() {
+        let mut backtrace = Backtrace::default();
+        for _ in 0..MAX_FRAMES + 10 {
+            backtrace.push(Frame::new(Some("f".into()), None));
+        }
+        assert_eq!(backtrace.frames().len(), MAX_FRAMES);
+        assert_eq!(backtrace.skipped_frames(), 10);
+    }
+}
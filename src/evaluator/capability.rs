@@ -0,0 +1,34 @@
+/// Access to the host, which can be granted to scripts.
+///
+/// Procedures that need a capability are not defined in engines, which are not allowed to use it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Reading files with `load`, `include`, `file-exists?` and input file ports.
+    FsRead,
+    /// Writing and deleting files with `delete-file` and output file ports.
+    FsWrite,
+    /// Reading environment variables and command line arguments.
+    Environment,
+    /// Requesting the host to exit with `exit` and `emergency-exit`.
+    Exit,
+    /// Reading from the standard input.
+    ///
+    /// Without it, the current input port is empty.
+    Stdin,
+    /// Writing to the standard output and error.
+    ///
+    /// Without it, output written to the current output and error ports is discarded.
+    Stdout,
+}
+
+impl Capability {
+    /// All capabilities, which are granted by default.
+    pub const ALL: [Capability; 6] = [
+        Capability::FsRead,
+        Capability::FsWrite,
+        Capability::Environment,
+        Capability::Exit,
+        Capability::Stdin,
+        Capability::Stdout,
+    ];
+}
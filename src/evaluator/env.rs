@@ -1,12 +1,21 @@
-use super::primitives::{
-    chars, convert, equal, eval, forms, io, lists, macros, nums, ports, strings, system, types,
+use super::{
+    capability::Capability,
+    primitives::{
+        chars, convert, equal, eval, forms, io, lists, macros, nums, ports, strings, system, types,
+    },
+    runtime::Runtime,
 };
 use crate::expr::{
-    Expr, FromExpr, FromExprResult, InputPortSuperTrait, OutputPortSuperTrait, Procedure,
-    StdinInputPort, StdoutOutputPort,
+    Expr, FromExpr, FromExprResult, InputPortSuperTrait, NullOutputPort, OutputPortSuperTrait,
+    Procedure, StderrOutputPort, StdinInputPort, StdoutOutputPort, StringInputPort,
 };
 use core::fmt;
-use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    rc::Rc,
+};
 
 #[derive(Debug, Clone)]
 struct Env {
@@ -16,6 +25,8 @@ struct Env {
     cwd: PathBuf,
     cur_input_port: Rc<RefCell<dyn InputPortSuperTrait>>,
     cur_output_port: Rc<RefCell<dyn OutputPortSuperTrait>>,
+    cur_error_port: Rc<RefCell<dyn OutputPortSuperTrait>>,
+    runtime: Rc<Runtime>,
 }
 
 impl PartialEq for Env {
@@ -26,18 +37,30 @@ impl PartialEq for Env {
             && self.cwd == other.cwd
             && Rc::ptr_eq(&self.cur_input_port, &other.cur_input_port)
             && Rc::ptr_eq(&self.cur_output_port, &other.cur_output_port)
+            && Rc::ptr_eq(&self.cur_error_port, &other.cur_error_port)
+            && Rc::ptr_eq(&self.runtime, &other.runtime)
     }
 }
 
 impl Default for Env {
     fn default() -> Self {
+        let cur_output_port: Rc<RefCell<dyn OutputPortSuperTrait>> =
+            Rc::new(RefCell::new(StdoutOutputPort::new()));
+        let cur_error_port: Rc<RefCell<dyn OutputPortSuperTrait>> =
+            Rc::new(RefCell::new(StderrOutputPort::new()));
+        let runtime = Runtime::default();
+        runtime.register_output_port(&cur_output_port);
+        runtime.register_output_port(&cur_error_port);
+
         Self {
             bindings: HashMap::new(),
             macros: HashMap::new(),
             parent: None,
             cwd: PathBuf::new(),
             cur_input_port: Rc::new(RefCell::new(StdinInputPort::new())),
-            cur_output_port: Rc::new(RefCell::new(StdoutOutputPort::new())),
+            cur_output_port,
+            cur_error_port,
+            runtime: Rc::new(runtime),
         }
     }
 }
@@ -47,11 +70,15 @@ impl Env {
         let cwd = parent.0.borrow().cwd.clone();
         let cur_input_port = parent.0.borrow().cur_input_port.clone();
         let cur_output_port = parent.0.borrow().cur_output_port.clone();
+        let cur_error_port = parent.0.borrow().cur_error_port.clone();
+        let runtime = parent.0.borrow().runtime.clone();
 
         Env {
             cwd,
             cur_input_port,
             cur_output_port,
+            cur_error_port,
+            runtime,
             bindings: HashMap::new(),
             macros: HashMap::new(),
             parent: Some(parent),
@@ -123,6 +150,7 @@ macro_rules! insert_procedures(
 /// Environment holds:
 /// - bindings of symbols to expressions.
 /// - current working directory.
+/// - current input, output and error ports.
 /// - reference to the parent environment.
 pub struct EnvRef(Rc<RefCell<Env>>);
 
@@ -165,7 +193,7 @@ impl EnvRef {
     }
 
     /// Returns the expression bound to `name`.
-
+    ///
     /// If the no expression is found, returns [`None`].
     pub fn get_expr(&self, name: &str) -> Option<Expr> {
         self.0.borrow().get(name)
@@ -220,6 +248,11 @@ impl EnvRef {
         self.0.borrow().cur_output_port.clone()
     }
 
+    /// Returns the current error port of the environment.
+    pub fn current_error_port(&self) -> Rc<RefCell<dyn OutputPortSuperTrait>> {
+        self.0.borrow().cur_error_port.clone()
+    }
+
     /// Sets the current input port of the environment.
     pub fn set_current_input_port(&mut self, port: Rc<RefCell<dyn InputPortSuperTrait>>) {
         self.0.borrow_mut().cur_input_port = port;
@@ -229,11 +262,87 @@ impl EnvRef {
     pub fn set_current_output_port(&mut self, port: Rc<RefCell<dyn OutputPortSuperTrait>>) {
         self.0.borrow_mut().cur_output_port = port;
     }
+
+    /// Sets the current error port of the environment.
+    pub fn set_current_error_port(&mut self, port: Rc<RefCell<dyn OutputPortSuperTrait>>) {
+        self.0.borrow_mut().cur_error_port = port;
+    }
+
+    /// Returns the state shared by all environments of the engine.
+    pub(crate) fn runtime(&self) -> Rc<Runtime> {
+        self.0.borrow().runtime.clone()
+    }
 }
 
 pub fn new_root_env() -> EnvRef {
+    new_sandboxed_root_env(&Capability::ALL.into_iter().collect(), None)
+}
+
+/// Creates a root environment, which defines only procedures allowed by `capabilities`.
+///
+/// If `root_dir` is given, files outside of it can not be accessed
+/// and it is used as the current working directory.
+pub fn new_sandboxed_root_env(
+    capabilities: &HashSet<Capability>,
+    root_dir: Option<PathBuf>,
+) -> EnvRef {
     let mut env = EnvRef::default();
-    env.set_cwd(std::env::current_dir().expect("failed to get current working directory"));
+    // paths are compared after resolving symlinks, so root directory is resolved too
+    let root_dir = root_dir.map(|dir| dir.canonicalize().unwrap_or(dir));
+    match root_dir {
+        Some(root_dir) => {
+            env.set_cwd(root_dir.clone());
+            env.runtime().set_root_dir(root_dir);
+        }
+        None => {
+            env.set_cwd(std::env::current_dir().expect("failed to get current working directory"))
+        }
+    }
+
+    if !capabilities.contains(&Capability::Stdin) {
+        env.set_current_input_port(Rc::new(RefCell::new(StringInputPort::new(String::new()))));
+    }
+    if !capabilities.contains(&Capability::Stdout) {
+        env.set_current_output_port(Rc::new(RefCell::new(NullOutputPort::new())));
+        env.set_current_error_port(Rc::new(RefCell::new(NullOutputPort::new())));
+    }
+
+    if capabilities.contains(&Capability::FsRead) {
+        insert_procedures! {
+            env,
+            system::include,
+            system::load,
+            system::file_exists,
+            ports::open_input_file,
+            ports::with_input_from_file,
+            ports::call_with_input_file,
+        }
+    }
+    if capabilities.contains(&Capability::FsWrite) {
+        insert_procedures! {
+            env,
+            system::delete_file,
+            ports::open_output_file,
+            ports::with_output_to_file,
+            ports::with_error_to_file,
+            ports::call_with_output_file,
+        }
+    }
+    if capabilities.contains(&Capability::Environment) {
+        insert_procedures! {
+            env,
+            system::command_line,
+            system::get_environment_variables,
+            system::get_environment_variable,
+        }
+    }
+    if capabilities.contains(&Capability::Exit) {
+        insert_procedures! {
+            env,
+            system::exit,
+            system::emergency_exit,
+        }
+    }
 
     insert_procedures! {
         env,
@@ -254,6 +363,7 @@ pub fn new_root_env() -> EnvRef {
         // evaluation
         eval::eval,
         eval::apply,
+        eval::dynamic_wind,
         // equivalence
         equal::eqv,
         equal::equal,
@@ -301,6 +411,7 @@ pub fn new_root_env() -> EnvRef {
         convert::string_to_symbol,
         // type checking
         types::is_pair,
+        types::is_list,
         types::is_number,
         types::is_symbol,
         types::is_string,
@@ -310,16 +421,9 @@ pub fn new_root_env() -> EnvRef {
         types::is_output_port,
         types::is_port,
         // system interaction
-        system::include,
-        system::load,
-        system::file_exists,
-        system::delete_file,
-        system::command_line,
-        system::exit,
         system::current_second,
-        system::get_environment_variables,
-        system::get_environment_variable,
         system::error,
+        system::raise,
         //strings
         strings::string_set,
         strings::string_eq,
@@ -349,23 +453,26 @@ pub fn new_root_env() -> EnvRef {
         chars::is_char_numeric,
         chars::digit_value,
         // ports
-        ports::open_input_file,
-        ports::open_output_file,
+        ports::open_input_string,
         ports::is_input_port,
         ports::is_output_port,
         ports::current_input_port,
         ports::current_output_port,
+        ports::current_error_port,
         ports::close_input_port,
         ports::close_output_port,
-        ports::with_input_from_file,
-        ports::with_output_to_file,
-        ports::call_with_input_file,
-        ports::call_with_output_file,
+        ports::flush_output_port,
+        ports::port_line,
+        ports::port_column,
+        ports::port_position,
+        ports::set_port_position,
         // io
         io::read,
         io::read_char,
         io::read_string,
         io::write,
+        io::write_shared,
+        io::write_simple,
         io::write_char,
         io::write_string,
         io::display,
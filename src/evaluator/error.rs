@@ -1,33 +1,253 @@
+use super::backtrace::{Backtrace, CallChain, Frame};
+use crate::{
+    expr::{Arity, Expr, Printer, WriteMode},
+    parser::{ParseError, Span},
+};
+use std::fmt;
+
+/// Kind of an error, so host code can tell errors apart without parsing messages.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// Source code can not be parsed.
+    Parse(String),
+    /// Variable is not bound in the environment.
+    UnboundVariable(String),
+    /// Procedure is called with a wrong number of arguments.
+    Arity {
+        /// Name of the called procedure.
+        procedure: String,
+        /// Number of arguments the procedure accepts.
+        expected: Arity,
+        /// Number of passed arguments.
+        got: usize,
+    },
+    /// Value of an unexpected type is passed to a procedure or a special form.
+    TypeMismatch(String),
+    /// Reading or writing a file or a port failed.
+    Io(String),
+    /// Script is not allowed to access a resource, e.g. a file outside of the root directory.
+    PermissionDenied(String),
+    /// Object raised with `raise`, which was not handled.
+    Raise(Expr),
+    /// Evaluations are nested deeper than the limit, e.g. by a non-tail recursion.
+    RecursionDepthExceeded(usize),
+    /// Evaluation spent all of its fuel, see [`Limits::with_fuel`](crate::Limits::with_fuel).
+    OutOfFuel,
+    /// Evaluation did not finish before its deadline,
+    /// see [`Limits::with_deadline`](crate::Limits::with_deadline).
+    DeadlineExceeded,
+    /// Evaluation was interrupted with [`InterruptHandle`](crate::InterruptHandle).
+    Interrupted,
+    /// Program requested to exit with `exit`, carries the exit status.
+    ///
+    /// Handlers of `dynamic-wind` are run and output ports are flushed before it is returned.
+    Exit(i32),
+    /// Program requested to exit with `emergency-exit`, carries the exit status.
+    ///
+    /// Handlers of `dynamic-wind` are not run.
+    EmergencyExit(i32),
+    /// Any other error.
+    Runtime(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Parse(err) => write!(f, "parse error: {}", err),
+            ErrorKind::UnboundVariable(name) => write!(f, "unbound variable: {}", name),
+            ErrorKind::Arity {
+                procedure,
+                expected,
+                got,
+            } => write!(
+                f,
+                "arity error: expected {} arguments for {}, got {}",
+                expected, procedure, got
+            ),
+            ErrorKind::TypeMismatch(err) => write!(f, "type error: {}", err),
+            ErrorKind::Io(err) => write!(f, "I/O error: {}", err),
+            ErrorKind::PermissionDenied(err) => write!(f, "permission denied: {}", err),
+            ErrorKind::Raise(obj) => {
+                write!(f, "uncaught raise: {}", Printer::new(obj, WriteMode::Write))
+            }
+            ErrorKind::RecursionDepthExceeded(limit) => {
+                write!(f, "maximum recursion depth exceeded (limit is {})", limit)
+            }
+            ErrorKind::OutOfFuel => write!(f, "evaluation ran out of fuel"),
+            ErrorKind::DeadlineExceeded => write!(f, "evaluation exceeded its deadline"),
+            ErrorKind::Interrupted => write!(f, "evaluation interrupted"),
+            ErrorKind::Exit(status) => write!(f, "exit requested with status {}", status),
+            ErrorKind::EmergencyExit(status) => {
+                write!(f, "emergency exit requested with status {}", status)
+            }
+            ErrorKind::Runtime(err) => write!(f, "runtime error: {}", err),
+        }
+    }
+}
+
+/// Error that represents all possible errors in LispDM.
+///
+/// Host procedures can return it too, it can be created from [`ErrorKind`] or from a message.
+///
+/// # Examples
+/// ```
+/// use lispdm::{Engine, ErrorKind};
+/// let mut engine = Engine::default();
+/// let err = engine.eval::<()>("(define x 1)\n(+ x y)").unwrap_err();
+/// assert_eq!(err.kind(), &ErrorKind::UnboundVariable("y".to_string()));
+/// let span = err.span().unwrap();
+/// assert_eq!((span.line(), span.column()), (2, 1));
+/// assert_eq!(err.to_string(), "2:1: unbound variable: y");
+/// ```
 #[derive(Debug, PartialEq)]
-pub enum EvalError {
-    RuntimeError(String),
+pub struct LispDMError {
+    // boxed to keep results returned by the evaluator small
+    inner: Box<ErrorInner>,
 }
 
-impl std::error::Error for EvalError {}
+#[derive(Debug, PartialEq)]
+struct ErrorInner {
+    kind: ErrorKind,
+    span: Option<Span>,
+    backtrace: Backtrace,
+}
 
-impl std::fmt::Display for EvalError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            EvalError::RuntimeError(err) => write!(f, "runtime error: {}", err),
+impl LispDMError {
+    /// Creates a new error of `kind` without location and backtrace.
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            inner: Box::new(ErrorInner {
+                kind,
+                span: None,
+                backtrace: Backtrace::default(),
+            }),
+        }
+    }
+
+    /// Returns kind of the error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner.kind
+    }
+
+    /// Converts error into its kind, e.g. to take the object raised with `raise`.
+    pub fn into_kind(self) -> ErrorKind {
+        self.inner.kind
+    }
+
+    /// Returns location of the error in source code, if it is known.
+    ///
+    /// Evaluation errors are located at the innermost list form, which was evaluated.
+    pub fn span(&self) -> Option<&Span> {
+        self.inner.span.as_ref()
+    }
+
+    /// Returns procedure calls that led to the error.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::Engine;
+    /// let mut engine = Engine::default();
+    /// let source = "(define (f x) (car x))\n(define (g x) (+ 1 (f x)))\n(g 1)";
+    /// let err = engine.eval::<()>(source).unwrap_err();
+    /// let names: Vec<_> = err
+    ///     .backtrace()
+    ///     .frames()
+    ///     .iter()
+    ///     .map(|frame| frame.name())
+    ///     .collect();
+    /// assert_eq!(names, ["car", "f", "g"]);
+    /// ```
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.inner.backtrace
+    }
+
+    /// Locates error at `span`, unless a more precise location is already known.
+    pub(crate) fn with_span(mut self, span: Option<&Span>) -> Self {
+        if self.inner.span.is_none() {
+            self.inner.span = span.cloned();
         }
+        self
+    }
+
+    pub(crate) fn with_frame(mut self, frame: Frame) -> Self {
+        self.inner.backtrace.push(frame);
+        self
+    }
+
+    pub(crate) fn with_call_chain(mut self, calls: CallChain) -> Self {
+        calls.unwind_into(&mut self.inner.backtrace);
+        self
+    }
+}
+
+/// Errors are raised by the evaluator as [`LispDMError`].
+pub type EvalError = LispDMError;
+
+impl std::error::Error for LispDMError {}
+
+impl fmt::Display for LispDMError {
+    // backtrace is not included, it spans several lines
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(span) = &self.inner.span {
+            write!(f, "{}: ", span)?;
+        }
+        write!(f, "{}", self.inner.kind)
+    }
+}
+
+impl From<ErrorKind> for LispDMError {
+    fn from(kind: ErrorKind) -> Self {
+        LispDMError::new(kind)
     }
 }
 
-impl From<String> for EvalError {
+impl From<ParseError> for LispDMError {
+    fn from(err: ParseError) -> Self {
+        match err {
+            ParseError::Located(span, err) => {
+                LispDMError::new(ErrorKind::Parse(err.to_string())).with_span(Some(&span))
+            }
+            err => LispDMError::new(ErrorKind::Parse(err.to_string())),
+        }
+    }
+}
+
+impl From<String> for LispDMError {
     fn from(err: String) -> Self {
-        EvalError::RuntimeError(err)
+        LispDMError::new(ErrorKind::Runtime(err))
     }
 }
 
-impl From<&str> for EvalError {
+impl From<&str> for LispDMError {
     fn from(err: &str) -> Self {
-        EvalError::RuntimeError(err.to_string())
+        LispDMError::new(ErrorKind::Runtime(err.to_string()))
     }
 }
 
 macro_rules! runtime_error {
     ($($arg:tt)*) => (
-        crate::evaluator::error::EvalError::RuntimeError(format!($($arg)*))
+        crate::evaluator::error::LispDMError::new(
+            crate::evaluator::error::ErrorKind::Runtime(format!($($arg)*))
+        )
     )
 }
 pub(super) use runtime_error;
+
+macro_rules! type_error {
+    ($($arg:tt)*) => (
+        crate::evaluator::error::LispDMError::new(
+            crate::evaluator::error::ErrorKind::TypeMismatch(format!($($arg)*))
+        )
+    )
+}
+pub(super) use type_error;
+
+macro_rules! io_error {
+    ($($arg:tt)*) => (
+        crate::evaluator::error::LispDMError::new(
+            crate::evaluator::error::ErrorKind::Io(format!($($arg)*))
+        )
+    )
+}
+pub(super) use io_error;
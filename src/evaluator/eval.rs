@@ -1,31 +1,37 @@
 use super::{
+    backtrace::{CallChain, Frame},
     env::EnvRef,
-    error::{runtime_error, EvalError},
+    error::{runtime_error, type_error, ErrorKind, EvalError},
+    runtime::Runtime,
 };
 use crate::{
     evaluator::procedure::ApplyProcedure,
     expr::{
-        proc_result_tailcall, proc_result_value, AsExprs, Expr, Exprs, ListKind, ProcedureResult,
-        ProcedureReturn,
+        proc_result_tailcall, proc_result_value, AsExprs, Expr, Exprs, List, ListKind,
+        NamedProcedure, Procedure, ProcedureResult, ProcedureReturn,
     },
-    utils::debug,
+    parser::Span,
+    utils::{debug, grow_stack},
 };
 
 pub type EvalResult = Result<Expr, EvalError>;
 
 pub fn expand_macros(expr: Expr, env: &mut EnvRef) -> EvalResult {
-    if !expr.is_list() {
-        return Ok(expr);
-    }
-
-    let original_expr = expr.clone();
+    let list = match expr {
+        Expr::List(list) if list.is_proper() && !list.is_empty() => list,
+        expr => return Ok(expr),
+    };
+    // nested lists are expanded recursively, so their depth is bounded same as evaluation
+    let runtime = env.runtime();
+    let _depth = runtime.enter()?;
+    grow_stack(|| expand_list_macros(list, env))
+}
 
-    let list = expr.into_list().unwrap();
-    if !list.is_proper() || list.is_empty() {
-        return Ok(original_expr);
-    }
+fn expand_list_macros(list: List, env: &mut EnvRef) -> EvalResult {
+    // expanded list forms keep location of the original form
+    let span = list.span().cloned();
 
-    // safe to unwrap because we just checked that list is not empty
+    // safe to unwrap because `expand_macros` checked that list is not empty
     let (first_expr, cdr_list) = list.split_first().unwrap();
     // list is proper, so list layout is same as exprs list
     let mut expanded_cdr_list = Exprs::new();
@@ -38,15 +44,22 @@ pub fn expand_macros(expr: Expr, env: &mut EnvRef) -> EvalResult {
             // expr is a macro call
             // evaluate a macro and return result
             Some(macro_proc) => {
-                debug!("expand_macros: {}", original_expr);
-                let expanded_expr = match macro_proc.apply(expanded_cdr_list, env)? {
-                    ProcedureReturn::Value(expr) => expr,
-                    ProcedureReturn::TailCall(expr, mut eval_env) => {
+                debug!("expand_macros: macro call of {}", proc_name);
+                let expanded_expr = match macro_proc.apply(expanded_cdr_list, env) {
+                    Ok(ProcedureReturn::Value(expr)) => expr,
+                    Ok(ProcedureReturn::TailCall(expr, mut eval_env)) => {
                         debug!("expand_macro_tailcall_expr: {}", expr);
                         // using eval_expanede_expr here
                         // so we do not call make unnecessary expand_macros
-                        eval_expanded_expr(expr, &mut eval_env)?
+                        eval_expanded_expr(expr, &mut eval_env)
+                            .map_err(|err| err.with_span(span.as_ref()))?
                     }
+                    Err(err) => return Err(err.with_span(span.as_ref())),
+                };
+                // code built by a macro is located at the macro call
+                let expanded_expr = match expanded_expr {
+                    Expr::List(list) if list.span().is_none() => Expr::List(list.with_span(span)),
+                    expr => expr,
                 };
                 // return with expand_macros call
                 // so we also expand macro calls from expanded macro
@@ -56,14 +69,18 @@ pub fn expand_macros(expr: Expr, env: &mut EnvRef) -> EvalResult {
             // so apply it and return result
             None => {
                 expanded_cdr_list.push_front(Expr::new_symbol(proc_name));
-                Ok(Expr::new_proper_list(expanded_cdr_list))
+                Ok(Expr::List(
+                    List::new_proper(expanded_cdr_list).with_span(span),
+                ))
             }
         },
         // expr is a call, but not a macro call
         // so construct expr back and return it
         first_expr => {
             expanded_cdr_list.push_front(first_expr);
-            Ok(Expr::new_proper_list(expanded_cdr_list))
+            Ok(Expr::List(
+                List::new_proper(expanded_cdr_list).with_span(span),
+            ))
         }
     }
 }
@@ -99,10 +116,37 @@ pub fn eval_expr(expr: Expr, env: &mut EnvRef) -> EvalResult {
     Ok(evaluated)
 }
 
-fn eval_expanded_expr(mut expr: Expr, env: &mut EnvRef) -> EvalResult {
+/// Applies `proc` to `args` and evaluates its tail call, so the result is the final value.
+pub fn apply_to_value(proc: &Procedure, args: Exprs, env: &mut EnvRef) -> EvalResult {
+    match proc.apply(args, env)? {
+        ProcedureReturn::Value(expr) => Ok(expr),
+        ProcedureReturn::TailCall(expr, mut eval_env) => eval_expanded_expr(expr, &mut eval_env),
+    }
+}
+
+fn eval_expanded_expr(expr: Expr, env: &mut EnvRef) -> EvalResult {
+    // depth of nested evaluations is bounded by the runtime limit instead of the native stack,
+    // so a deep recursion fails with an error and does not crash the host
+    let runtime = env.runtime();
+    let _depth = runtime.enter()?;
+    grow_stack(|| {
+        // calls of procedures are recorded, so they are added to the backtrace of an error
+        let mut calls = CallChain::default();
+        eval_tail_calls(expr, env, &runtime, &mut calls).map_err(|err| err.with_call_chain(calls))
+    })
+}
+
+fn eval_tail_calls(
+    mut expr: Expr,
+    env: &mut EnvRef,
+    runtime: &Runtime,
+    calls: &mut CallChain,
+) -> EvalResult {
     debug!("eval_expanded_expr: {}", expr);
     let mut env = env.clone();
     loop {
+        // each tail call is a step, so a loop can not run past the limits
+        runtime.step()?;
         match expr {
             Expr::Boolean(_) => return Ok(expr),
             Expr::Integer(_) => return Ok(expr),
@@ -111,18 +155,26 @@ fn eval_expanded_expr(mut expr: Expr, env: &mut EnvRef) -> EvalResult {
             Expr::String(_) => return Ok(expr),
             Expr::InputPort(_) => return Ok(expr),
             Expr::OutputPort(_) => return Ok(expr),
+            Expr::Foreign(_) => return Ok(expr),
             Expr::Symbol(symbol) => return eval_symbol(symbol, &mut env),
             Expr::List(list) => match list.kind() {
-                ListKind::Proper => match eval_list(list.into(), &mut env)? {
-                    ProcedureReturn::Value(e) => return Ok(e),
-                    ProcedureReturn::TailCall(e, eval_env) => {
-                        expr = e;
-                        env = eval_env;
-                        debug!("tailcall: {} in {:?}", expr, env);
+                ListKind::Proper => {
+                    // errors are located at the innermost list form that has a location
+                    let span = list.span().cloned();
+                    match eval_list(list.into(), span.as_ref(), calls, &mut env)
+                        .map_err(|err| err.with_span(span.as_ref()))?
+                    {
+                        ProcedureReturn::Value(e) => return Ok(e),
+                        ProcedureReturn::TailCall(e, eval_env) => {
+                            expr = e;
+                            env = eval_env;
+                            debug!("tailcall: {} in {:?}", expr, env);
+                        }
                     }
-                },
+                }
                 ListKind::Dotted => return Err(runtime_error!("dotted list cannot be evaluated")),
             },
+            Expr::Pair(_) => return Err(runtime_error!("cyclic list cannot be evaluated")),
             Expr::Void => return Err(runtime_error!("void object cannot be evaluated")),
             Expr::Procedure(_) => {
                 return Err(runtime_error!("procedure object cannot be evaluated"))
@@ -135,12 +187,17 @@ fn eval_symbol(symbol: String, env: &mut EnvRef) -> EvalResult {
     debug!("eval_symbol: {}", symbol);
     let value = env
         .get_expr(&symbol)
-        .ok_or(runtime_error!("undefined symbol: {}", symbol))?;
+        .ok_or_else(|| EvalError::new(ErrorKind::UnboundVariable(symbol.clone())))?;
     debug!("symbol {} resolved to {}", symbol, value);
     Ok(value)
 }
 
-fn eval_list(mut list: Exprs, env: &mut EnvRef) -> ProcedureResult {
+fn eval_list(
+    mut list: Exprs,
+    span: Option<&Span>,
+    calls: &mut CallChain,
+    env: &mut EnvRef,
+) -> ProcedureResult {
     debug!("eval_list: {:?}", list);
 
     let proc = list
@@ -148,20 +205,30 @@ fn eval_list(mut list: Exprs, env: &mut EnvRef) -> ProcedureResult {
         .ok_or(runtime_error!("empty list cannot be evaluated"))
         .and_then(|expr| eval_expr(expr, env))?
         .into_procedure()
-        .map_err(|expr| {
-            runtime_error!("expected procedure as first element of call, got {}", expr)
-        })?;
-
-    let mut args = list;
-    if !proc.is_special_form() {
-        // if procedure is not a special form, evaluate arguments first
-        args = args
-            .into_iter()
-            .map(|arg| eval_expr(arg, env))
-            .collect::<Result<Exprs, EvalError>>()?;
+        .map_err(|expr| type_error!("expected procedure as first element of call, got {}", expr))?;
+
+    // special forms are not procedure calls, so they are not recorded
+    if proc.is_special_form() {
+        return proc.apply(list, env);
     }
 
-    proc.apply(args, env)
+    // evaluate arguments first
+    let args = list
+        .into_iter()
+        .map(|arg| eval_expr(arg, env))
+        .collect::<Result<Exprs, EvalError>>()?;
+
+    let frame = || Frame::new(proc.name_stored().cloned(), span.cloned());
+    match proc.apply(args, env) {
+        // compound procedures return their body as a tail call,
+        // so the call is recorded until the evaluation of the body finishes
+        Ok(ProcedureReturn::TailCall(expr, env)) => {
+            calls.push(frame());
+            Ok(ProcedureReturn::TailCall(expr, env))
+        }
+        Ok(value) => Ok(value),
+        Err(err) => Err(err.with_span(span).with_frame(frame())),
+    }
 }
 
 #[cfg(test)]
@@ -1,10 +1,16 @@
+mod backtrace;
+mod capability;
 mod env;
 mod error;
 mod eval;
 mod primitives;
 mod procedure;
+mod runtime;
 mod utils;
 
-pub use env::{new_root_env, EnvRef};
-pub use error::EvalError;
-pub use eval::{eval_exprs, EvalResult};
+pub use backtrace::{Backtrace, Frame};
+pub use capability::Capability;
+pub use env::{new_root_env, new_sandboxed_root_env, EnvRef};
+pub use error::{ErrorKind, EvalError, LispDMError};
+pub use eval::{apply_to_value, eval_exprs, EvalResult};
+pub use runtime::{InterruptHandle, Limits};
@@ -1,7 +1,7 @@
 use super::utils::define_procedures;
 use crate::{
     evaluator::{error::runtime_error, EnvRef},
-    expr::{proc_result_value, Arity, Expr, Exprs, ProcedureResult},
+    expr::{format_float, proc_result_value, Arity, Expr, Exprs, ProcedureResult},
 };
 
 define_procedures! {
@@ -33,7 +33,7 @@ fn number_to_string_fn(mut args: Exprs, _env: &mut EnvRef) -> ProcedureResult {
 
     let result = match number {
         Expr::Integer(n) => n.to_string_radix(radix),
-        Expr::Float(f) => format!("{}", f),
+        Expr::Float(f) => format_float(f),
         _ => {
             return Err(runtime_error!(
                 "number->string is only supported for integers and inexact numbers with radix 10"
@@ -1,10 +1,11 @@
-use std::rc::Rc;
+use std::{collections::HashSet, rc::Rc};
 
 use super::utils::define_procedures;
 use crate::{
     evaluator::EnvRef,
     expr::list::List,
     expr::{proc_result_value, Arity, Expr, Exprs, ProcedureResult},
+    utils::grow_stack,
 };
 
 define_procedures! {
@@ -25,7 +26,9 @@ fn eqv_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         (Expr::String(a), Expr::String(b)) => Expr::Boolean(Rc::ptr_eq(&a, &b)),
         (Expr::Symbol(a), Expr::Symbol(b)) => Expr::Boolean(a == b),
         (Expr::List(a), Expr::List(b)) => Expr::Boolean(std::ptr::eq(&a, &b)),
+        (Expr::Pair(a), Expr::Pair(b)) => Expr::Boolean(a == b),
         (Expr::Procedure(a), Expr::Procedure(b)) => Expr::Boolean(a == b),
+        (Expr::Foreign(a), Expr::Foreign(b)) => Expr::Boolean(a.ptr_eq(&b)),
         _ => Expr::Boolean(false),
     };
 
@@ -44,7 +47,9 @@ fn eq_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         (Expr::String(a), Expr::String(b)) => Expr::Boolean(Rc::ptr_eq(&a, &b)),
         (Expr::Symbol(a), Expr::Symbol(b)) => Expr::Boolean(a == b),
         (Expr::List(a), Expr::List(b)) => Expr::Boolean(std::ptr::eq(&a, &b)),
+        (Expr::Pair(a), Expr::Pair(b)) => Expr::Boolean(a == b),
         (Expr::Procedure(a), Expr::Procedure(b)) => Expr::Boolean(a == b),
+        (Expr::Foreign(a), Expr::Foreign(b)) => Expr::Boolean(a.ptr_eq(&b)),
         _ => Expr::Boolean(false),
     };
 
@@ -55,41 +60,96 @@ fn equal_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let arg1 = args.pop_front().unwrap();
     let arg2 = args.pop_front().unwrap();
 
-    let result = match (arg1, arg2) {
-        (Expr::Boolean(a), Expr::Boolean(b)) => Expr::Boolean(a == b),
-        (Expr::Integer(a), Expr::Integer(b)) => Expr::Boolean(a == b),
-        (Expr::Float(a), Expr::Float(b)) => Expr::Boolean(a == b),
-        (Expr::Char(a), Expr::Char(b)) => Expr::Boolean(a == b),
-        (Expr::String(a), Expr::String(b)) => {
-            Expr::Boolean(a.borrow().as_str() == b.borrow().as_str())
-        }
-        (Expr::Symbol(a), Expr::Symbol(b)) => Expr::Boolean(a == b),
-        (Expr::List(a), Expr::List(b)) => Expr::Boolean(equal_lists(&a, &b)),
-        (Expr::Procedure(a), Expr::Procedure(b)) => Expr::Boolean(a == b),
-        _ => Expr::Boolean(false),
-    };
+    let result = equal_positions(
+        Position::Expr(&arg1),
+        Position::Expr(&arg2),
+        &mut HashSet::new(),
+    );
 
-    proc_result_value!(result)
+    proc_result_value!(Expr::Boolean(result))
 }
 
-fn equal_lists(list1: &List, list2: &List) -> bool {
-    if list1.len() != list2.len() {
-        return false;
+// position in a chain of pairs, which is a value or the rest of a list after its first items
+#[derive(Clone, Copy)]
+enum Position<'a> {
+    Expr(&'a Expr),
+    Rest(&'a List, usize),
+}
+
+// address of a value and index of the list item, which start a position
+type PositionId = (*const (), usize);
+
+// value at a position, lists are compared as chains of pairs
+enum Node<'a> {
+    Pair(&'a Expr, Position<'a>),
+    Null,
+    Atom(&'a Expr),
+}
+
+impl<'a> Position<'a> {
+    fn node(self) -> Node<'a> {
+        match self {
+            Position::Expr(Expr::List(list)) => Position::Rest(list, 0).node(),
+            Position::Expr(Expr::Pair(pair)) => Node::Pair(pair.car(), Position::Expr(pair.cdr())),
+            Position::Expr(expr) => Node::Atom(expr),
+            Position::Rest(list, index) => match (list.item(index), list.last()) {
+                (Some(item), _) => Node::Pair(item, Position::Rest(list, index + 1)),
+                (None, Some(last)) => Position::Expr(last).node(),
+                (None, None) => Node::Null,
+            },
+        }
     }
 
-    let mut iter1 = list1.iter();
-    let mut iter2 = list2.iter();
+    fn is_shared_pair(self) -> bool {
+        matches!(self, Position::Expr(Expr::Pair(_)))
+    }
 
-    while let (Some(elem1), Some(elem2)) = (iter1.next(), iter2.next()) {
-        if !equal_recursive(elem1, elem2) {
-            return false;
+    // compared values are borrowed during the comparison, so their addresses identify positions
+    fn id(self) -> PositionId {
+        match self {
+            Position::Expr(Expr::Pair(pair)) => (pair.as_ptr(), 0),
+            Position::Expr(Expr::List(list)) | Position::Rest(list, 0) => {
+                (std::ptr::from_ref(list).cast(), 0)
+            }
+            Position::Expr(expr) => (std::ptr::from_ref(expr).cast(), 0),
+            Position::Rest(list, index) => (std::ptr::from_ref(list).cast(), index),
         }
     }
+}
 
-    true
+// lists own their elements, so data can be cyclic only through shared pairs;
+// positions compared at shared pairs are remembered and assumed equal when they are
+// compared again, so the comparison of cyclic data terminates
+fn equal_positions<'a>(
+    mut pos1: Position<'a>,
+    mut pos2: Position<'a>,
+    compared: &mut HashSet<(PositionId, PositionId)>,
+) -> bool {
+    loop {
+        if (pos1.is_shared_pair() || pos2.is_shared_pair())
+            && !compared.insert((pos1.id(), pos2.id()))
+        {
+            return true;
+        }
+        match (pos1.node(), pos2.node()) {
+            (Node::Pair(car1, cdr1), Node::Pair(car2, cdr2)) => {
+                let equal_cars = grow_stack(|| {
+                    equal_positions(Position::Expr(car1), Position::Expr(car2), compared)
+                });
+                if !equal_cars {
+                    return false;
+                }
+                pos1 = cdr1;
+                pos2 = cdr2;
+            }
+            (Node::Null, Node::Null) => return true,
+            (Node::Atom(atom1), Node::Atom(atom2)) => return equal_atoms(atom1, atom2),
+            _ => return false,
+        }
+    }
 }
 
-fn equal_recursive(expr1: &Expr, expr2: &Expr) -> bool {
+fn equal_atoms(expr1: &Expr, expr2: &Expr) -> bool {
     match (expr1, expr2) {
         (Expr::Boolean(a), Expr::Boolean(b)) => a == b,
         (Expr::Integer(a), Expr::Integer(b)) => a == b,
@@ -97,8 +157,8 @@ fn equal_recursive(expr1: &Expr, expr2: &Expr) -> bool {
         (Expr::Char(a), Expr::Char(b)) => a == b,
         (Expr::String(a), Expr::String(b)) => a.borrow().as_str() == b.borrow().as_str(),
         (Expr::Symbol(a), Expr::Symbol(b)) => a == b,
-        (Expr::List(a), Expr::List(b)) => equal_lists(a, b),
         (Expr::Procedure(a), Expr::Procedure(b)) => a == b,
+        (Expr::Foreign(a), Expr::Foreign(b)) => a.equal(b),
         _ => false,
     }
 }
@@ -1,23 +1,24 @@
 use super::utils::define_procedures;
 use crate::{
-    evaluator::{error::runtime_error, procedure::ApplyProcedure, EnvRef},
-    expr::{proc_result_tailcall, Arity, Exprs, ProcedureResult},
+    evaluator::{error::type_error, eval, procedure::ApplyProcedure, EnvRef, ErrorKind},
+    expr::{proc_result_tailcall, proc_result_value, Arity, Exprs, ProcedureResult},
 };
 
 define_procedures! {
     apply = ("apply", apply_fn, Arity::AtLeast(2)),
     eval = ("eval", eval_fn, Arity::Exact(1)),
+    dynamic_wind = ("dynamic-wind", dynamic_wind_fn, Arity::Exact(3)),
 }
 
 fn apply_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let proc = args.pop_front().unwrap().into_procedure().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected procedure as first argument of apply, got {}",
             expr.kind()
         )
     })?;
     let last_list = args.pop_back().unwrap().into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected list as second argument for apply, got {}",
             expr.kind()
         )
@@ -31,3 +32,32 @@ fn apply_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 fn eval_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     proc_result_tailcall!(args.pop_front().unwrap(), env)
 }
+
+fn dynamic_wind_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    let mut thunks = Vec::with_capacity(3);
+    for (arg, idx) in args.into_iter().zip(1..) {
+        thunks.push(arg.into_procedure().map_err(|expr| {
+            type_error!(
+                "expected procedure as argument {} of dynamic-wind, got {}",
+                idx,
+                expr.kind()
+            )
+        })?);
+    }
+    let (before, thunk, after) = (&thunks[0], &thunks[1], &thunks[2]);
+
+    eval::apply_to_value(before, Exprs::new(), env)?;
+    let result = eval::apply_to_value(thunk, Exprs::new(), env);
+    // `after` is called when leaving `thunk` with an error or `exit`, but not `emergency-exit`
+    if let Err(err) = &result {
+        if let ErrorKind::EmergencyExit(_) = err.kind() {
+            return Err(result.unwrap_err());
+        }
+    }
+    let after_result = eval::apply_to_value(after, Exprs::new(), env);
+    // an error of `after` does not replace the error or the exit request of `thunk`
+    let value = result?;
+    after_result?;
+
+    proc_result_value!(value)
+}
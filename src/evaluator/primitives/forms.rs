@@ -1,7 +1,7 @@
 use super::utils::{create_procedure, define_special_forms};
 use crate::{
     evaluator::{
-        error::runtime_error,
+        error::{runtime_error, type_error},
         eval::{self, EvalResult},
         procedure::ApplyProcedure,
         EnvRef,
@@ -40,7 +40,7 @@ fn modify_env(
     mod_env: ModifyEnv,
 ) -> ProcedureResult {
     let symbol = symbol_expr.into_symbol().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected symbol as first argument of define, got {}",
             expr.kind()
         )
@@ -71,7 +71,7 @@ fn define_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     // if first arg is not a symbol (define variable)
     // it must be a list (define procedure)
     let name_and_params = first_arg.into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected symbol or list as the first argument for define, got {}",
             expr
         )
@@ -83,7 +83,7 @@ fn define_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         .zip(1..)
         .try_for_each(|expr| match expr {
             (Expr::Symbol(_), _) => Ok(()),
-            (expr, idx) => Err(runtime_error!(
+            (expr, idx) => Err(type_error!(
                 "expected symbols in define procedure formals list, got {} at position {}",
                 expr.kind(),
                 idx
@@ -91,7 +91,7 @@ fn define_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         })?;
 
     let (name_expr, mut params) = name_and_params.split_first().map_err(|_| {
-        runtime_error!("expected at least 1 argument for define procedure formals list, got 0")
+        type_error!("expected at least 1 argument for define procedure formals list, got 0")
     })?;
     // unwrap is safe since we checked `name_and_params` above
     let name = name_expr.into_symbol().unwrap();
@@ -128,7 +128,7 @@ fn let_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     }
 
     let mut bindings = first_arg.into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected bindings list as first argument of let, got {}",
             expr
         )
@@ -140,17 +140,17 @@ fn let_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
             .pop_front()
             .unwrap()
             .into_list()
-            .map_err(|expr| runtime_error!("expected list as binding in let, got {}", expr))?;
+            .map_err(|expr| type_error!("expected list as binding in let, got {}", expr))?;
 
         if binding.len() != 2 {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected 2 elements in binding in let, got {}",
                 binding.len()
             ));
         }
 
         let symbol = binding.pop_front().unwrap().into_symbol().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected symbol as first element of binding in let, got {}",
                 expr.kind()
             )
@@ -167,7 +167,7 @@ fn let_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 // named let (let <symbol> <bindings> <body>)
 fn named_let_form(name: String, mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let mut bindings = args.pop_front().unwrap().into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected bindings list as first argument of let, got {}",
             expr
         )
@@ -180,17 +180,17 @@ fn named_let_form(name: String, mut args: Exprs, env: &mut EnvRef) -> ProcedureR
             .pop_front()
             .unwrap()
             .into_list()
-            .map_err(|expr| runtime_error!("expected list as binding in let, got {}", expr))?;
+            .map_err(|expr| type_error!("expected list as binding in let, got {}", expr))?;
 
         if binding.len() != 2 {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected 2 elements in binding in let, got {}",
                 binding.len()
             ));
         }
 
         let symbol = binding.pop_front().unwrap().into_symbol().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected symbol as first element of binding in let, got {}",
                 expr.kind()
             )
@@ -214,7 +214,7 @@ fn named_let_form(name: String, mut args: Exprs, env: &mut EnvRef) -> ProcedureR
 
 fn letrec_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let mut bindings = args.pop_front().unwrap().into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected bindings list as first argument of letrec, got {}",
             expr
         )
@@ -222,18 +222,19 @@ fn letrec_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 
     let mut eval_env = env.extend();
     while !bindings.is_empty() {
-        let mut binding =
-            bindings.pop_front().unwrap().into_list().map_err(|expr| {
-                runtime_error!("expected list as binding in letrec, got {}", expr)
-            })?;
+        let mut binding = bindings
+            .pop_front()
+            .unwrap()
+            .into_list()
+            .map_err(|expr| type_error!("expected list as binding in letrec, got {}", expr))?;
         if binding.len() != 2 {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected 2 elements in binding in letrec, got {}",
                 binding.len()
             ));
         }
         let symbol = binding.pop_front().unwrap().into_symbol().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected symbol as first element of binding in letrec, got {}",
                 expr.kind()
             )
@@ -290,7 +291,7 @@ fn cond_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     for (clause, idx) in args.into_iter().zip(1..) {
         let mut clause = clause
             .into_list()
-            .map_err(|expr| runtime_error!("expected list as clause, got {}", expr))?;
+            .map_err(|expr| type_error!("expected list as clause, got {}", expr))?;
 
         if clause.is_empty() {
             return Err(runtime_error!("expected at least 1 element in clause",));
@@ -322,7 +323,7 @@ fn cond_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
                             eval::eval_expr(proc_expr, env)?
                                 .into_procedure()
                                 .map_err(|expr| {
-                                    runtime_error!(
+                                    type_error!(
                                         "expected procedure after `=>` in clause, got {}",
                                         expr.kind()
                                     )
@@ -375,7 +376,7 @@ fn begin_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 
 fn do_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let mut bindings = args.pop_front().unwrap().into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected bindings list as first argument of do, got {}",
             expr
         )
@@ -388,17 +389,17 @@ fn do_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
             .pop_front()
             .unwrap()
             .into_list()
-            .map_err(|expr| runtime_error!("expected list as binding in do, got {}", expr))?;
+            .map_err(|expr| type_error!("expected list as binding in do, got {}", expr))?;
 
         if binding.len() < 2 || binding.len() > 3 {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected 2 or 3 elements in binding in do, got {}",
                 binding.len()
             ));
         }
 
         let symbol = binding.pop_front().unwrap().into_symbol().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected symbol as first element of binding in do, got {}",
                 expr.kind()
             )
@@ -415,10 +416,11 @@ fn do_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         steps.push((symbol, step));
     }
 
-    let mut test_expr_list =
-        args.pop_front().unwrap().into_list().map_err(|expr| {
-            runtime_error!("expected list as test expression in do, got {}", expr)
-        })?;
+    let mut test_expr_list = args
+        .pop_front()
+        .unwrap()
+        .into_list()
+        .map_err(|expr| type_error!("expected list as test expression in do, got {}", expr))?;
     let test = test_expr_list
         .pop_front()
         .ok_or(runtime_error!("expected test expression in do"))?;
@@ -482,7 +484,7 @@ fn quasiquote_list(list: Exprs, env: &mut EnvRef) -> EvalResult {
                         .ok_or(runtime_error!("expected expression after unquote"))?;
 
                     let list = eval::eval_expr(expr, env)?.into_list().map_err(|expr| {
-                        runtime_error!("expected list after unquote-splicing, got {}", expr.kind())
+                        type_error!("expected list after unquote-splicing, got {}", expr.kind())
                     })?;
                     new_list.extend(list);
                 }
@@ -1,38 +1,39 @@
 use super::utils::define_procedures;
 use crate::{
-    evaluator::{error::runtime_error, EnvRef},
-    expr::{proc_result_value, Arity, Expr, Exprs, ProcedureResult},
+    evaluator::{
+        error::{io_error, runtime_error, type_error},
+        EnvRef, EvalError,
+    },
+    expr::{
+        proc_result_value, Arity, Expr, Exprs, InputPortSuperTrait, Printer, ProcedureResult,
+        WriteMode,
+    },
     parser,
 };
+use std::{cell::RefCell, rc::Rc};
 
 define_procedures! {
     read = ("read", read_fn, Arity::Range(0, 1)),
     read_char = ("read-char", read_char_fn, Arity::Range(0, 1)),
     read_string = ("read-string", read_string_fn, Arity::Range(0, 1)),
     write = ("write", write_fn, Arity::Range(1, 2)),
+    write_shared = ("write-shared", write_shared_fn, Arity::Range(1, 2)),
+    write_simple = ("write-simple", write_simple_fn, Arity::Range(1, 2)),
     write_char = ("write-char", write_char_fn, Arity::Range(1, 2)),
     write_string = ("write-string", write_string_fn, Arity::Range(1, 4)),
-    display = ("display", write_fn, Arity::Range(1, 2)),
+    display = ("display", display_fn, Arity::Range(1, 2)),
     newline = ("newline", newline_fn, Arity::Range(0 ,1)),
 }
 
 fn read_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
-    let port = match args.pop_front() {
-        Some(e) => e.into_input_port().map_err(|expr| {
-            runtime_error!(
-                "expected input port as read-string argument, got {}",
-                expr.kind()
-            )
-        })?,
-        None => env.current_input_port(),
-    };
+    let port = input_port(args.pop_front(), env, "read")?;
     let input = port
         .borrow_mut()
         .read_string()
-        .map_err(|e| runtime_error!("Could not read input string: {}", e))?;
+        .map_err(|e| io_error!("Could not read input string: {}", e))?;
 
     let expr = parser::parse_str(&input)
-        .map_err(|e| runtime_error!("Could not parse input: {}", e))?
+        .map_err(EvalError::from)?
         .pop_front()
         .ok_or(runtime_error!("Could not parse input: empty input"))?;
 
@@ -40,47 +41,78 @@ fn read_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 }
 
 fn read_char_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
-    let port = match args.pop_front() {
-        Some(e) => e.into_input_port().map_err(|expr| {
-            runtime_error!(
-                "expected input port as read-char argument, got {}",
-                expr.kind()
-            )
-        })?,
-        None => env.current_input_port(),
-    };
+    let port = input_port(args.pop_front(), env, "read-char")?;
     let char_result = port
         .borrow_mut()
         .read_char()
-        .map_err(|e| runtime_error!("Could not read character: {}", e))?;
+        .map_err(|e| io_error!("Could not read character: {}", e))?;
 
     proc_result_value!(Expr::Char(char_result))
 }
 
 fn read_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
-    let port = match args.pop_front() {
+    let port = input_port(args.pop_front(), env, "read-string")?;
+    let input = port
+        .borrow_mut()
+        .read_string()
+        .map_err(|e| io_error!("Could not read input string: {}", e))?;
+
+    proc_result_value!(Expr::new_string(input))
+}
+
+// returns the port to read from, prompts are flushed before reading from interactive ports
+fn input_port(
+    arg: Option<Expr>,
+    env: &EnvRef,
+    proc_name: &str,
+) -> Result<Rc<RefCell<dyn InputPortSuperTrait>>, EvalError> {
+    let port = match arg {
         Some(e) => e.into_input_port().map_err(|expr| {
-            runtime_error!(
-                "expected input port as read-string argument, got {}",
+            type_error!(
+                "expected input port as {} argument, got {}",
+                proc_name,
                 expr.kind()
             )
         })?,
         None => env.current_input_port(),
     };
-    let input = port
-        .borrow_mut()
-        .read_string()
-        .map_err(|e| runtime_error!("Could not read input string: {}", e))?;
+    if port.borrow().is_interactive() {
+        env.current_output_port()
+            .borrow_mut()
+            .flush()
+            .map_err(|e| io_error!("{}", e))?;
+    }
+    Ok(port)
+}
 
-    proc_result_value!(Expr::new_string(input))
+fn write_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    write_in_mode(args, env, WriteMode::Write, "write")
+}
+
+fn write_shared_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    write_in_mode(args, env, WriteMode::Shared, "write-shared")
+}
+
+fn write_simple_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    write_in_mode(args, env, WriteMode::Simple, "write-simple")
+}
+
+fn display_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    write_in_mode(args, env, WriteMode::Display, "display")
 }
 
-fn write_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+fn write_in_mode(
+    mut args: Exprs,
+    env: &mut EnvRef,
+    mode: WriteMode,
+    proc_name: &str,
+) -> ProcedureResult {
     let expr = args.pop_front().unwrap();
     let port = match args.pop_front() {
         Some(expr) => expr.into_output_port().map_err(|expr| {
-            runtime_error!(
-                "expected output port as second display argument, got {}",
+            type_error!(
+                "expected output port as second {} argument, got {}",
+                proc_name,
                 expr.kind()
             )
         })?,
@@ -88,7 +120,7 @@ fn write_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     };
 
     let mut port = port.borrow_mut();
-    write!(port, "{}", expr).map_err(|e| e.to_string())?;
+    write!(port, "{}", Printer::new(&expr, mode)).map_err(|e| io_error!("{}", e))?;
 
     proc_result_value!(Expr::Void)
 }
@@ -96,7 +128,7 @@ fn write_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 fn newline_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let port = match args.pop_front() {
         Some(expr) => expr.into_output_port().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected output port as newline argument, got {}",
                 expr.kind()
             )
@@ -105,19 +137,20 @@ fn newline_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     };
 
     let mut port = port.borrow_mut();
-    writeln!(port).map_err(|e| e.to_string())?;
+    writeln!(port).map_err(|e| io_error!("{}", e))?;
 
     proc_result_value!(Expr::Void)
 }
 
 fn write_char_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
-    let char_arg = args.pop_front().unwrap().into_char().map_err(|expr| {
-        runtime_error!("expected char as write-char argument, got {}", expr.kind())
-    })?;
+    let char_arg =
+        args.pop_front().unwrap().into_char().map_err(|expr| {
+            type_error!("expected char as write-char argument, got {}", expr.kind())
+        })?;
 
     let port = match args.pop_front() {
         Some(expr) => expr.into_output_port().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected output port as second write-char argument, got {}",
                 expr.kind()
             )
@@ -126,14 +159,14 @@ fn write_char_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     };
 
     let mut port = port.borrow_mut();
-    write!(port, "{}", char_arg).map_err(|e| e.to_string())?;
+    write!(port, "{}", char_arg).map_err(|e| io_error!("{}", e))?;
 
     proc_result_value!(Expr::Void)
 }
 
 fn write_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let string_arg = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as write-string argument, got {}",
             expr.kind()
         )
@@ -141,7 +174,7 @@ fn write_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 
     let port = match args.pop_front() {
         Some(expr) => expr.into_output_port().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected output port as second write-string argument, got {}",
                 expr.kind()
             )
@@ -151,7 +184,7 @@ fn write_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 
     let start = match args.pop_front() {
         Some(expr) => expr.into_integer().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected integer as third write-string argument, got {}",
                 expr.kind()
             )
@@ -161,7 +194,7 @@ fn write_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 
     let end = match args.pop_front() {
         Some(expr) => expr.into_integer().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected integer as fourth write-string argument, got {}",
                 expr.kind()
             )
@@ -173,7 +206,7 @@ fn write_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let substring = string_value[start..end].to_string();
 
     let mut port = port.borrow_mut();
-    write!(port, "{}", substring).map_err(|e| e.to_string())?;
+    write!(port, "{}", substring).map_err(|e| io_error!("{}", e))?;
 
     proc_result_value!(Expr::Void)
 }
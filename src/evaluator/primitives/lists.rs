@@ -1,6 +1,9 @@
 use super::utils::define_procedures;
 use crate::{
-    evaluator::{error::runtime_error, EnvRef},
+    evaluator::{
+        error::{runtime_error, type_error},
+        EnvRef,
+    },
     expr::{list::List, proc_result_value, Arity, Expr, Exprs, ListKind, ProcedureResult},
     exprs,
 };
@@ -35,7 +38,8 @@ fn cons_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
 fn car_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let list = match args.pop_front().unwrap() {
         Expr::List(list) => list,
-        expr => return Err(runtime_error!("expected list for car, got {}", expr.kind())),
+        Expr::Pair(pair) => return proc_result_value!(pair.car().clone()),
+        expr => return Err(type_error!("expected list for car, got {}", expr.kind())),
     };
 
     let res = list
@@ -49,7 +53,8 @@ fn car_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
 fn cdr_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let list = match args.pop_front().unwrap() {
         Expr::List(list) => list,
-        expr => return Err(runtime_error!("expected list for cdr, got {}", expr.kind())),
+        Expr::Pair(pair) => return proc_result_value!(pair.cdr().clone()),
+        expr => return Err(type_error!("expected list for cdr, got {}", expr.kind())),
     };
 
     let (_, cdr_list) = list
@@ -65,23 +70,23 @@ fn cdr_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     proc_result_value!(res)
 }
 
-fn make_list_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+fn make_list_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let k = args
         .pop_front()
         .unwrap()
         .into_integer()
         .map_err(|_| runtime_error!("make-list expected an integer as its first argument"))?;
+    let k: usize = k.try_into().map_err(|_| {
+        runtime_error!("make-list expected a non-negative integer as its first argument")
+    })?;
+    env.runtime().spend(k as u64)?;
 
     if args.is_empty() {
-        let list = std::iter::repeat(Expr::Integer(0))
-            .take(k as usize)
-            .collect::<Exprs>();
+        let list = std::iter::repeat_n(Expr::Integer(0), k).collect::<Exprs>();
         proc_result_value!(Expr::List(List::new_proper(list)))
     } else {
         let fill = args.pop_front().unwrap();
-        let list = std::iter::repeat(fill.clone())
-            .take(k as usize)
-            .collect::<Exprs>();
+        let list = std::iter::repeat_n(fill, k).collect::<Exprs>();
         proc_result_value!(Expr::List(List::new_proper(list)))
     }
 }
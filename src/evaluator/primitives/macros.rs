@@ -1,6 +1,6 @@
 use super::utils::define_special_forms;
 use crate::{
-    evaluator::{error::runtime_error, primitives::utils::create_procedure, EnvRef},
+    evaluator::{error::type_error, primitives::utils::create_procedure, EnvRef},
     expr::{proc_result_value, Arity, Body, Expr, Exprs, ListKind, ProcedureResult},
 };
 
@@ -10,7 +10,7 @@ define_special_forms! {
 
 fn define_macro_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let name_and_params = args.pop_front().unwrap().into_list().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected list as the first argument for define-macro, got {}",
             expr
         )
@@ -22,7 +22,7 @@ fn define_macro_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         .zip(1..)
         .try_for_each(|expr| match expr {
             (Expr::Symbol(_), _) => Ok(()),
-            (expr, idx) => Err(runtime_error!(
+            (expr, idx) => Err(type_error!(
                 "expected symbols in define procedure formals list, got {} at position {}",
                 expr.kind(),
                 idx
@@ -30,7 +30,7 @@ fn define_macro_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         })?;
 
     let (name_expr, mut params) = name_and_params.split_first().map_err(|_| {
-        runtime_error!("expected at least 1 argument for define-macro formals list, got 0")
+        type_error!("expected at least 1 argument for define-macro formals list, got 0")
     })?;
     // unwrap is safe since we checked `name_and_params` above
     let name = name_expr.into_symbol().unwrap();
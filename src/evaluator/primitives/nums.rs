@@ -1,6 +1,9 @@
 use super::utils::{define_procedures, fold_binary_op};
 use crate::{
-    evaluator::{error::runtime_error, EnvRef},
+    evaluator::{
+        error::{runtime_error, type_error},
+        EnvRef,
+    },
     expr::{proc_result_value, Arity, Expr, Exprs, ProcedureResult, ProcedureReturn},
 };
 
@@ -35,7 +38,7 @@ fn add_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
             (Expr::Integer(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs as f64 + rhs)),
             (Expr::Float(lhs), Expr::Integer(rhs)) => Ok(Expr::Float(lhs + rhs as f64)),
             (Expr::Float(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs + rhs)),
-            (lhs, rhs) => Err(runtime_error!(
+            (lhs, rhs) => Err(type_error!(
                 "expected integers or floats for +, got {} and {}",
                 lhs.kind(),
                 rhs.kind(),
@@ -57,7 +60,7 @@ fn sub_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         (Expr::Integer(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs as f64 - rhs)),
         (Expr::Float(lhs), Expr::Integer(rhs)) => Ok(Expr::Float(lhs - rhs as f64)),
         (Expr::Float(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs - rhs)),
-        (lhs, rhs) => Err(runtime_error!(
+        (lhs, rhs) => Err(type_error!(
             "expected integers or floats -, got {} and {}",
             lhs,
             rhs
@@ -73,7 +76,7 @@ fn mult_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
             (Expr::Integer(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs as f64 * rhs)),
             (Expr::Float(lhs), Expr::Integer(rhs)) => Ok(Expr::Float(lhs * rhs as f64)),
             (Expr::Float(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs * rhs)),
-            (lhs, rhs) => Err(runtime_error!(
+            (lhs, rhs) => Err(type_error!(
                 "expected integers or floats for *, got {} and {}",
                 lhs.kind(),
                 rhs.kind()
@@ -95,7 +98,7 @@ fn divide_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         (Expr::Integer(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs as f64 / rhs)),
         (Expr::Float(lhs), Expr::Integer(rhs)) => Ok(Expr::Float(lhs / rhs as f64)),
         (Expr::Float(lhs), Expr::Float(rhs)) => Ok(Expr::Float(lhs / rhs)),
-        (lhs, rhs) => Err(runtime_error!(
+        (lhs, rhs) => Err(type_error!(
             "expected integers or floats for /, got {} and {}",
             lhs.kind(),
             rhs.kind()
@@ -114,7 +117,7 @@ fn less_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         (Expr::Float(lhs), Expr::Integer(rhs)) => Expr::Boolean(lhs < rhs as f64),
         (Expr::Float(lhs), Expr::Float(rhs)) => Expr::Boolean(lhs < rhs),
         (lhs, rhs) => {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected integers or floats for <, got {} and {}",
                 lhs.kind(),
                 rhs.kind()
@@ -135,7 +138,7 @@ fn equal_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         (Expr::Float(lhs), Expr::Integer(rhs)) => Expr::Boolean(lhs == rhs as f64),
         (Expr::Float(lhs), Expr::Float(rhs)) => Expr::Boolean(lhs == rhs),
         (lhs, rhs) => {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected integers or floats for =, got {} and {}",
                 lhs.kind(),
                 rhs.kind()
@@ -156,7 +159,7 @@ fn more_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         (Expr::Float(lhs), Expr::Integer(rhs)) => Expr::Boolean(lhs > rhs as f64),
         (Expr::Float(rhs), Expr::Float(lhs)) => Expr::Boolean(rhs > lhs),
         (lhs, rhs) => {
-            return Err(runtime_error!(
+            return Err(type_error!(
                 "expected integers or floats for >, got {} and {}",
                 lhs.kind(),
                 rhs.kind()
@@ -171,7 +174,7 @@ fn abs_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     (match &args[0] {
         Expr::Integer(n) => Ok(Expr::Integer(n.abs())),
         Expr::Float(f) => Ok(Expr::Float(f.abs())),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integer or float for abs, got {}",
             args[0].kind()
         )),
@@ -183,7 +186,7 @@ fn sqrt_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     (match &args[0] {
         Expr::Integer(n) if *n >= 0 => Ok(Expr::Float(((*n) as f64).sqrt())),
         Expr::Float(f) if *f >= 0.0 => Ok(Expr::Float(f.sqrt())),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected non-negative integer or float for sqrt, got {}",
             args[0].kind()
         )),
@@ -195,7 +198,7 @@ fn square_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     match &args[0] {
         Expr::Integer(n) => Ok(Expr::Integer(n * n)),
         Expr::Float(f) => Ok(Expr::Float(f * f)),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integer or float for square, got {}",
             args[0].kind()
         )),
@@ -214,7 +217,7 @@ fn expt_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         (Expr::Integer(base), Expr::Float(exp)) => Ok(Expr::Float(((*base) as f64).powf(*exp))),
         (Expr::Float(base), Expr::Integer(exp)) => Ok(Expr::Float(base.powi(*exp as i32))),
         (Expr::Float(base), Expr::Float(exp)) => Ok(Expr::Float(base.powf(*exp))),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integers or floats for expt, got {} and {}",
             base.kind(),
             exponent.kind()
@@ -229,7 +232,7 @@ fn min_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         .try_fold(f64::INFINITY, |acc, arg| match arg {
             Expr::Integer(n) => Ok(acc.min(n as f64)),
             Expr::Float(f) => Ok(acc.min(f)),
-            _ => Err(runtime_error!(
+            _ => Err(type_error!(
                 "expected integers or floats for min, got {}",
                 arg.kind()
             )),
@@ -244,7 +247,7 @@ fn max_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         .try_fold(f64::NEG_INFINITY, |acc, arg| match arg {
             Expr::Integer(n) => Ok(acc.max(n as f64)),
             Expr::Float(f) => Ok(acc.max(f)),
-            _ => Err(runtime_error!(
+            _ => Err(type_error!(
                 "expected integers or floats for max, got {}",
                 arg.kind()
             )),
@@ -257,7 +260,7 @@ fn floor_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     (match &args[0] {
         Expr::Integer(n) => Ok(Expr::Float(*n as f64)),
         Expr::Float(f) => Ok(Expr::Float(f.floor())),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integer or float for floor, got {}",
             args[0].kind()
         )),
@@ -269,7 +272,7 @@ fn ceiling_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     (match &args[0] {
         Expr::Integer(n) => Ok(Expr::Float(*n as f64)),
         Expr::Float(f) => Ok(Expr::Float(f.ceil())),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integer or float for ceiling, got {}",
             args[0].kind()
         )),
@@ -281,7 +284,7 @@ fn truncate_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     (match &args[0] {
         Expr::Integer(n) => Ok(Expr::Float(*n as f64)),
         Expr::Float(f) => Ok(Expr::Float(f.trunc())),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integer or float for truncate, got {}",
             args[0].kind()
         )),
@@ -293,7 +296,7 @@ fn round_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     (match &args[0] {
         Expr::Integer(n) => Ok(Expr::Float(*n as f64)),
         Expr::Float(f) => Ok(Expr::Float(f.round())),
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integer or float for round, got {}",
             args[0].kind()
         )),
@@ -343,7 +346,7 @@ fn quotient_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
                 Ok(Expr::Float(lhs / rhs))
             }
         }
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integers or floats for quotient, got {} and {}",
             dividend.kind(),
             divisor.kind()
@@ -385,7 +388,7 @@ fn remainder_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
                 Ok(Expr::Float(lhs % rhs))
             }
         }
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integers or floats for remainder, got {} and {}",
             dividend.kind(),
             divisor.kind()
@@ -431,7 +434,7 @@ fn modulo_fn(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
                 Ok(Expr::Float(result))
             }
         }
-        _ => Err(runtime_error!(
+        _ => Err(type_error!(
             "expected integers or floats for modulo, got {} and {}",
             dividend.kind(),
             divisor.kind()
@@ -1,52 +1,228 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, io, path::Path, rc::Rc};
 
 use super::utils::{define_procedures, resolve_path};
 use crate::{
-    evaluator::{error::runtime_error, procedure::ApplyProcedure, EnvRef},
-    expr::{proc_result_value, Arity, Expr, Exprs, FileInputPort, FileOutputPort, ProcedureResult},
+    evaluator::{
+        error::{io_error, runtime_error, type_error, EvalError},
+        eval, EnvRef,
+    },
+    expr::{
+        proc_result_value, Arity, BufferMode, Codec, ErrorHandlingMode, Expr, Exprs, FileInputPort,
+        FileOpenMode, FileOutputPort, InputPortSuperTrait, OutputPortSuperTrait, Procedure,
+        ProcedureResult, StringInputPort, Transcoder,
+    },
 };
 
 define_procedures! {
-    open_input_file = ("open-input-file", open_input_file_fn, Arity::Exact(1)),
-    open_output_file = ("open-output-file", open_output_file_fn, Arity::Exact(1)),
+    open_input_file = ("open-input-file", open_input_file_fn, Arity::AtLeast(1)),
+    open_output_file = ("open-output-file", open_output_file_fn, Arity::AtLeast(1)),
+    open_input_string = ("open-input-string", open_input_string_fn, Arity::Exact(1)),
     is_input_port = ("input-port?", is_input_port_fn, Arity::Exact(1)),
     is_output_port = ("output-port?", is_output_port_fn, Arity::Exact(1)),
     current_input_port = ("current-input-port", current_input_port_fn, Arity::Exact(0)),
     current_output_port = ("current-output-port", current_output_port_fn, Arity::Exact(0)),
+    current_error_port = ("current-error-port", current_error_port_fn, Arity::Exact(0)),
     close_input_port = ("close-input-port", close_input_port_fn, Arity::Exact(1)),
     close_output_port = ("close-output-port", close_output_port_fn, Arity::Exact(1)),
     with_input_from_file = ("with-input-from-file", with_input_from_file_fn, Arity::Exact(2)),
     with_output_to_file = ("with-output-to-file", with_output_to_file_fn, Arity::Exact(2)),
+    with_error_to_file = ("with-error-to-file", with_error_to_file_fn, Arity::Exact(2)),
     call_with_input_file = ("call-with-input-file", call_with_input_file_fn, Arity::Exact(2)),
     call_with_output_file = ("call-with-output-file", call_with_output_file_fn, Arity::Exact(2)),
+    flush_output_port = ("flush-output-port", flush_output_port_fn, Arity::Range(0, 1)),
+    port_line = ("port-line", port_line_fn, Arity::Exact(1)),
+    port_column = ("port-column", port_column_fn, Arity::Exact(1)),
+    port_position = ("port-position", port_position_fn, Arity::Exact(1)),
+    set_port_position = ("set-port-position!", set_port_position_fn, Arity::Exact(2)),
 }
 
 fn open_input_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as open-input-file argument, got {}",
             expr.kind()
         )
     })?;
 
+    let options = parse_port_options(args, "open-input-file")?;
+    if options.buffer_mode.is_some() || options.open_mode.is_some() {
+        return Err(runtime_error!(
+            "open-input-file accepts only encoding options"
+        ));
+    }
+
     let resolved_path = resolve_path(&file_path.borrow(), env)?;
-    let port = FileInputPort::from_path(resolved_path).map_err(|e| e.to_string())?;
+    let port = FileInputPort::from_path_with_transcoder(&resolved_path, options.transcoder)
+        .map_err(|e| {
+            io_error!(
+                "could not open '{}' for reading: {}",
+                resolved_path.display(),
+                e
+            )
+        })?;
 
     proc_result_value!(Expr::new_input_port(port))
 }
 
 fn open_output_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as open-output-file argument, got {}",
             expr.kind()
         )
     })?;
 
-    let resolved_path = resolve_path(&file_path.borrow(), env)?;
-    let port = FileOutputPort::from_path(resolved_path).map_err(|e| e.to_string())?;
+    let options = parse_port_options(args, "open-output-file")?;
+    let port = open_file_output_port(&file_path.borrow(), env, |path| {
+        let open_mode = options.open_mode.unwrap_or_default();
+        let port = FileOutputPort::from_path_with_options(path, open_mode, options.transcoder)?;
+        Ok(match options.buffer_mode {
+            Some(buffer_mode) => port.with_buffer_mode(buffer_mode),
+            None => port,
+        })
+    })?;
+
+    proc_result_value!(Expr::OutputPort(port))
+}
+
+fn open_input_string_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let string = args.pop_front().unwrap().into_string().map_err(|expr| {
+        type_error!(
+            "expected string as open-input-string argument, got {}",
+            expr.kind()
+        )
+    })?;
+
+    let port = StringInputPort::new(string.borrow().clone());
+
+    proc_result_value!(Expr::new_input_port(port))
+}
+
+// options given as trailing symbols of open-input-file and open-output-file,
+// e.g. (open-output-file "data.txt" 'latin-1 'replace 'line-buffered)
+#[derive(Default)]
+struct PortOptions {
+    transcoder: Transcoder,
+    buffer_mode: Option<BufferMode>,
+    open_mode: Option<FileOpenMode>,
+}
+
+fn parse_port_options(options: Exprs, proc_name: &str) -> Result<PortOptions, EvalError> {
+    let mut codec = None;
+    let mut error_handling_mode = None;
+    let mut buffer_mode = None;
+    let mut open_mode = None;
+
+    for option in options {
+        let option = option.into_symbol().map_err(|expr| {
+            type_error!(
+                "expected symbol as {} option, got {}",
+                proc_name,
+                expr.kind()
+            )
+        })?;
+        let name = option.as_str();
+        match name {
+            "utf-8" => set_port_option(&mut codec, Codec::Utf8, name, proc_name)?,
+            "latin-1" => set_port_option(&mut codec, Codec::Latin1, name, proc_name)?,
+            "utf-16le" => set_port_option(&mut codec, Codec::Utf16Le, name, proc_name)?,
+            "utf-16be" => set_port_option(&mut codec, Codec::Utf16Be, name, proc_name)?,
+            "raise" => set_port_option(
+                &mut error_handling_mode,
+                ErrorHandlingMode::Raise,
+                name,
+                proc_name,
+            )?,
+            "replace" => set_port_option(
+                &mut error_handling_mode,
+                ErrorHandlingMode::Replace,
+                name,
+                proc_name,
+            )?,
+            "unbuffered" => set_port_option(&mut buffer_mode, BufferMode::None, name, proc_name)?,
+            "line-buffered" => {
+                set_port_option(&mut buffer_mode, BufferMode::Line, name, proc_name)?
+            }
+            "block-buffered" => {
+                set_port_option(&mut buffer_mode, BufferMode::Block, name, proc_name)?
+            }
+            "truncate" => set_port_option(&mut open_mode, FileOpenMode::Truncate, name, proc_name)?,
+            "append" => set_port_option(&mut open_mode, FileOpenMode::Append, name, proc_name)?,
+            "exclusive" => set_port_option(
+                &mut open_mode,
+                FileOpenMode::CreateExclusive,
+                name,
+                proc_name,
+            )?,
+            _ => return Err(runtime_error!("unknown {} option: {}", proc_name, option)),
+        }
+    }
+
+    Ok(PortOptions {
+        transcoder: Transcoder::new(
+            codec.map(|(value, _)| value).unwrap_or_default(),
+            error_handling_mode
+                .map(|(value, _)| value)
+                .unwrap_or_default(),
+        ),
+        buffer_mode: buffer_mode.map(|(value, _)| value),
+        open_mode: open_mode.map(|(value, _)| value),
+    })
+}
+
+// sets an option of one kind, e.g. the codec, repeating the same option is allowed
+fn set_port_option<T>(
+    option: &mut Option<(T, String)>,
+    value: T,
+    name: &str,
+    proc_name: &str,
+) -> Result<(), EvalError> {
+    match option {
+        Some((_, previous)) if previous != name => Err(runtime_error!(
+            "conflicting {} options: {} and {}",
+            proc_name,
+            previous,
+            name
+        )),
+        _ => {
+            *option = Some((value, name.to_string()));
+            Ok(())
+        }
+    }
+}
+
+// opens a file output port and registers it, so it is flushed on exit
+fn open_file_output_port(
+    file_path: &str,
+    env: &EnvRef,
+    open: impl FnOnce(&Path) -> io::Result<FileOutputPort>,
+) -> Result<Rc<RefCell<dyn OutputPortSuperTrait>>, EvalError> {
+    let resolved_path = resolve_path(file_path, env)?;
+    let port = open(&resolved_path).map_err(|e| {
+        io_error!(
+            "could not open '{}' for writing: {}",
+            resolved_path.display(),
+            e
+        )
+    })?;
 
-    proc_result_value!(Expr::new_output_port(port))
+    let port: Rc<RefCell<dyn OutputPortSuperTrait>> = Rc::new(RefCell::new(port));
+    env.runtime().register_output_port(&port);
+    Ok(port)
+}
+
+// closes the port of a file with `close` when `thunk` returns,
+// so output is written to the file and the file is not kept open
+fn apply_and_close(
+    thunk: &Procedure,
+    close: impl FnOnce() -> io::Result<()>,
+    env: &mut EnvRef,
+) -> ProcedureResult {
+    let result = eval::apply_to_value(thunk, Exprs::new(), env);
+    let closed = close();
+    let value = result?;
+    closed.map_err(|e| io_error!("got error while closing port: {}", e))?;
+    proc_result_value!(value)
 }
 
 fn is_input_port_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
@@ -71,13 +247,17 @@ fn current_output_port_fn(_: Exprs, env: &mut EnvRef) -> ProcedureResult {
     proc_result_value!(Expr::OutputPort(env.current_output_port()))
 }
 
+fn current_error_port_fn(_: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    proc_result_value!(Expr::OutputPort(env.current_error_port()))
+}
+
 fn close_input_port_fn(mut exprs: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let port = exprs
         .pop_front()
         .unwrap()
         .into_input_port()
         .map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected input port as close-input-port argument, got {}",
                 expr.kind()
             )
@@ -95,7 +275,7 @@ fn close_output_port_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
         .unwrap()
         .into_output_port()
         .map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected output port as close-output-port argument, got {}",
                 expr.kind()
             )
@@ -109,92 +289,232 @@ fn close_output_port_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
 
 fn with_input_from_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as first with-input-from-file argument, got {}",
             expr.kind()
         )
     })?;
     let thunk = args.pop_front().unwrap().into_procedure().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected procedure as second with-input-from-file argument, got {}",
             expr.kind()
         )
     })?;
 
     let resolved_path = resolve_path(&file_path.borrow(), env)?;
-    let port = FileInputPort::from_path(resolved_path).map_err(|e| e.to_string())?;
+    let port = FileInputPort::from_path(&resolved_path).map_err(|e| {
+        io_error!(
+            "could not open '{}' for reading: {}",
+            resolved_path.display(),
+            e
+        )
+    })?;
 
+    let port: Rc<RefCell<dyn InputPortSuperTrait>> = Rc::new(RefCell::new(port));
     let mut eval_env = env.extend();
-    eval_env.set_current_input_port(Rc::new(RefCell::new(port)));
+    eval_env.set_current_input_port(port.clone());
 
-    thunk.apply(Exprs::new(), &mut eval_env)
+    apply_and_close(&thunk, || port.borrow_mut().close(), &mut eval_env)
 }
 
 fn with_output_to_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as first with-output-to-file argument, got {}",
             expr.kind()
         )
     })?;
     let thunk = args.pop_front().unwrap().into_procedure().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected procedure as second with-output-to-file argument, got {}",
             expr.kind()
         )
     })?;
 
-    let resolved_path = resolve_path(&file_path.borrow(), env)?;
-    let port = FileOutputPort::from_path(resolved_path).map_err(|e| e.to_string())?;
+    let port = open_file_output_port(&file_path.borrow(), env, |path| {
+        FileOutputPort::from_path(path)
+    })?;
 
     let mut eval_env = env.extend();
-    eval_env.set_current_output_port(Rc::new(RefCell::new(port)));
+    eval_env.set_current_output_port(port.clone());
 
-    thunk.apply(Exprs::new(), &mut eval_env)
+    apply_and_close(&thunk, || port.borrow_mut().close(), &mut eval_env)
+}
+
+fn with_error_to_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
+        type_error!(
+            "expected string as first with-error-to-file argument, got {}",
+            expr.kind()
+        )
+    })?;
+    let thunk = args.pop_front().unwrap().into_procedure().map_err(|expr| {
+        type_error!(
+            "expected procedure as second with-error-to-file argument, got {}",
+            expr.kind()
+        )
+    })?;
+
+    let port = open_file_output_port(&file_path.borrow(), env, |path| {
+        FileOutputPort::from_path(path)
+    })?;
+
+    let mut eval_env = env.extend();
+    eval_env.set_current_error_port(port.clone());
+
+    apply_and_close(&thunk, || port.borrow_mut().close(), &mut eval_env)
 }
 
 fn call_with_input_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as first call-with-input-file argument, got {}",
             expr.kind()
         )
     })?;
     let thunk = args.pop_front().unwrap().into_procedure().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected procedure as second call-with-input-file argument, got {}",
             expr.kind()
         )
     })?;
 
     let resolved_path = resolve_path(&file_path.borrow(), env)?;
-    let port = FileInputPort::from_path(resolved_path).map_err(|e| e.to_string())?;
+    let port = FileInputPort::from_path(&resolved_path).map_err(|e| {
+        io_error!(
+            "could not open '{}' for reading: {}",
+            resolved_path.display(),
+            e
+        )
+    })?;
 
+    let port: Rc<RefCell<dyn InputPortSuperTrait>> = Rc::new(RefCell::new(port));
     let mut eval_env = env.extend();
-    eval_env.set_current_input_port(Rc::new(RefCell::new(port)));
+    eval_env.set_current_input_port(port.clone());
 
-    thunk.apply(Exprs::new(), &mut eval_env)
+    apply_and_close(&thunk, || port.borrow_mut().close(), &mut eval_env)
 }
 
 fn call_with_output_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let file_path = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected string as first call-with-output-file argument, got {}",
             expr.kind()
         )
     })?;
     let thunk = args.pop_front().unwrap().into_procedure().map_err(|expr| {
-        runtime_error!(
+        type_error!(
             "expected procedure as second call-with-output-file argument, got {}",
             expr.kind()
         )
     })?;
 
-    let resolved_path = resolve_path(&file_path.borrow(), env)?;
-    let port = FileOutputPort::from_path(resolved_path).map_err(|e| e.to_string())?;
+    let port = open_file_output_port(&file_path.borrow(), env, |path| {
+        FileOutputPort::from_path(path)
+    })?;
 
     let mut eval_env = env.extend();
-    eval_env.set_current_output_port(Rc::new(RefCell::new(port)));
+    eval_env.set_current_output_port(port.clone());
 
-    thunk.apply(Exprs::new(), &mut eval_env)
+    apply_and_close(&thunk, || port.borrow_mut().close(), &mut eval_env)
+}
+
+fn flush_output_port_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    let port = match args.pop_front() {
+        Some(expr) => expr.into_output_port().map_err(|expr| {
+            type_error!(
+                "expected output port as flush-output-port argument, got {}",
+                expr.kind()
+            )
+        })?,
+        None => env.current_output_port(),
+    };
+
+    port.borrow_mut()
+        .flush()
+        .map_err(|e| runtime_error!("got error while flushing output port: {}", e))?;
+    proc_result_value!(Expr::Void)
+}
+
+fn port_line_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let port = args
+        .pop_front()
+        .unwrap()
+        .into_input_port()
+        .map_err(|expr| {
+            type_error!(
+                "expected input port as port-line argument, got {}",
+                expr.kind()
+            )
+        })?;
+
+    let line = port
+        .borrow()
+        .line()
+        .map_err(|e| runtime_error!("could not get port line: {}", e))?;
+    proc_result_value!(Expr::Integer(line as i64))
+}
+
+fn port_column_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let port = args
+        .pop_front()
+        .unwrap()
+        .into_input_port()
+        .map_err(|expr| {
+            type_error!(
+                "expected input port as port-column argument, got {}",
+                expr.kind()
+            )
+        })?;
+
+    let column = port
+        .borrow()
+        .column()
+        .map_err(|e| runtime_error!("could not get port column: {}", e))?;
+    proc_result_value!(Expr::Integer(column as i64))
+}
+
+fn port_position_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let port = args
+        .pop_front()
+        .unwrap()
+        .into_input_port()
+        .map_err(|expr| {
+            type_error!(
+                "expected input port as port-position argument, got {}",
+                expr.kind()
+            )
+        })?;
+
+    let position = port
+        .borrow_mut()
+        .position()
+        .map_err(|e| runtime_error!("could not get port position: {}", e))?;
+    proc_result_value!(Expr::Integer(position as i64))
+}
+
+fn set_port_position_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let port = args
+        .pop_front()
+        .unwrap()
+        .into_input_port()
+        .map_err(|expr| {
+            type_error!(
+                "expected input port as first set-port-position! argument, got {}",
+                expr.kind()
+            )
+        })?;
+    let position = args.pop_front().unwrap().into_integer().map_err(|expr| {
+        type_error!(
+            "expected integer as second set-port-position! argument, got {}",
+            expr.kind()
+        )
+    })?;
+    let position = u64::try_from(position)
+        .map_err(|_| runtime_error!("port position cannot be negative, got {}", position))?;
+
+    port.borrow_mut()
+        .set_position(position)
+        .map_err(|e| runtime_error!("could not set port position: {}", e))?;
+    proc_result_value!(Expr::Void)
 }
@@ -124,21 +124,25 @@ fn string_ge_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     proc_result_value!(Expr::Boolean(str1 >= str2))
 }
 
-fn make_string_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+fn make_string_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let k = args
         .pop_front()
         .unwrap()
         .into_integer()
         .map_err(|_| runtime_error!("make-string expected an integer as its first argument"))?;
+    let k: usize = k.try_into().map_err(|_| {
+        runtime_error!("make-string expected a non-negative integer as its first argument")
+    })?;
+    env.runtime().spend(k as u64)?;
 
     if args.is_empty() {
-        let result_string: String = "#".repeat(k as usize);
+        let result_string: String = "#".repeat(k);
         proc_result_value!(Expr::new_string(result_string))
     } else {
         let char_arg = args.pop_front().unwrap().into_char().map_err(|_| {
             runtime_error!("make-string expected a character as its second argument")
         })?;
-        let result_string: String = std::iter::repeat(char_arg).take(k as usize).collect();
+        let result_string: String = std::iter::repeat_n(char_arg, k).collect();
         proc_result_value!(Expr::new_string(result_string))
     }
 }
@@ -255,14 +259,20 @@ fn string_ref_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     proc_result_value!(Expr::Char(char_at_k))
 }
 
-fn string_append_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
-    let mut result_string = String::new();
-
-    while let Some(arg) = args.pop_front() {
-        let string_arg = arg
-            .into_string()
-            .map_err(|_| runtime_error!("string-append expected strings as arguments"))?;
-        result_string.push_str(&string_arg.borrow());
+fn string_append_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    let strings = args
+        .into_iter()
+        .map(|arg| {
+            arg.into_string()
+                .map_err(|_| runtime_error!("string-append expected strings as arguments"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let len: usize = strings.iter().map(|string| string.borrow().len()).sum();
+    env.runtime().spend(len as u64)?;
+
+    let mut result_string = String::with_capacity(len);
+    for string in strings {
+        result_string.push_str(&string.borrow());
     }
 
     proc_result_value!(Expr::new_string(result_string))
@@ -1,13 +1,15 @@
 use super::utils::{define_procedures, define_special_forms, read_exprs_from_path, resolve_path};
 use crate::{
-    evaluator::{error::runtime_error, eval, EnvRef},
+    evaluator::{
+        error::{runtime_error, type_error},
+        eval, EnvRef, ErrorKind, EvalError,
+    },
     expr::{proc_result_tailcall, proc_result_value, Arity, Expr, Exprs, ProcedureResult},
 };
 use std::{
     cell::RefCell,
     collections::HashMap,
     env, fs,
-    path::Path,
     rc::Rc,
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -21,7 +23,9 @@ define_procedures! {
     file_exists = ("file-exists?", file_exists_fn, Arity::Exact(1)),
     delete_file = ("delete-file", delete_file_fn, Arity::Exact(1)),
     error = ("error", error_fn, Arity::Exact(1)),
-    exit = ("exit", exit_fn, Arity::Exact(0)),
+    raise = ("raise", raise_fn, Arity::Exact(1)),
+    exit = ("exit", exit_fn, Arity::Range(0, 1)),
+    emergency_exit = ("emergency-exit", emergency_exit_fn, Arity::Range(0, 1)),
     current_second = ("current-second", current_second_fn, Arity::Exact(0)),
     command_line = ("command-line", command_line_fn, Arity::Exact(0)),
     get_environment_variables = ("get-environment-variables", get_environment_variables_fn, Arity::Exact(0)),
@@ -32,7 +36,7 @@ fn include_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     let mut exprs = Exprs::new();
     for (arg, idx) in args.into_iter().zip(1..) {
         let src_path = arg.into_string().map_err(|expr| {
-            runtime_error!(
+            type_error!(
                 "expected strings as arguments for include, got {} at position {}",
                 expr.kind(),
                 idx
@@ -48,10 +52,11 @@ fn include_fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult {
 }
 
 fn load_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
-    let src_path =
-        args.pop_front().unwrap().into_string().map_err(|expr| {
-            runtime_error!("expected string as load argument, got {}", expr.kind())
-        })?;
+    let src_path = args
+        .pop_front()
+        .unwrap()
+        .into_string()
+        .map_err(|expr| type_error!("expected string as load argument, got {}", expr.kind()))?;
     let src_path = resolve_path(&src_path.borrow(), env)?;
     let exprs = read_exprs_from_path(&src_path)?;
     let mut eval_env = env.extend();
@@ -61,8 +66,26 @@ fn load_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
     proc_result_value!(res)
 }
 
-fn exit_fn(_: Exprs, _: &mut EnvRef) -> ProcedureResult {
-    std::process::exit(0);
+fn exit_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    // exit is returned as an error, so evaluation is unwound and the host decides what to do
+    Err(EvalError::new(ErrorKind::Exit(exit_status(
+        args.pop_front(),
+    ))))
+}
+
+fn emergency_exit_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    Err(EvalError::new(ErrorKind::EmergencyExit(exit_status(
+        args.pop_front(),
+    ))))
+}
+
+// #f is an abnormal exit, integers are used as is and other objects are a normal exit
+fn exit_status(obj: Option<Expr>) -> i32 {
+    match obj {
+        Some(Expr::Boolean(false)) => 1,
+        Some(Expr::Integer(status)) => status as i32,
+        _ => 0,
+    }
 }
 
 fn current_second_fn(_: Exprs, _: &mut EnvRef) -> ProcedureResult {
@@ -73,44 +96,35 @@ fn current_second_fn(_: Exprs, _: &mut EnvRef) -> ProcedureResult {
     proc_result_value!(Expr::Float(current_time))
 }
 
-fn file_exists_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
-    let path_str = args
-        .pop_front()
-        .ok_or_else(|| runtime_error!("expected one argument for file-exists?, but got none"))?
-        .into_string()
-        .map_err(|expr| {
-            runtime_error!(
-                "expected string as argument for file-exists?, got {}",
-                expr.kind()
-            )
-        })?;
+fn file_exists_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    let path_str = args.pop_front().unwrap().into_string().map_err(|expr| {
+        type_error!(
+            "expected string as argument for file-exists?, got {}",
+            expr.kind()
+        )
+    })?;
 
-    let binding = path_str.borrow();
-    let path = Path::new(&*binding);
+    let path = resolve_path(&path_str.borrow(), env)?;
 
     let exists = path.exists();
 
     proc_result_value!(Expr::Boolean(exists))
 }
 
-fn delete_file_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
-    let path_str = args
-        .pop_front()
-        .ok_or_else(|| runtime_error!("expected one argument for delete-file, but got none"))?
-        .into_string()
-        .map_err(|expr| {
-            runtime_error!(
-                "expected string as argument for delete-file, got {}",
-                expr.kind()
-            )
-        })?;
+fn delete_file_fn(mut args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+    let path_str = args.pop_front().unwrap().into_string().map_err(|expr| {
+        type_error!(
+            "expected string as argument for delete-file, got {}",
+            expr.kind()
+        )
+    })?;
 
-    let path = path_str.borrow().clone();
+    let path = resolve_path(&path_str.borrow(), env)?;
 
     match fs::remove_file(&path) {
         Ok(_) => proc_result_value!(Expr::Void),
         Err(_) => {
-            runtime_error!("No such file or directory: {}", path);
+            runtime_error!("No such file or directory: {}", path.display());
             proc_result_value!(Expr::Void)
         }
     }
@@ -126,12 +140,18 @@ fn command_line_fn(_: Exprs, _: &mut EnvRef) -> ProcedureResult {
 }
 
 fn error_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
-    let msg = args.pop_front().unwrap().into_string().map_err(|expr| {
-        runtime_error!("expected string as argument of error, got {}", expr.kind())
-    })?;
+    let msg =
+        args.pop_front().unwrap().into_string().map_err(|expr| {
+            type_error!("expected string as argument of error, got {}", expr.kind())
+        })?;
     Err(runtime_error!("{}", (*msg).borrow().clone()))
 }
 
+fn raise_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let obj = args.pop_front().unwrap();
+    Err(EvalError::new(ErrorKind::Raise(obj)))
+}
+
 fn get_environment_variables_fn(_: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let env_vars: HashMap<String, String> = std::env::vars().collect();
     let mut result_list = Exprs::new();
@@ -146,18 +166,12 @@ fn get_environment_variables_fn(_: Exprs, _: &mut EnvRef) -> ProcedureResult {
 }
 
 fn get_environment_variable_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
-    let key = args
-        .pop_front()
-        .ok_or_else(|| {
-            runtime_error!("Expected one argument for get-environment-variable, but got none")
-        })?
-        .into_string()
-        .map_err(|expr| {
-            runtime_error!(
-                "Expected string as argument for get-environment-variable, got {}",
-                expr.kind()
-            )
-        })?;
+    let key = args.pop_front().unwrap().into_string().map_err(|expr| {
+        type_error!(
+            "Expected string as argument for get-environment-variable, got {}",
+            expr.kind()
+        )
+    })?;
 
     let value = match std::env::var(&*key.borrow()) {
         Ok(val) => Expr::String(Rc::new(RefCell::new(val))),
@@ -3,12 +3,14 @@ use crate::{
     evaluator::EnvRef,
     expr::{proc_result_value, Arity, Expr, Exprs, ProcedureResult},
 };
+use std::collections::HashSet;
 
 define_procedures! {
     is_char = ("char?", is_char_fn, Arity::Exact(1)),
     is_number = ("number?", is_number_fn, Arity::Exact(1)),
     is_string = ("string?", is_string_fn, Arity::Exact(1)),
     is_pair = ("pair?", is_pair_fn, Arity::Exact(1)),
+    is_list = ("list?", is_list_fn, Arity::Exact(1)),
     is_procedure = ("procedure?", is_procedure_fn, Arity::Exact(1)),
     is_symbol = ("symbol?", is_symbol_fn, Arity::Exact(1)),
     is_input_port = ("input-port?", is_input_port_fn, Arity::Exact(1)),
@@ -41,12 +43,29 @@ fn is_pair_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let expr = args.pop_front().unwrap();
     let is_type = match expr {
         Expr::List(list) => !list.is_empty(),
+        Expr::Pair(_) => true,
         _ => false,
     };
 
     proc_result_value!(Expr::Boolean(is_type))
 }
 
+// cyclic lists are not lists, they are found by pairs which occur twice
+fn is_list_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+    let mut expr = args.pop_front().unwrap();
+    let mut pairs = HashSet::new();
+    let is_type = loop {
+        expr = match expr {
+            Expr::List(list) if list.is_proper() => break true,
+            Expr::List(list) => list.into_iter().last().unwrap(),
+            Expr::Pair(pair) if pairs.insert(pair.as_ptr()) => pair.cdr().clone(),
+            _ => break false,
+        }
+    };
+
+    proc_result_value!(Expr::Boolean(is_type))
+}
+
 fn is_procedure_fn(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     let expr = args.pop_front().unwrap();
     let is_type = expr.is_procedure();
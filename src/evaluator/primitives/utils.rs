@@ -1,11 +1,14 @@
 use std::{
     fs,
     ops::Deref,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use crate::{
-    evaluator::{error::runtime_error, EnvRef, EvalError, EvalResult},
+    evaluator::{
+        error::{io_error, runtime_error, type_error},
+        EnvRef, ErrorKind, EvalError, EvalResult,
+    },
     expr::{Body, Expr, Exprs, ListKind, Procedure, ProcedureParams},
     parser,
 };
@@ -58,7 +61,7 @@ pub fn create_procedure(
                 .into_iter()
                 .map(|expr| {
                     expr.into_symbol().map_err(|expr| {
-                        runtime_error!("expected symbols in lambda params, got {}", expr.kind())
+                        type_error!("expected symbols in lambda params, got {}", expr.kind())
                     })
                 })
                 .collect::<Result<Vec<String>, EvalError>>()?;
@@ -84,23 +87,84 @@ pub fn create_procedure(
 }
 
 pub fn resolve_path(path: &str, env: &EnvRef) -> Result<PathBuf, EvalError> {
-    let path = shellexpand::tilde(path);
-    let path = Path::new(path.deref());
-    if path.is_absolute() {
-        return Ok(path.into());
+    let expanded_path = shellexpand::tilde(path);
+    let expanded_path = Path::new(expanded_path.deref());
+    let src_path = if expanded_path.is_absolute() {
+        expanded_path.into()
+    } else {
+        env.cwd().join(expanded_path)
+    };
+
+    match env.runtime().root_dir() {
+        Some(root_dir) => {
+            let outside_root = || {
+                EvalError::new(ErrorKind::PermissionDenied(format!(
+                    "{} is outside of the root directory",
+                    path
+                )))
+            };
+            // resolved path is returned, so the checked file is the one which is accessed
+            let resolved_path =
+                resolve_symlinks(&normalize_path(&src_path)).ok_or_else(outside_root)?;
+            if resolved_path.starts_with(&root_dir) {
+                Ok(resolved_path)
+            } else {
+                Err(outside_root())
+            }
+        }
+        None => Ok(src_path),
     }
+}
 
-    let cwd = env.cwd();
-    let src_path = cwd.join(path);
+// removes `.` and `..` components without accessing the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
 
-    Ok(src_path)
+// resolves symlinks in the longest part of normalized `path` that exists,
+// returns `None` if it ends with a symlink which can not be resolved,
+// because opening the path would follow it to an unchecked location
+fn resolve_symlinks(path: &Path) -> Option<PathBuf> {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return Some(
+                missing
+                    .iter()
+                    .rev()
+                    .fold(resolved, |path, name| path.join(name)),
+            );
+        }
+        // a dangling symlink exists, but can not be canonicalized
+        if existing.symlink_metadata().is_ok() {
+            return None;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return Some(path.to_path_buf()),
+        }
+    }
 }
 
 pub fn read_exprs_from_path<P: AsRef<Path>>(src_path: P) -> Result<Exprs, EvalError> {
     let src_path = src_path.as_ref();
     let src = fs::read_to_string(src_path)
-        .map_err(|err| runtime_error!("failed to read file {}: {}", src_path.display(), err))?;
+        .map_err(|err| io_error!("failed to read file {}: {}", src_path.display(), err))?;
 
-    parser::parse_str(&src)
-        .map_err(|err| runtime_error!("failed to parse file {}: {}", src_path.display(), err))
+    // errors are located in the file, so its name is not repeated
+    parser::parse_str_with_file(&src, &src_path.display().to_string()).map_err(EvalError::from)
 }
@@ -26,7 +26,7 @@ impl ApplyProcedure for Procedure {
 impl ApplyProcedure for AtomicProcedure {
     fn apply(&self, args: Exprs, env: &mut EnvRef) -> ProcedureResult {
         args.validate_arity(self.name(), self.arity())?;
-        (self.proc())(args, env)
+        self.call(args, env)
     }
 }
 
@@ -36,6 +36,7 @@ impl ApplyProcedure for CompoundProcedure {
         eval_env.set_cwd(env.cwd());
         eval_env.set_current_input_port(env.current_input_port());
         eval_env.set_current_output_port(env.current_output_port());
+        eval_env.set_current_error_port(env.current_error_port());
 
         match self.params.clone() {
             ProcedureParams::Fixed(params) => {
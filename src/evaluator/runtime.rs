@@ -0,0 +1,285 @@
+use super::error::{ErrorKind, EvalError};
+use crate::expr::OutputPortSuperTrait;
+use std::{
+    cell::{Cell, RefCell},
+    io,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+type OutputPortRef = Rc<RefCell<dyn OutputPortSuperTrait>>;
+
+/// Default limit of nested evaluations, see [`Runtime::enter`].
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+// reading the clock is slower than a step, so the deadline is checked once per this many steps
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// Bounds how long an evaluation may run.
+///
+/// By default evaluation is not bounded.
+///
+/// # Examples
+/// ```
+/// use lispdm::Limits;
+/// use std::time::Duration;
+/// let limits = Limits::new()
+///     .with_fuel(1_000_000)
+///     .with_timeout(Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    fuel: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+impl Limits {
+    /// Creates limits, which do not bound evaluation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits evaluation to `fuel` steps.
+    ///
+    /// A step is spent on evaluation of each expression and on each tail call.
+    /// Primitives, which build strings or lists of a requested size, spend a step per element,
+    /// other primitives run as a single step, so the limit is approximate.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Limits evaluation to finish before `deadline`.
+    ///
+    /// The deadline is checked between steps, so a primitive, which is running when it passes,
+    /// finishes before evaluation fails.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Limits evaluation to finish within `timeout` from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Returns the number of steps evaluation may take.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Returns the time by which evaluation has to finish.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+}
+
+/// Interrupts evaluation of an engine, possibly from another thread.
+///
+/// Evaluation, which is running when it is interrupted, fails with
+/// [`ErrorKind::Interrupted`](crate::ErrorKind::Interrupted).
+/// Environment of the engine is kept, so the engine can be used after it.
+///
+/// # Examples
+/// ```
+/// use lispdm::{Engine, ErrorKind};
+/// use std::{thread, time::Duration};
+/// let mut engine = Engine::default();
+/// let handle = engine.interrupt_handle();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_millis(10));
+///     handle.interrupt();
+/// });
+/// let err = engine.eval::<()>("(let loop () (loop))").unwrap_err();
+/// assert_eq!(err.kind(), &ErrorKind::Interrupted);
+/// ```
+#[derive(Debug, Clone)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    /// Interrupts the running evaluation.
+    ///
+    /// Interrupting an engine, which is not evaluating, has no effect.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// State shared by all environments of a single engine.
+#[derive(Debug)]
+pub struct Runtime {
+    // ports are held weakly so registering a port does not keep it open
+    output_ports: RefCell<Vec<Weak<RefCell<dyn OutputPortSuperTrait>>>>,
+    depth: Cell<usize>,
+    max_depth: Cell<usize>,
+    fuel: Cell<Option<u64>>,
+    deadline: Cell<Option<Instant>>,
+    steps: Cell<u32>,
+    interrupted: Arc<AtomicBool>,
+    root_dir: RefCell<Option<PathBuf>>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            output_ports: RefCell::default(),
+            depth: Cell::new(0),
+            max_depth: Cell::new(DEFAULT_MAX_DEPTH),
+            fuel: Cell::new(None),
+            deadline: Cell::new(None),
+            steps: Cell::new(0),
+            interrupted: Arc::default(),
+            root_dir: RefCell::new(None),
+        }
+    }
+}
+
+impl Runtime {
+    /// Returns the directory, which files accessed by scripts have to be in.
+    pub fn root_dir(&self) -> Option<PathBuf> {
+        self.root_dir.borrow().clone()
+    }
+
+    /// Confines files accessed by scripts to `root_dir`.
+    pub fn set_root_dir<P: AsRef<Path>>(&self, root_dir: P) {
+        *self.root_dir.borrow_mut() = Some(root_dir.as_ref().to_path_buf());
+    }
+
+    /// Returns the maximum number of nested evaluations.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth.get()
+    }
+
+    /// Sets the maximum number of nested evaluations.
+    pub fn set_max_depth(&self, max_depth: usize) {
+        self.max_depth.set(max_depth);
+    }
+
+    /// Starts a nested evaluation, which lasts until the returned guard is dropped.
+    ///
+    /// Fails if the evaluation would be nested deeper than [`max_depth`](#method.max_depth).
+    pub fn enter(self: &Rc<Self>) -> Result<DepthGuard, EvalError> {
+        let depth = self.depth.get();
+        if depth >= self.max_depth.get() {
+            return Err(EvalError::new(ErrorKind::RecursionDepthExceeded(
+                self.max_depth.get(),
+            )));
+        }
+        self.depth.set(depth + 1);
+        Ok(DepthGuard(self.clone()))
+    }
+
+    /// Bounds evaluation by `limits`, until they are replaced.
+    pub fn set_limits(&self, limits: Limits) {
+        self.fuel.set(limits.fuel);
+        self.deadline.set(limits.deadline);
+        self.steps.set(0);
+    }
+
+    /// Returns a handle, which interrupts evaluation.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle(self.interrupted.clone())
+    }
+
+    /// Forgets interrupts requested before evaluation has started.
+    pub fn clear_interrupt(&self) {
+        self.interrupted.store(false, Ordering::Relaxed);
+    }
+
+    /// Spends a step of evaluation.
+    ///
+    /// Fails if evaluation was interrupted, fuel is exhausted or the deadline has passed.
+    pub fn step(&self) -> Result<(), EvalError> {
+        if self.interrupted.swap(false, Ordering::Relaxed) {
+            return Err(EvalError::new(ErrorKind::Interrupted));
+        }
+        if let Some(fuel) = self.fuel.get() {
+            if fuel == 0 {
+                return Err(EvalError::new(ErrorKind::OutOfFuel));
+            }
+            self.fuel.set(Some(fuel - 1));
+        }
+        if let Some(deadline) = self.deadline.get() {
+            let steps = self.steps.get().wrapping_add(1);
+            self.steps.set(steps);
+            if steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(EvalError::new(ErrorKind::DeadlineExceeded));
+            }
+        }
+        Ok(())
+    }
+
+    /// Spends `steps` steps of evaluation at once, for primitives doing work proportional to them.
+    ///
+    /// Fails same as [`step`](#method.step), the deadline is checked on every call.
+    pub fn spend(&self, steps: u64) -> Result<(), EvalError> {
+        if self.interrupted.swap(false, Ordering::Relaxed) {
+            return Err(EvalError::new(ErrorKind::Interrupted));
+        }
+        if let Some(fuel) = self.fuel.get() {
+            if fuel < steps {
+                self.fuel.set(Some(0));
+                return Err(EvalError::new(ErrorKind::OutOfFuel));
+            }
+            self.fuel.set(Some(fuel - steps));
+        }
+        if let Some(deadline) = self.deadline.get() {
+            if Instant::now() >= deadline {
+                return Err(EvalError::new(ErrorKind::DeadlineExceeded));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remembers `port`, so its buffered output is written by [`flush_output_ports`].
+    ///
+    /// [`flush_output_ports`]: #method.flush_output_ports
+    pub fn register_output_port(&self, port: &OutputPortRef) {
+        let mut output_ports = self.output_ports.borrow_mut();
+        // dropped ports are forgotten before the list grows, so it is bounded by open ports
+        if output_ports.len() == output_ports.capacity() {
+            output_ports.retain(|port| port.strong_count() > 0);
+        }
+        output_ports.push(Rc::downgrade(port));
+    }
+
+    /// Flushes every registered output port that is still open.
+    ///
+    /// All ports are flushed even if some of them fail, the first error is returned.
+    pub fn flush_output_ports(&self) -> io::Result<()> {
+        let mut result = Ok(());
+        self.output_ports.borrow_mut().retain(|port| {
+            let port = match port.upgrade() {
+                Some(port) => port,
+                None => return false,
+            };
+            // port is borrowed only if flushing was requested while writing to it
+            if let Ok(mut port) = port.try_borrow_mut() {
+                if !port.is_closed() {
+                    let flushed = port.flush();
+                    if result.is_ok() {
+                        result = flushed;
+                    }
+                }
+            }
+            true
+        });
+        result
+    }
+}
+
+/// Nested evaluation started with [`Runtime::enter`].
+///
+/// Depth is restored on drop, so it is also restored when evaluation fails.
+pub struct DepthGuard(Rc<Runtime>);
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        self.0.depth.set(self.0.depth.get() - 1);
+    }
+}
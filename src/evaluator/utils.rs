@@ -1,14 +1,15 @@
-use super::error::{runtime_error, EvalError};
+use super::error::{ErrorKind, EvalError};
 use crate::expr::{Arity, Expr, Exprs};
 
-pub trait CheckArity<T> {
-    // pop the first argument or return an expected arity error
-    fn pop_front_or_expected_arity(&mut self, name: &str, arity: Arity) -> Result<T, EvalError>;
-
-    // check that there are no more arguments
-    // if there are, return an expected arity error
-    fn empty_or_expected_arity(&self, name: &str, arity: Arity) -> Result<(), EvalError>;
+fn arity_error(name: &str, expected: Arity, got: usize) -> EvalError {
+    EvalError::new(ErrorKind::Arity {
+        procedure: name.to_string(),
+        expected,
+        got,
+    })
+}
 
+pub trait CheckArity<T> {
     // check that there are at least `min` arguments
     // returns the length of the arguments
     // if there are less, return an expected arity error
@@ -30,7 +31,7 @@ pub trait CheckArity<T> {
     ) -> Result<(), EvalError> {
         match self.at_least_or_expected_arity(count, name, arity)? {
             len if len == count => Ok(()),
-            _ => Err(runtime_error!("expected {} arguments for {}", arity, name)),
+            len => Err(arity_error(name, arity, len)),
         }
     }
 
@@ -43,7 +44,7 @@ pub trait CheckArity<T> {
     ) -> Result<(), EvalError> {
         match self.at_least_or_expected_arity(min, name, arity)? {
             len if len <= max => Ok(()),
-            _ => Err(runtime_error!("expected {} arguments for {}", arity, name)),
+            len => Err(arity_error(name, arity, len)),
         }
     }
 
@@ -60,18 +61,6 @@ pub trait CheckArity<T> {
 }
 
 impl CheckArity<Expr> for Exprs {
-    fn pop_front_or_expected_arity(&mut self, name: &str, arity: Arity) -> Result<Expr, EvalError> {
-        self.pop_front()
-            .ok_or(runtime_error!("expected {} arguments for {}", arity, name))
-    }
-
-    fn empty_or_expected_arity(&self, name: &str, arity: Arity) -> Result<(), EvalError> {
-        match self.is_empty() {
-            true => Ok(()),
-            false => Err(runtime_error!("expected {} arguments for {}", arity, name)),
-        }
-    }
-
     fn at_least_or_expected_arity(
         &self,
         min: usize,
@@ -79,7 +68,7 @@ impl CheckArity<Expr> for Exprs {
         arity: Arity,
     ) -> Result<usize, EvalError> {
         if self.len() < min {
-            Err(runtime_error!("expected {} arguments for {}", arity, name,))
+            Err(arity_error(name, arity, self.len()))
         } else {
             Ok(self.len())
         }
@@ -0,0 +1,93 @@
+use super::{
+    expr::{Expr, Exprs},
+    procedure::Procedure,
+};
+use crate::evaluator::{EnvRef, ErrorKind, LispDMError};
+
+/// Arguments of a procedure called with [`Engine::call`](crate::Engine::call).
+///
+/// Implemented for tuples with up to 8 values, which can be converted into [`Expr`],
+/// and for [`Exprs`], which are passed as they are.
+pub trait IntoArgs {
+    /// Converts values into arguments of the procedure.
+    fn into_args(self) -> Exprs;
+}
+
+impl IntoArgs for Exprs {
+    fn into_args(self) -> Exprs {
+        self
+    }
+}
+
+macro_rules! impl_into_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: Into<Expr>,)*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case, unused_mut)]
+            fn into_args(self) -> Exprs {
+                let ($($arg,)*) = self;
+                let mut args = Exprs::new();
+                $(args.push_back($arg.into());)*
+                args
+            }
+        }
+    };
+}
+
+impl_into_args!();
+impl_into_args!(A1);
+impl_into_args!(A1, A2);
+impl_into_args!(A1, A2, A3);
+impl_into_args!(A1, A2, A3, A4);
+impl_into_args!(A1, A2, A3, A4, A5);
+impl_into_args!(A1, A2, A3, A4, A5, A6);
+impl_into_args!(A1, A2, A3, A4, A5, A6, A7);
+impl_into_args!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+/// Procedure called with [`Engine::call`](crate::Engine::call).
+///
+/// Implemented for names of procedures, which are looked up in the root environment,
+/// and for [`Procedure`] handles.
+pub trait Callee {
+    /// Returns the procedure to call.
+    fn into_procedure(self, env: &EnvRef) -> Result<Procedure, LispDMError>;
+}
+
+impl Callee for Procedure {
+    fn into_procedure(self, _: &EnvRef) -> Result<Procedure, LispDMError> {
+        Ok(self)
+    }
+}
+
+impl Callee for &Procedure {
+    fn into_procedure(self, _: &EnvRef) -> Result<Procedure, LispDMError> {
+        Ok(self.clone())
+    }
+}
+
+impl Callee for &str {
+    fn into_procedure(self, env: &EnvRef) -> Result<Procedure, LispDMError> {
+        match env.get_expr(self) {
+            Some(Expr::Procedure(proc)) => Ok(proc),
+            Some(expr) => Err(LispDMError::new(ErrorKind::TypeMismatch(format!(
+                "expected procedure as {}, got {}",
+                self,
+                expr.kind()
+            )))),
+            None => Err(LispDMError::new(ErrorKind::UnboundVariable(
+                self.to_string(),
+            ))),
+        }
+    }
+}
+
+impl Callee for String {
+    fn into_procedure(self, env: &EnvRef) -> Result<Procedure, LispDMError> {
+        self.as_str().into_procedure(env)
+    }
+}
+
+impl Callee for &String {
+    fn into_procedure(self, env: &EnvRef) -> Result<Procedure, LispDMError> {
+        self.as_str().into_procedure(env)
+    }
+}
@@ -0,0 +1,137 @@
+//! Support of conversions generated by `#[derive(FromExpr, IntoExpr)]`.
+//!
+//! Structs with named fields are association lists `((field . value) ...)`,
+//! tuple structs are lists of values, and variants of enums are lists tagged with their name,
+//! e.g. `(circle (radius . 1.0))`. Names of fields and variants are written in kebab case.
+
+use super::{
+    expr::{Expr, Exprs, FromExpr},
+    list::List,
+};
+use crate::exprs;
+
+/// Creates an entry of an association list `(name . value)`.
+pub fn field<T: Into<Expr>>(name: &str, value: T) -> Expr {
+    Expr::new_dotted_list(exprs![Expr::Symbol(name.to_string()), value.into()])
+}
+
+/// Creates an entry of an association list `(name . value)`, if there is a `value`.
+pub fn optional_field<T: Into<Expr>>(name: &str, value: Option<T>) -> Option<Expr> {
+    value.map(|value| field(name, value))
+}
+
+/// Creates a tagged list `(tag values ...)`.
+pub fn variant(tag: &str, mut values: Exprs) -> Expr {
+    values.push_front(Expr::Symbol(tag.to_string()));
+    Expr::new_proper_list(values)
+}
+
+/// Splits an entry of an association list into its key and value.
+pub fn split_entry(entry: &Expr) -> Option<(&Expr, Expr)> {
+    let list = entry.as_list()?;
+    let key = list.car()?;
+    let value = if list.is_dotted() && list.len() == 2 {
+        list.last()?.clone()
+    } else {
+        let rest: Exprs = list.cdr().cloned().collect();
+        Expr::new_list(rest, list.kind())
+    };
+    Some((key, value))
+}
+
+/// Checks if `expr` is a proper list of entries `(key . value)`.
+pub fn is_alist(expr: &Expr) -> bool {
+    match expr.as_list() {
+        Some(list) => list.is_proper() && list.iter().all(|entry| split_entry(entry).is_some()),
+        None => false,
+    }
+}
+
+/// Returns value of the first entry of an association list, which key is the symbol `name`.
+pub fn alist_get(expr: &Expr, name: &str) -> Option<Expr> {
+    expr.as_list()?
+        .iter()
+        .find_map(|entry| match split_entry(entry)? {
+            (Expr::Symbol(key), value) if key == name => Some(value),
+            _ => None,
+        })
+}
+
+/// Returns elements of a proper list with `len` elements.
+pub fn list_items(expr: &Expr, len: usize) -> Option<Exprs> {
+    match expr.as_list() {
+        Some(list) if list.is_proper() && list.len() == len => Some(list.iter().cloned().collect()),
+        _ => None,
+    }
+}
+
+/// Splits a tagged list into its tag and the list of the rest of elements.
+pub fn split_variant(expr: &Expr) -> Option<(String, Expr)> {
+    let list = expr.as_list()?;
+    match list.car()? {
+        Expr::Symbol(tag) if list.is_proper() => {
+            let rest = List::new_proper(list.cdr().cloned().collect());
+            Some((tag.clone(), Expr::List(rest)))
+        }
+        _ => None,
+    }
+}
+
+/// Converts `value`, if there is one.
+pub fn convert<T: FromExpr>(value: Option<Expr>) -> Option<T> {
+    T::from_expr(value?).ok()
+}
+
+/// Converts value of an optional field, which is `None` if it is missing.
+///
+/// `#f` is `None` only if it can not be converted into `T`, so `Option<bool>` fields round-trip.
+pub fn convert_optional<T: FromExpr>(value: Option<Expr>) -> Option<Option<T>> {
+    match value {
+        Some(value) => match T::from_expr(value) {
+            Ok(value) => Some(Some(value)),
+            Err(Expr::Boolean(false)) => Some(None),
+            Err(_) => None,
+        },
+        None => Some(None),
+    }
+}
+
+/// Describes why a field `name` with `value` can not be converted into `T`.
+///
+/// Returns `None` if it can be converted.
+pub fn field_mismatch<T: FromExpr>(name: &str, value: Option<Expr>) -> Option<String> {
+    match value {
+        Some(value) => mismatch::<T>(&format!("in field {}", name), value),
+        None => Some(format!("missing field {}", name)),
+    }
+}
+
+/// Describes why an optional field `name` with `value` can not be converted into `T`.
+///
+/// Returns `None` if it can be converted.
+pub fn optional_field_mismatch<T: FromExpr>(name: &str, value: Option<Expr>) -> Option<String> {
+    match value {
+        Some(Expr::Boolean(false)) | None => None,
+        Some(value) => mismatch::<T>(&format!("in field {}", name), value),
+    }
+}
+
+/// Describes why an element at `position` of a list can not be converted into `T`.
+///
+/// Returns `None` if it can be converted.
+pub fn item_mismatch<T: FromExpr>(position: usize, value: Expr) -> Option<String> {
+    mismatch::<T>(&format!("as element {}", position), value)
+}
+
+fn mismatch<T: FromExpr>(place: &str, value: Expr) -> Option<String> {
+    let detail = T::mismatch_detail(&value);
+    let kind = value.kind();
+    if T::from_expr(value).is_ok() {
+        return None;
+    }
+    let message = format!("expected {} {}, got {}", T::expected_kind(), place, kind);
+    Some(match detail {
+        Some(detail) => format!("{}: {}", message, detail),
+        None => message,
+    })
+}
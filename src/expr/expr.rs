@@ -1,10 +1,18 @@
 use super::{
+    derive,
     list::{List, ListKind},
+    pair::Pair,
     procedure::Procedure,
-    InputPortSuperTrait, OutputPortSuperTrait,
+    Foreign, InputPortSuperTrait, OutputPortSuperTrait, Printer, WriteMode,
 };
 use core::fmt;
-use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, VecDeque},
+    hash::Hash,
+    path::PathBuf,
+    rc::Rc,
+};
 
 #[derive(Debug, Clone)]
 /// Represents all possible values in interpreter.
@@ -35,6 +43,8 @@ pub enum Expr {
     /// NOTE: Since lists are immutable, they are flattened on creation.
     /// This means that `(1 2 3)` and `(1 . (2 . (3 . '()))` are represented as same list internally.
     List(List),
+    /// Pair shared by reference, which cyclic data read by the parser are made of
+    Pair(Pair),
     /// Unspecified value
     ///
     /// <div class="warning">
@@ -46,13 +56,15 @@ pub enum Expr {
     /// <div class="warning">
     /// This expression is created only on evaluation stage and evaluating it will cause error.
     ///
-    /// Because of this, internal <code>Procedure</code> struct does not exported, so you can't construct procedure directly.
+    /// Because of this, you can't construct [`Procedure`] directly, it is taken from evaluated expressions.
     /// </div>
     Procedure(Procedure),
     /// Input port
     InputPort(Rc<RefCell<dyn InputPortSuperTrait>>),
     /// Output port
     OutputPort(Rc<RefCell<dyn OutputPortSuperTrait>>),
+    /// Opaque value of the host, see [`Foreign`]
+    Foreign(Foreign),
 }
 
 impl PartialEq for Expr {
@@ -65,10 +77,12 @@ impl PartialEq for Expr {
             (Expr::Char(a), Expr::Char(b)) => a == b,
             (Expr::Boolean(a), Expr::Boolean(b)) => a == b,
             (Expr::List(a), Expr::List(b)) => a == b,
+            (Expr::Pair(a), Expr::Pair(b)) => a == b,
             (Expr::Void, Expr::Void) => true,
             (Expr::Procedure(a), Expr::Procedure(b)) => a == b,
             (Expr::InputPort(a), Expr::InputPort(b)) => Rc::ptr_eq(a, b),
             (Expr::OutputPort(a), Expr::OutputPort(b)) => Rc::ptr_eq(a, b),
+            (Expr::Foreign(a), Expr::Foreign(b)) => a == b,
             _ => false,
         }
     }
@@ -166,10 +180,6 @@ impl Expr {
         Self::InputPort(Rc::new(RefCell::new(port)))
     }
 
-    pub(crate) fn new_output_port(port: impl OutputPortSuperTrait + 'static) -> Self {
-        Self::OutputPort(Rc::new(RefCell::new(port)))
-    }
-
     /// Returns string representation of the type of `self`
     // Note: This method is named `kind` instead of `type` because `type` is a reserved keyword
     pub fn kind(&self) -> &'static str {
@@ -184,10 +194,12 @@ impl Expr {
                 ListKind::Proper => "list",
                 ListKind::Dotted => "dotted list",
             },
+            Expr::Pair(_) => "pair",
             Expr::Void => "void",
             Expr::Procedure(_) => "procedure",
             Expr::InputPort(_) => "input_port",
             Expr::OutputPort(_) => "output_port",
+            Expr::Foreign(_) => "foreign",
         }
     }
 
@@ -277,6 +289,11 @@ impl Expr {
         matches!(self, Expr::OutputPort(_))
     }
 
+    /// Checks if `self` is a [`Expr::Foreign`]
+    pub fn is_foreign(&self) -> bool {
+        matches!(self, Expr::Foreign(_))
+    }
+
     // exctraction methods
 
     /// Tries to convert `self` into `T`, which is a type that implements [`FromExpr`]
@@ -354,6 +371,13 @@ impl Expr {
         }
     }
 
+    pub(crate) fn into_foreign(self) -> FromExprResult<Foreign> {
+        match self {
+            Expr::Foreign(foreign) => Ok(foreign),
+            _ => Err(self),
+        }
+    }
+
     pub(crate) fn as_list(&self) -> Option<&List> {
         match self {
             Expr::List(list) => Some(list),
@@ -364,19 +388,13 @@ impl Expr {
 
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Expr::Void => write!(f, "#<void>"),
-            Expr::Integer(int) => write!(f, "{}", int),
-            Expr::Float(float) => write!(f, "{}", float),
-            Expr::Symbol(symbol) => write!(f, "{}", symbol),
-            Expr::String(string) => write!(f, "\"{}\"", string.borrow()),
-            Expr::Char(ch) => write!(f, "'{}'", ch),
-            Expr::Procedure(proc) => write!(f, "{}", proc),
-            Expr::Boolean(bool) => write!(f, "{}", if *bool { "#t" } else { "#f" }),
-            Expr::List(list) => write!(f, "{}", list),
-            Expr::InputPort(port) => write!(f, "{}", port.borrow()),
-            Expr::OutputPort(port) => write!(f, "{}", port.borrow()),
-        }
+        write!(f, "{}", Printer::new(self, WriteMode::Write))
+    }
+}
+
+impl From<()> for Expr {
+    fn from(_: ()) -> Self {
+        Expr::Void
     }
 }
 
@@ -462,6 +480,33 @@ impl<A: Into<Expr>, B: Into<Expr>> From<(A, B)> for Expr {
     }
 }
 
+impl<T: Into<Expr>> From<Option<T>> for Expr {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(value) => value.into(),
+            None => Expr::Boolean(false),
+        }
+    }
+}
+
+impl<K: Into<Expr>, V: Into<Expr>> From<HashMap<K, V>> for Expr {
+    fn from(map: HashMap<K, V>) -> Self {
+        map.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<K: Into<Expr>, V: Into<Expr>> From<BTreeMap<K, V>> for Expr {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        map.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl From<PathBuf> for Expr {
+    fn from(path: PathBuf) -> Self {
+        Expr::new_string(path.to_string_lossy())
+    }
+}
+
 /// The exit point for turning [`Expr`] into Rust types.
 ///
 /// This trait implemented for most primitives.
@@ -475,15 +520,55 @@ impl<A: Into<Expr>, B: Into<Expr>> From<(A, B)> for Expr {
 /// | [`Expr::Char`] | [`char`]
 /// | [`Expr::Integer`] | [`i64`]
 /// | [`Expr::Float`] | [`f64`]
-/// | [`Expr::Symbol`] | [`String`]
-/// | [`Expr::String`] | [`Rc<RefCell<String>>`]
+/// | [`Expr::Symbol`] or [`Expr::String`] | [`String`]
+/// | [`Expr::String`] | [`Rc<RefCell<String>>`] or [`PathBuf`]
 /// | [`Expr::Port`] | [`Rc<RefCell<Port>>`]
 /// | [`Expr::List`] | [`List`]
 /// | proper [`Expr::List`] | [`Vec<T>`] or [`VecDeque<T>`], where `T` is a type that implements [`FromExpr`]
 /// | dotted [`Expr::List`] with 2 elements | `(A, B)`, where `A` and `B` are types that implements [`FromExpr`]
+/// | association list `((key . value) ...)` | [`HashMap<K, V>`] or [`BTreeMap<K, V>`], where `K` and `V` are types that implements [`FromExpr`]
+/// | `#f` or any other [`Expr`] | [`Option<T>`], where `#f` is `None`
+///
+/// `None` is converted into `#f`, so `Some(false)` of `Option<bool>` is read back as `None`.
+///
+/// Structs and enums can implement it with `#[derive(FromExpr)]`, when `derive` feature is enabled.
 pub trait FromExpr: Sized {
     /// Tries to convert [`Expr`] into `Self`. Returns [`FromExprResult<Self>`] with result of conversion.
     fn from_expr(expr: Expr) -> FromExprResult<Self>;
+
+    /// Describes expressions, which can be converted into `Self`.
+    ///
+    /// Used in errors, e.g. when an argument of a registered Rust function has a wrong type.
+    /// Defaults to the name of the Rust type.
+    fn expected_kind() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Explains why `expr` can not be converted into `Self`, if there is more to it than its kind.
+    ///
+    /// Used in errors after [`expected_kind`](#tymethod.expected_kind),
+    /// e.g. to name a field of a derived struct, which has a wrong type. Defaults to `None`.
+    fn mismatch_detail(expr: &Expr) -> Option<String> {
+        let _ = expr;
+        None
+    }
+    /// Describes why `expr` can not be converted into `Self`,
+    /// e.g. to report the expression returned by [`Engine::eval`](crate::Engine::eval).
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Engine, FromExpr};
+    /// let mut engine = Engine::default();
+    /// let expr = engine.eval::<Vec<i64>>("(+ 1 2)").unwrap().unwrap_err();
+    /// assert_eq!(Vec::<i64>::describe_mismatch(&expr), "expected list, got integer");
+    /// ```
+    fn describe_mismatch(expr: &Expr) -> String {
+        let message = format!("expected {}, got {}", Self::expected_kind(), expr.kind());
+        match Self::mismatch_detail(expr) {
+            Some(detail) => format!("{}: {}", message, detail),
+            None => message,
+        }
+    }
 }
 
 impl FromExpr for () {
@@ -493,41 +578,72 @@ impl FromExpr for () {
             _ => Err(expr),
         }
     }
+
+    fn expected_kind() -> &'static str {
+        "void"
+    }
 }
 
 impl FromExpr for Expr {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         Ok(expr)
     }
+
+    fn expected_kind() -> &'static str {
+        "any"
+    }
 }
 
 impl FromExpr for bool {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_boolean()
     }
+
+    fn expected_kind() -> &'static str {
+        "boolean"
+    }
 }
 
 impl FromExpr for char {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_char()
     }
+
+    fn expected_kind() -> &'static str {
+        "char"
+    }
 }
 
 impl FromExpr for i64 {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_integer()
     }
+
+    fn expected_kind() -> &'static str {
+        "integer"
+    }
 }
 
 impl FromExpr for f64 {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_float()
     }
+
+    fn expected_kind() -> &'static str {
+        "float"
+    }
 }
 
 impl FromExpr for String {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
-        expr.into_symbol()
+        match expr {
+            Expr::String(string) => Ok(string.borrow().clone()),
+            _ => expr.into_symbol(),
+        }
+    }
+
+    fn expected_kind() -> &'static str {
+        "symbol"
     }
 }
 
@@ -535,24 +651,50 @@ impl FromExpr for Rc<RefCell<String>> {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_string()
     }
+
+    fn expected_kind() -> &'static str {
+        "string"
+    }
 }
 
 impl FromExpr for List {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_list()
     }
+
+    fn expected_kind() -> &'static str {
+        "list"
+    }
+}
+
+impl FromExpr for Procedure {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        expr.into_procedure()
+    }
+
+    fn expected_kind() -> &'static str {
+        "procedure"
+    }
 }
 
 impl FromExpr for Rc<RefCell<dyn InputPortSuperTrait>> {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_input_port()
     }
+
+    fn expected_kind() -> &'static str {
+        "input_port"
+    }
 }
 
 impl FromExpr for Rc<RefCell<dyn OutputPortSuperTrait>> {
     fn from_expr(expr: Expr) -> FromExprResult<Self> {
         expr.into_output_port()
     }
+
+    fn expected_kind() -> &'static str {
+        "output_port"
+    }
 }
 
 impl<T: FromExpr> FromExpr for Vec<T> {
@@ -564,6 +706,10 @@ impl<T: FromExpr> FromExpr for Vec<T> {
             _ => Err(expr),
         }
     }
+
+    fn expected_kind() -> &'static str {
+        "list"
+    }
 }
 
 impl<T: FromExpr> FromExpr for VecDeque<T> {
@@ -575,6 +721,10 @@ impl<T: FromExpr> FromExpr for VecDeque<T> {
             _ => Err(expr),
         }
     }
+
+    fn expected_kind() -> &'static str {
+        "list"
+    }
 }
 
 impl<A: FromExpr, B: FromExpr> FromExpr for (A, B) {
@@ -593,12 +743,82 @@ impl<A: FromExpr, B: FromExpr> FromExpr for (A, B) {
             _ => Err(expr),
         }
     }
+
+    fn expected_kind() -> &'static str {
+        "pair"
+    }
+}
+
+impl<T: FromExpr> FromExpr for Option<T> {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        match expr {
+            Expr::Boolean(false) => Ok(None),
+            _ => T::from_expr(expr).map(Some),
+        }
+    }
+
+    fn expected_kind() -> &'static str {
+        T::expected_kind()
+    }
+
+    fn mismatch_detail(expr: &Expr) -> Option<String> {
+        T::mismatch_detail(expr)
+    }
+}
+
+impl<K: FromExpr + Eq + Hash, V: FromExpr> FromExpr for HashMap<K, V> {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        alist_from_expr(expr)
+    }
+
+    fn expected_kind() -> &'static str {
+        "association list"
+    }
+}
+
+impl<K: FromExpr + Ord, V: FromExpr> FromExpr for BTreeMap<K, V> {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        alist_from_expr(expr)
+    }
+
+    fn expected_kind() -> &'static str {
+        "association list"
+    }
+}
+
+// later entries overwrite earlier ones with the same key
+fn alist_from_expr<K: FromExpr, V: FromExpr, M: FromIterator<(K, V)>>(
+    expr: Expr,
+) -> FromExprResult<M> {
+    if !derive::is_alist(&expr) {
+        return Err(expr);
+    }
+    let entries = expr.as_list().unwrap().iter().map(|entry| {
+        let (key, value) = derive::split_entry(entry).unwrap();
+        Some((K::from_expr(key.clone()).ok()?, V::from_expr(value).ok()?))
+    });
+    match entries.collect::<Option<M>>() {
+        Some(map) => Ok(map),
+        None => Err(expr),
+    }
+}
+
+impl FromExpr for PathBuf {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        match expr {
+            Expr::String(string) => Ok(PathBuf::from(string.borrow().as_str())),
+            _ => Err(expr),
+        }
+    }
+
+    fn expected_kind() -> &'static str {
+        "string"
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::exprs;
 
     #[test]
     fn display_empty_list() {
@@ -0,0 +1,284 @@
+use super::expr::{Expr, FromExpr, FromExprResult};
+use std::{any::Any, fmt, marker::PhantomData, ops::Deref, rc::Rc};
+
+/// Hooks of a host value, which define how it is printed and compared by `equal?`.
+///
+/// Implement it and create the object with [`Foreign::with_hooks`]
+/// to customize the default behaviour of [`Foreign::new`].
+///
+/// # Examples
+/// ```
+/// use lispdm::{Engine, Foreign, ForeignObject};
+/// use std::{any::Any, fmt};
+///
+/// #[derive(PartialEq)]
+/// struct Point(i64, i64);
+///
+/// impl ForeignObject for Point {
+///     fn fmt_foreign(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "#<point {} {}>", self.0, self.1)
+///     }
+///
+///     fn equal_foreign(&self, other: &dyn Any) -> bool {
+///         other.downcast_ref::<Point>() == Some(self)
+///     }
+/// }
+///
+/// let mut engine = Engine::default();
+/// engine.register("point", |x: i64, y: i64| Foreign::with_hooks(Point(x, y)));
+/// let result = engine.eval::<bool>("(equal? (point 1 2) (point 1 2))").unwrap();
+/// assert_eq!(result, Ok(true));
+/// let result = engine.eval::<lispdm::Expr>("(point 1 2)").unwrap().unwrap();
+/// assert_eq!(result.to_string(), "#<point 1 2>");
+/// ```
+pub trait ForeignObject: Any {
+    /// Writes the object for `display` and `write`.
+    ///
+    /// Defaults to `#<foreign Type>`.
+    fn fmt_foreign(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#<foreign {}>", short_type_name::<Self>())
+    }
+
+    /// Checks if the object is `equal?` to `other`, which is the value of another foreign object.
+    ///
+    /// Objects are always equal to themselves. Defaults to `false` for other objects.
+    fn equal_foreign(&self, other: &dyn Any) -> bool {
+        let _ = other;
+        false
+    }
+}
+
+/// Host value, which is passed through Scheme code as [`Expr::Foreign`].
+///
+/// Value is shared, so copies of the expression refer to the same value.
+/// Value can be taken back with [`downcast_ref`](#method.downcast_ref),
+/// or by converting the expression into [`ForeignRef`].
+///
+/// # Examples
+/// ```
+/// use lispdm::{Engine, Expr, Foreign, ForeignRef};
+///
+/// struct Config {
+///     name: String,
+/// }
+///
+/// let mut engine = Engine::default();
+/// engine.register("config-name", |config: ForeignRef<Config>| config.name.clone());
+/// engine.env().add(
+///     "config".to_string(),
+///     Foreign::new(Config { name: "main".to_string() }),
+/// );
+///
+/// let result = engine.eval::<Expr>("(config-name config)").unwrap().unwrap();
+/// assert_eq!(result, Expr::new_string("main".to_string()));
+/// let config = engine.eval::<ForeignRef<Config>>("config").unwrap().unwrap();
+/// assert_eq!(config.name, "main");
+/// ```
+#[derive(Clone)]
+pub struct Foreign {
+    value: Rc<dyn Any>,
+    type_name: &'static str,
+    // points to the same value, if it was created with hooks
+    hooks: Option<Rc<dyn ForeignObject>>,
+}
+
+impl Foreign {
+    /// Wraps `value`, which is printed as `#<foreign Type>` and is `equal?` only to itself.
+    pub fn new<T: Any>(value: T) -> Self {
+        Self {
+            value: Rc::new(value),
+            type_name: short_type_name::<T>(),
+            hooks: None,
+        }
+    }
+
+    /// Wraps `value`, which is printed and compared by its [`ForeignObject`] hooks.
+    pub fn with_hooks<T: ForeignObject>(value: T) -> Self {
+        let value = Rc::new(value);
+        Self {
+            value: value.clone(),
+            type_name: short_type_name::<T>(),
+            hooks: Some(value),
+        }
+    }
+
+    /// Returns name of the type of the value, without the module path.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Checks if the value has type `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.value.is::<T>()
+    }
+
+    /// Returns reference to the value, if it has type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// Returns the shared value, if it has type `T`.
+    pub fn downcast<T: Any>(&self) -> Option<Rc<T>> {
+        self.value.clone().downcast().ok()
+    }
+
+    /// Checks if both objects refer to the same value, used by `eqv?` and `eq?`.
+    pub fn ptr_eq(&self, other: &Foreign) -> bool {
+        std::ptr::addr_eq(Rc::as_ptr(&self.value), Rc::as_ptr(&other.value))
+    }
+
+    /// Checks if objects are `equal?`, with the hook of this object if it has one.
+    pub fn equal(&self, other: &Foreign) -> bool {
+        if self.ptr_eq(other) {
+            return true;
+        }
+        match &self.hooks {
+            Some(hooks) => hooks.equal_foreign(other.value.as_ref()),
+            None => false,
+        }
+    }
+}
+
+impl PartialEq for Foreign {
+    fn eq(&self, other: &Self) -> bool {
+        self.equal(other)
+    }
+}
+
+impl fmt::Debug for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Foreign({})", self.type_name)
+    }
+}
+
+impl fmt::Display for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.hooks {
+            Some(hooks) => hooks.fmt_foreign(f),
+            None => write!(f, "#<foreign {}>", self.type_name),
+        }
+    }
+}
+
+impl From<Foreign> for Expr {
+    fn from(foreign: Foreign) -> Self {
+        Expr::Foreign(foreign)
+    }
+}
+
+impl FromExpr for Foreign {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        expr.into_foreign()
+    }
+
+    fn expected_kind() -> &'static str {
+        "foreign"
+    }
+}
+
+/// Foreign object, which value has type `T`.
+///
+/// Used to take a value of a known type from [`Expr::Foreign`] with [`FromExpr`],
+/// e.g. as an argument of a function registered with [`Engine::register`](crate::Engine::register).
+/// Dereferences to the value.
+pub struct ForeignRef<T> {
+    foreign: Foreign,
+    _type: PhantomData<T>,
+}
+
+impl<T: Any> ForeignRef<T> {
+    /// Returns the foreign object.
+    pub fn into_foreign(self) -> Foreign {
+        self.foreign
+    }
+}
+
+impl<T: Any> Deref for ForeignRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // type is checked on conversion
+        self.foreign.downcast_ref().unwrap()
+    }
+}
+
+impl<T> Clone for ForeignRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            foreign: self.foreign.clone(),
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for ForeignRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ForeignRef({})", self.foreign.type_name)
+    }
+}
+
+impl<T: Any> FromExpr for ForeignRef<T> {
+    fn from_expr(expr: Expr) -> FromExprResult<Self> {
+        match expr {
+            Expr::Foreign(foreign) if foreign.is::<T>() => Ok(Self {
+                foreign,
+                _type: PhantomData,
+            }),
+            expr => Err(expr),
+        }
+    }
+
+    fn expected_kind() -> &'static str {
+        short_type_name::<T>()
+    }
+}
+
+impl<T> From<ForeignRef<T>> for Expr {
+    fn from(foreign: ForeignRef<T>) -> Self {
+        Expr::Foreign(foreign.foreign)
+    }
+}
+
+// module path is removed from the name of the type, but kept in its generic arguments
+fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let path_end = name.find('<').unwrap_or(name.len());
+    match name[..path_end].rfind("::") {
+        Some(idx) => &name[idx + 2..],
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Handle;
+
+    #[test]
+    fn short_type_names() {
+        assert_eq!(short_type_name::<Handle>(), "Handle");
+        assert_eq!(short_type_name::<i64>(), "i64");
+        assert_eq!(
+            short_type_name::<Vec<String>>(),
+            "Vec<alloc::string::String>"
+        );
+    }
+
+    #[test]
+    fn downcast_foreign() {
+        let foreign = Foreign::new(42_i64);
+        assert!(foreign.is::<i64>());
+        assert_eq!(foreign.downcast_ref::<i64>(), Some(&42));
+        assert_eq!(foreign.downcast_ref::<Handle>().map(|_| ()), None);
+        assert_eq!(foreign.downcast::<i64>().as_deref(), Some(&42));
+        assert_eq!(foreign.to_string(), "#<foreign i64>");
+    }
+
+    #[test]
+    fn compare_foreign() {
+        let foreign = Foreign::new(1_i64);
+        assert!(foreign.equal(&foreign.clone()));
+        assert!(!foreign.equal(&Foreign::new(1_i64)));
+    }
+}
@@ -0,0 +1,103 @@
+use super::{
+    expr::{Expr, Exprs, FromExpr},
+    procedure::{Arity, ProcedureClosure, ProcedureReturn},
+};
+use crate::evaluator::{EnvRef, ErrorKind, LispDMError};
+use std::rc::Rc;
+
+/// Rust function or closure, which can be registered as a procedure
+/// with [`Engine::register`](crate::Engine::register).
+///
+/// Implemented for functions with up to 8 arguments, which implement [`FromExpr`],
+/// and a result, which implements [`IntoProcedureValue`].
+/// Arity of the procedure is the number of arguments of the function,
+/// and arguments of wrong types are reported as [`ErrorKind::TypeMismatch`].
+///
+/// `Args` is a tuple of types of the arguments, it is inferred from the function.
+pub trait IntoProcedure<Args> {
+    /// Returns the number of arguments of the procedure.
+    fn arity(&self) -> Arity;
+
+    /// Converts function into a closure, which converts arguments and the result.
+    ///
+    /// `name` is used in errors.
+    fn into_closure(self, name: &str) -> ProcedureClosure;
+}
+
+/// Result of a Rust function registered as a procedure.
+///
+/// Implemented for values, which can be converted into [`Expr`],
+/// and for [`Result`] of them with an error, which can be converted into [`LispDMError`].
+pub trait IntoProcedureValue {
+    /// Converts result of a function into the value of the procedure.
+    fn into_procedure_value(self) -> Result<Expr, LispDMError>;
+}
+
+impl<T: Into<Expr>> IntoProcedureValue for T {
+    fn into_procedure_value(self) -> Result<Expr, LispDMError> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<Expr>, E: Into<LispDMError>> IntoProcedureValue for Result<T, E> {
+    fn into_procedure_value(self) -> Result<Expr, LispDMError> {
+        self.map(Into::into).map_err(Into::into)
+    }
+}
+
+fn convert_arg<T: FromExpr>(arg: Expr, position: usize, name: &str) -> Result<T, LispDMError> {
+    let detail = T::mismatch_detail(&arg);
+    T::from_expr(arg).map_err(|expr| {
+        let message = format!(
+            "expected {} as argument {} of {}, got {}",
+            T::expected_kind(),
+            position,
+            name,
+            expr.kind()
+        );
+        let message = match detail {
+            Some(detail) => format!("{}: {}", message, detail),
+            None => message,
+        };
+        LispDMError::new(ErrorKind::TypeMismatch(message))
+    })
+}
+
+macro_rules! impl_into_procedure {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> IntoProcedure<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoProcedureValue,
+            $($arg: FromExpr,)*
+        {
+            fn arity(&self) -> Arity {
+                Arity::Exact(<[&str]>::len(&[$(stringify!($arg)),*]))
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_closure(self, name: &str) -> ProcedureClosure {
+                let name = name.to_string();
+                Rc::new(move |args: Exprs, _: &mut EnvRef| {
+                    // arity is checked before the call, so there is an argument for each parameter
+                    let mut args = args.into_iter().zip(1..);
+                    $(
+                        let (arg, position) = args.next().unwrap();
+                        let $arg = convert_arg::<$arg>(arg, position, &name)?;
+                    )*
+                    Ok(ProcedureReturn::Value(self($($arg),*).into_procedure_value()?))
+                })
+            }
+        }
+    };
+}
+
+impl_into_procedure!();
+impl_into_procedure!(A1);
+impl_into_procedure!(A1, A2);
+impl_into_procedure!(A1, A2, A3);
+impl_into_procedure!(A1, A2, A3, A4);
+impl_into_procedure!(A1, A2, A3, A4, A5);
+impl_into_procedure!(A1, A2, A3, A4, A5, A6);
+impl_into_procedure!(A1, A2, A3, A4, A5, A6, A7);
+impl_into_procedure!(A1, A2, A3, A4, A5, A6, A7, A8);
@@ -1,11 +1,72 @@
 use super::expr::{Expr, Exprs};
-use core::fmt;
+use crate::{parser::Span, utils::grow_stack};
+use std::{fmt, rc::Rc};
 
-#[derive(Debug, PartialEq, Clone)]
 /// List of expressions.
 pub struct List {
     but_last: Exprs,
     last: Option<Box<Expr>>,
+    // location of the list form in source code, lists built at runtime have none
+    span: Option<Rc<Span>>,
+}
+
+// lists are compared by elements, their location does not matter
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        grow_stack(|| self.but_last == other.but_last && self.last == other.last)
+    }
+}
+
+impl Clone for List {
+    fn clone(&self) -> Self {
+        grow_stack(|| List {
+            but_last: self.but_last.clone(),
+            last: self.last.clone(),
+            span: self.span.clone(),
+        })
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        grow_stack(|| {
+            f.debug_struct("List")
+                .field("but_last", &self.but_last)
+                .field("last", &self.last)
+                .field("span", &self.span)
+                .finish()
+        })
+    }
+}
+
+// nested lists are dropped one by one instead of recursively,
+// so dropping deeply nested data does not overflow the stack
+impl Drop for List {
+    fn drop(&mut self) {
+        let is_nested = |list: &List| {
+            list.but_last
+                .iter()
+                .chain(list.last.as_deref())
+                .any(Expr::is_list)
+        };
+        if !is_nested(self) {
+            return;
+        }
+        let mut nested = vec![std::mem::replace(self, List::new_empty())];
+        while let Some(mut list) = nested.pop() {
+            if !is_nested(&list) {
+                continue;
+            }
+            let exprs = std::mem::take(&mut list.but_last)
+                .into_iter()
+                .chain(list.last.take().map(|expr| *expr));
+            for expr in exprs {
+                if let Expr::List(list) = expr {
+                    nested.push(list);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -20,6 +81,7 @@ impl List {
         List {
             but_last,
             last: last.map(Box::new),
+            span: None,
         }
         .flatten()
     }
@@ -39,26 +101,37 @@ impl List {
         List::new(but_last, Some(last))
     }
 
-    fn flatten(self) -> Self {
-        let mut last = match self.last {
+    fn flatten(mut self) -> Self {
+        let mut last = match self.last.take() {
             None => return self,
             expr => expr.map(|e| *e),
         };
-        let mut but_last = self.but_last;
+        let mut but_last = std::mem::take(&mut self.but_last);
 
         // flatten list
-        while let Some(Expr::List(list)) = last {
-            but_last.extend(list.but_last);
-            last = list.last.map(|e| *e);
+        while let Some(Expr::List(mut list)) = last {
+            but_last.extend(std::mem::take(&mut list.but_last));
+            last = list.last.take().map(|e| *e);
         }
 
         // NOTE: do not use `List::new` here, because it will infinetly recurse
         List {
             but_last,
             last: last.map(Box::new),
+            span: self.span.take(),
         }
     }
 
+    /// Returns location of the list form in source code, if the list was parsed from it.
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_deref()
+    }
+
+    pub(crate) fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span.map(Rc::new);
+        self
+    }
+
     /// Returns the length of the list.
     pub fn len(&self) -> usize {
         self.but_last.len()
@@ -130,6 +203,11 @@ impl List {
         self.last.as_deref()
     }
 
+    // tail of a dotted list is not an item
+    pub(crate) fn item(&self, index: usize) -> Option<&Expr> {
+        self.but_last.get(index)
+    }
+
     /// Returns iterator over all elements of the list.
     pub fn iter(&self) -> impl Iterator<Item = &Expr> {
         self.into_iter()
@@ -172,10 +250,10 @@ pub struct IntoIter {
 }
 
 impl IntoIter {
-    fn new(list: List) -> Self {
+    fn new(mut list: List) -> Self {
         IntoIter {
-            but_last_iter: list.but_last.into_iter(),
-            last: list.last.map(|expr| *expr),
+            but_last_iter: std::mem::take(&mut list.but_last).into_iter(),
+            last: list.last.take().map(|expr| *expr),
         }
     }
 }
@@ -215,23 +293,6 @@ impl From<List> for Exprs {
     }
 }
 
-impl fmt::Display for List {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut but_last = self.but_last();
-        write!(f, "(")?;
-        if let Some(expr) = but_last.next() {
-            write!(f, "{}", expr)?;
-        }
-        for expr in but_last {
-            write!(f, " {}", expr)?;
-        }
-        if let Some(expr) = self.last() {
-            write!(f, " . {}", expr)?;
-        }
-        write!(f, ")")
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
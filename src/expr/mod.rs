@@ -1,12 +1,28 @@
+mod call;
+#[doc(hidden)]
+pub mod derive;
 mod expr;
+mod foreign;
+mod into_procedure;
 pub(crate) mod list;
+mod pair;
 pub(crate) mod port;
 mod procedure;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod writer;
 
+pub use call::{Callee, IntoArgs};
 pub use expr::{AsExprs, Expr, Exprs, FromExpr, FromExprResult};
+pub use foreign::{Foreign, ForeignObject, ForeignRef};
+pub use into_procedure::{IntoProcedure, IntoProcedureValue};
 pub use list::{List, ListKind};
+pub use pair::Pair;
 pub use port::{
-    FileInputPort, FileOutputPort, InputPortSuperTrait, OutputPortSuperTrait, StdinInputPort,
-    StdoutOutputPort,
+    BufferMode, Codec, ErrorHandlingMode, FileInputPort, FileOpenMode, FileOutputPort,
+    InputPortSuperTrait, NullOutputPort, OutputPortSuperTrait, StderrOutputPort, StdinInputPort,
+    StdoutOutputPort, StringInputPort, Transcoder,
 };
 pub use procedure::*;
+pub(crate) use writer::format_float;
+pub use writer::{Printer, WriteMode};
@@ -0,0 +1,60 @@
+use super::expr::Expr;
+use std::{cell::OnceCell, fmt, rc::Rc};
+
+/// Pair shared by reference, which cyclic data are made of, e.g. `#0=(a . #0#)`.
+///
+/// Lists own their elements, so data read from a datum label, which refers to its own datum,
+/// are built around a shared pair. Pairs are compared by reference.
+///
+/// Pairs are reference counted, so cyclic data are not freed.
+#[derive(Clone)]
+pub struct Pair(Rc<PairCell>);
+
+// car and cdr are set once, after the pair is referred to by them
+struct PairCell {
+    car: OnceCell<Expr>,
+    cdr: OnceCell<Expr>,
+}
+
+impl Pair {
+    /// Creates a pair, which has to be initialized with [`init`](#method.init) before it is used.
+    pub(crate) fn new_uninit() -> Self {
+        Pair(Rc::new(PairCell {
+            car: OnceCell::new(),
+            cdr: OnceCell::new(),
+        }))
+    }
+
+    pub(crate) fn init(&self, car: Expr, cdr: Expr) {
+        let initialized = self.0.car.set(car).is_ok() && self.0.cdr.set(cdr).is_ok();
+        debug_assert!(initialized, "pair is initialized twice");
+    }
+
+    /// Returns the first element of the pair.
+    pub fn car(&self) -> &Expr {
+        self.0.car.get().expect("pair is initialized")
+    }
+
+    /// Returns the rest of the pair.
+    pub fn cdr(&self) -> &Expr {
+        self.0.cdr.get().expect("pair is initialized")
+    }
+
+    /// Returns address of the pair, which identifies it.
+    pub(crate) fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.0).cast()
+    }
+}
+
+impl PartialEq for Pair {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// contents are not printed, since they can refer back to the pair
+impl fmt::Debug for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pair({:p})", self.as_ptr())
+    }
+}
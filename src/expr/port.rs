@@ -4,9 +4,10 @@ use std::os::fd::AsRawFd;
 #[cfg(windows)]
 use std::os::windows::io::AsRawHandle;
 use std::{
+    collections::VecDeque,
     fmt::Debug,
-    fs::File,
-    io::{self, BufReader, BufWriter, Read, Stdin, Stdout, Write},
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Stderr, Stdin, Stdout, Write},
     path::Path,
 };
 
@@ -16,6 +17,31 @@ pub trait InputPortTrait {
     fn read_string(&mut self) -> io::Result<String>;
     fn close(&mut self) -> io::Result<()>;
     fn is_closed(&self) -> bool;
+    /// Line of the next character to be read, starting from 1.
+    fn line(&self) -> io::Result<usize>;
+    /// Column of the next character to be read, starting from 1.
+    fn column(&self) -> io::Result<usize>;
+
+    /// Reading waits for the user, so prompts written to the output port are flushed before it.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    /// Byte offset of the next character to be read.
+    fn position(&mut self) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "port does not support positioning",
+        ))
+    }
+
+    /// Moves the port to `position`, which must be a character boundary.
+    fn set_position(&mut self, _position: u64) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "port does not support positioning",
+        ))
+    }
 }
 
 pub trait InputPortSuperTrait: InputPortTrait + std::fmt::Debug + std::fmt::Display {}
@@ -27,55 +53,293 @@ pub trait OutputPortTrait: Write {
 
 pub trait OutputPortSuperTrait: OutputPortTrait + std::fmt::Debug + std::fmt::Display {}
 
-fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
-    let mut byte_buf = [0_u8];
-    let num_read = reader.read(&mut byte_buf)?;
-    if num_read == 0 {
-        Err(io::ErrorKind::UnexpectedEof.into())
-    } else {
-        Ok(byte_buf[0])
-    }
-}
-
-fn read_char(reader: &mut impl Read) -> io::Result<char> {
-    let mut buf = [0_u8; 4];
-    for i in 0..4 {
-        match read_u8(reader) {
-            Ok(b) => buf[i] = b,
-            Err(e) => {
-                return if i != 0 && e.kind() == io::ErrorKind::UnexpectedEof {
-                    Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "stream does not contain valid UTF-8",
-                    ))
-                } else {
-                    Err(e)
+/// When an output port passes buffered data to the underlying writer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BufferMode {
+    /// Every write is passed through immediately.
+    None,
+    /// Buffer is flushed after every write that contains a newline.
+    Line,
+    /// Buffer is flushed only when it is full, on `flush-output-port` and on close.
+    Block,
+}
+
+/// How a file output port treats the file it opens.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FileOpenMode {
+    /// Create the file or discard its contents if it exists.
+    #[default]
+    Truncate,
+    /// Create the file or write after its contents if it exists.
+    Append,
+    /// Create the file, fail if it already exists.
+    CreateExclusive,
+}
+
+/// Character encoding used by a port to turn bytes into characters and back.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Codec {
+    #[default]
+    Utf8,
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// What a port does when it meets data that its codec cannot handle.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ErrorHandlingMode {
+    /// Fail the operation with an [`io::ErrorKind::InvalidData`] error.
+    #[default]
+    Raise,
+    /// Substitute U+FFFD when reading and `?` when writing.
+    Replace,
+}
+
+/// Codec together with its error handling mode, selected when a port is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transcoder {
+    codec: Codec,
+    error_handling_mode: ErrorHandlingMode,
+}
+
+enum Decoded {
+    Char(char),
+    Invalid,
+    Eof,
+}
+
+impl Transcoder {
+    pub fn new(codec: Codec, error_handling_mode: ErrorHandlingMode) -> Self {
+        Self {
+            codec,
+            error_handling_mode,
+        }
+    }
+
+    fn decode_char(&self, reader: &mut ByteReader<impl Read>) -> io::Result<char> {
+        let decoded = match self.codec {
+            Codec::Utf8 => decode_utf8(reader)?,
+            Codec::Latin1 => match reader.read_u8()? {
+                Some(b) => Decoded::Char(b as char),
+                None => Decoded::Eof,
+            },
+            Codec::Utf16Le => decode_utf16(reader, u16::from_le_bytes)?,
+            Codec::Utf16Be => decode_utf16(reader, u16::from_be_bytes)?,
+        };
+
+        match (decoded, self.error_handling_mode) {
+            (Decoded::Char(c), _) => Ok(c),
+            (Decoded::Eof, _) => Err(io::ErrorKind::UnexpectedEof.into()),
+            (Decoded::Invalid, ErrorHandlingMode::Replace) => Ok(char::REPLACEMENT_CHARACTER),
+            (Decoded::Invalid, ErrorHandlingMode::Raise) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("stream does not contain valid {}", self.codec),
+            )),
+        }
+    }
+
+    fn encode_str(&self, string: &str, out: &mut Vec<u8>) -> io::Result<()> {
+        match self.codec {
+            Codec::Utf8 => out.extend_from_slice(string.as_bytes()),
+            Codec::Latin1 => {
+                for c in string.chars() {
+                    match (u8::try_from(c), self.error_handling_mode) {
+                        (Ok(b), _) => out.push(b),
+                        (Err(_), ErrorHandlingMode::Replace) => out.push(b'?'),
+                        (Err(_), ErrorHandlingMode::Raise) => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("character {:?} cannot be encoded in {}", c, self.codec),
+                            ))
+                        }
+                    }
                 }
             }
+            Codec::Utf16Le => out.extend(string.encode_utf16().flat_map(u16::to_le_bytes)),
+            Codec::Utf16Be => out.extend(string.encode_utf16().flat_map(u16::to_be_bytes)),
         }
+        Ok(())
+    }
+}
 
-        if let Some(c) = char::from_u32(u32::from_le_bytes(buf)) {
-            return Ok(c);
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::Utf8 => write!(f, "UTF-8"),
+            Codec::Latin1 => write!(f, "Latin-1"),
+            Codec::Utf16Le => write!(f, "UTF-16LE"),
+            Codec::Utf16Be => write!(f, "UTF-16BE"),
         }
     }
-
-    Err(io::Error::new(
-        io::ErrorKind::InvalidData,
-        "stream does not contain valid UTF-8",
-    ))
 }
 
+/// Byte reader that can put bytes back, so decoders can look ahead
+/// without losing bytes that start the next character.
 #[derive(Debug)]
-struct PeekableBufReader<R: Read> {
+struct ByteReader<R: Read> {
     reader: BufReader<R>,
-    peek_buffer: Option<char>,
+    pushed_back: Vec<u8>,
+    // number of bytes consumed so far
+    offset: u64,
 }
 
-impl<R: Read> PeekableBufReader<R> {
+impl<R: Read> ByteReader<R> {
     fn new(reader: R) -> Self {
         Self {
             reader: BufReader::new(reader),
+            pushed_back: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    fn read_u8(&mut self) -> io::Result<Option<u8>> {
+        let byte = match self.pushed_back.pop() {
+            Some(b) => Some(b),
+            None => {
+                let mut byte_buf = [0_u8];
+                match self.reader.read(&mut byte_buf)? {
+                    0 => None,
+                    _ => Some(byte_buf[0]),
+                }
+            }
+        };
+        if byte.is_some() {
+            self.offset += 1;
+        }
+        Ok(byte)
+    }
+
+    fn unread_u8(&mut self, b: u8) {
+        self.pushed_back.push(b);
+        self.offset -= 1;
+    }
+}
+
+impl<R: Read + Seek> ByteReader<R> {
+    fn seek(&mut self, offset: u64) -> io::Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.pushed_back.clear();
+        self.offset = offset;
+        Ok(())
+    }
+}
+
+fn decode_utf8(reader: &mut ByteReader<impl Read>) -> io::Result<Decoded> {
+    let first = match reader.read_u8()? {
+        Some(b) => b,
+        None => return Ok(Decoded::Eof),
+    };
+    // number of continuation bytes, payload of the first byte
+    // and the allowed range of the second byte (excludes overlongs and surrogates)
+    let (len, code_point, second_range) = match first {
+        0x00..=0x7F => return Ok(Decoded::Char(first as char)),
+        0xC2..=0xDF => (1, first & 0x1F, 0x80..=0xBF),
+        0xE0 => (2, first & 0x0F, 0xA0..=0xBF),
+        0xED => (2, first & 0x0F, 0x80..=0x9F),
+        0xE1..=0xEF => (2, first & 0x0F, 0x80..=0xBF),
+        0xF0 => (3, first & 0x07, 0x90..=0xBF),
+        0xF4 => (3, first & 0x07, 0x80..=0x8F),
+        0xF1..=0xF3 => (3, first & 0x07, 0x80..=0xBF),
+        _ => return Ok(Decoded::Invalid),
+    };
+    let mut code_point = code_point as u32;
+
+    for i in 0..len {
+        let range = if i == 0 {
+            second_range.clone()
+        } else {
+            0x80..=0xBF
+        };
+        match reader.read_u8()? {
+            Some(b) if range.contains(&b) => code_point = (code_point << 6) | (b & 0x3F) as u32,
+            Some(b) => {
+                // the byte may start the next character, so leave it in the stream
+                reader.unread_u8(b);
+                return Ok(Decoded::Invalid);
+            }
+            None => return Ok(Decoded::Invalid),
+        }
+    }
+
+    Ok(char::from_u32(code_point).map_or(Decoded::Invalid, Decoded::Char))
+}
+
+// reads raw bytes of a UTF-16 code unit, `Err` means that the stream ended mid-unit
+fn read_code_unit(reader: &mut ByteReader<impl Read>) -> io::Result<Option<Result<[u8; 2], ()>>> {
+    let b0 = match reader.read_u8()? {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+    match reader.read_u8()? {
+        Some(b1) => Ok(Some(Ok([b0, b1]))),
+        None => Ok(Some(Err(()))),
+    }
+}
+
+fn decode_utf16(
+    reader: &mut ByteReader<impl Read>,
+    from_bytes: fn([u8; 2]) -> u16,
+) -> io::Result<Decoded> {
+    let unit = match read_code_unit(reader)? {
+        Some(Ok(bytes)) => from_bytes(bytes),
+        Some(Err(())) => return Ok(Decoded::Invalid),
+        None => return Ok(Decoded::Eof),
+    };
+    if !(0xD800..=0xDBFF).contains(&unit) {
+        return Ok(char::from_u32(unit as u32).map_or(Decoded::Invalid, Decoded::Char));
+    }
+
+    // high surrogate, must be followed by a low surrogate
+    match read_code_unit(reader)? {
+        Some(Ok(bytes)) => match from_bytes(bytes) {
+            low @ 0xDC00..=0xDFFF => {
+                let code_point = 0x10000 + ((unit as u32 - 0xD800) << 10) + (low as u32 - 0xDC00);
+                Ok(char::from_u32(code_point).map_or(Decoded::Invalid, Decoded::Char))
+            }
+            _ => {
+                // not a low surrogate, so it belongs to the next character
+                reader.unread_u8(bytes[1]);
+                reader.unread_u8(bytes[0]);
+                Ok(Decoded::Invalid)
+            }
+        },
+        Some(Err(())) | None => Ok(Decoded::Invalid),
+    }
+}
+
+#[derive(Debug)]
+struct PeekableBufReader<R: Read> {
+    reader: ByteReader<R>,
+    transcoder: Transcoder,
+    // peeked character and the offset right after it
+    peek_buffer: Option<(char, u64)>,
+    // offset, line and column of the next character
+    offset: u64,
+    line: usize,
+    column: usize,
+    // offsets, lines and columns recently returned by `position`, oldest first,
+    // so seeking back near them does not require rescanning the input from the start
+    known_positions: VecDeque<(u64, usize, usize)>,
+}
+
+// number of positions remembered by a port
+const MAX_KNOWN_POSITIONS: usize = 64;
+
+impl<R: Read> PeekableBufReader<R> {
+    fn new(reader: R, transcoder: Transcoder) -> Self {
+        Self {
+            reader: ByteReader::new(reader),
+            transcoder,
             peek_buffer: None,
+            offset: 0,
+            line: 1,
+            column: 1,
+            known_positions: VecDeque::new(),
         }
     }
 
@@ -83,33 +347,119 @@ impl<R: Read> PeekableBufReader<R> {
         self.reader.get_ref()
     }
 
+    fn decode_char(&mut self) -> io::Result<(char, u64)> {
+        let c = self.transcoder.decode_char(&mut self.reader)?;
+        Ok((c, self.reader.offset))
+    }
+
     fn peek(&mut self) -> io::Result<char> {
-        if let Some(c) = self.peek_buffer {
+        if let Some((c, _)) = self.peek_buffer {
             Ok(c)
         } else {
-            let c = read_char(&mut self.reader)?;
-            self.peek_buffer = Some(c);
-            Ok(c)
+            let decoded = self.decode_char()?;
+            self.peek_buffer = Some(decoded);
+            Ok(decoded.0)
         }
     }
 
     fn read_char(&mut self) -> io::Result<char> {
-        if let Some(c) = self.peek_buffer.take() {
-            Ok(c)
+        let (c, next_offset) = match self.peek_buffer.take() {
+            Some(decoded) => decoded,
+            None => self.decode_char()?,
+        };
+        self.offset = next_offset;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            read_char(&mut self.reader)
+            self.column += 1;
         }
+        Ok(c)
     }
 
     fn read_string(&mut self) -> io::Result<String> {
         let mut str_buf = String::new();
-        if let Some(c) = self.peek_buffer.take() {
-            str_buf.push(c);
+        loop {
+            match self.read_char() {
+                Ok(c) => str_buf.push(c),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
         }
-        self.reader.read_to_string(&mut str_buf)?;
         let str_buf = str_buf.strip_suffix('\n').unwrap_or(&str_buf).to_string();
         Ok(str_buf)
     }
+
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl<R: Read + Seek> PeekableBufReader<R> {
+    fn position(&mut self) -> u64 {
+        let known = (self.offset, self.line, self.column);
+        if !self.known_positions.contains(&known) {
+            if self.known_positions.len() == MAX_KNOWN_POSITIONS {
+                self.known_positions.pop_front();
+            }
+            self.known_positions.push_back(known);
+        }
+        self.offset
+    }
+
+    fn set_position(&mut self, position: u64) -> io::Result<()> {
+        let (offset, line, column) = (self.offset, self.line, self.column);
+        let result = self.move_to(position);
+        if result.is_err() {
+            // the port stays where it was, if the position is invalid
+            self.seek(offset, line, column)?;
+        }
+        result
+    }
+
+    fn move_to(&mut self, position: u64) -> io::Result<()> {
+        // lines are counted from the closest known position before `position`
+        let (offset, line, column) = self
+            .known_positions
+            .iter()
+            .filter(|(offset, _, _)| *offset <= position)
+            .max_by_key(|(offset, _, _)| *offset)
+            .copied()
+            .unwrap_or((0, 1, 1));
+        self.seek(offset, line, column)?;
+        while self.offset < position {
+            match self.read_char() {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("position {} is past the end of the port", position),
+                    ))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if self.offset != position {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("position {} is not at a character boundary", position),
+            ));
+        }
+        Ok(())
+    }
+
+    fn seek(&mut self, offset: u64, line: usize, column: usize) -> io::Result<()> {
+        self.reader.seek(offset)?;
+        self.peek_buffer = None;
+        self.offset = offset;
+        self.line = line;
+        self.column = column;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -118,9 +468,9 @@ struct ReaderInputPort<R: Read> {
 }
 
 impl<R: Read> ReaderInputPort<R> {
-    fn new(reader: R) -> Self {
+    fn new(reader: R, transcoder: Transcoder) -> Self {
         Self {
-            reader: Some(PeekableBufReader::new(reader)),
+            reader: Some(PeekableBufReader::new(reader, transcoder)),
         }
     }
 
@@ -128,7 +478,7 @@ impl<R: Read> ReaderInputPort<R> {
         if let Some(r) = self.reader.as_mut() {
             Ok(r)
         } else {
-            Err(io::Error::new(io::ErrorKind::BrokenPipe, "port is closed"))
+            Err(closed_port_err())
         }
     }
 
@@ -137,6 +487,16 @@ impl<R: Read> ReaderInputPort<R> {
     }
 }
 
+impl<R: Read + Seek> ReaderInputPort<R> {
+    fn position(&mut self) -> io::Result<u64> {
+        Ok(self.reader_or_closed_err()?.position())
+    }
+
+    fn set_position(&mut self, position: u64) -> io::Result<()> {
+        self.reader_or_closed_err()?.set_position(position)
+    }
+}
+
 impl<R: Read> InputPortTrait for ReaderInputPort<R> {
     fn peek(&mut self) -> io::Result<char> {
         self.reader_or_closed_err()?.peek()
@@ -158,17 +518,44 @@ impl<R: Read> InputPortTrait for ReaderInputPort<R> {
     fn is_closed(&self) -> bool {
         self.reader.is_none()
     }
+
+    fn line(&self) -> io::Result<usize> {
+        self.reader
+            .as_ref()
+            .map(|r| r.line())
+            .ok_or_else(closed_port_err)
+    }
+
+    fn column(&self) -> io::Result<usize> {
+        self.reader
+            .as_ref()
+            .map(|r| r.column())
+            .ok_or_else(closed_port_err)
+    }
+}
+
+fn closed_port_err() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "port is closed")
 }
 
 #[derive(Debug)]
 struct WriterOutputPort<W: Write> {
     writer: Option<BufWriter<W>>,
+    transcoder: Transcoder,
+    buffer_mode: BufferMode,
+    encode_buffer: Vec<u8>,
+    // trailing bytes of a multi-byte UTF-8 sequence, which is split between writes
+    incomplete: Vec<u8>,
 }
 
 impl<W: Write> WriterOutputPort<W> {
-    fn new(writer: W) -> Self {
+    fn new(writer: W, transcoder: Transcoder, buffer_mode: BufferMode) -> Self {
         Self {
             writer: Some(BufWriter::new(writer)),
+            transcoder,
+            buffer_mode,
+            encode_buffer: Vec::new(),
+            incomplete: Vec::new(),
         }
     }
 
@@ -176,7 +563,7 @@ impl<W: Write> WriterOutputPort<W> {
         if let Some(r) = self.writer.as_mut() {
             Ok(r)
         } else {
-            Err(io::Error::new(io::ErrorKind::BrokenPipe, "port is closed"))
+            Err(closed_port_err())
         }
     }
 
@@ -187,9 +574,32 @@ impl<W: Write> WriterOutputPort<W> {
 
 impl<W: Write> Write for WriterOutputPort<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut bytes = std::mem::take(&mut self.incomplete);
+        bytes.extend_from_slice(buf);
+        let valid_len = match std::str::from_utf8(&bytes) {
+            Ok(_) => bytes.len(),
+            // the sequence at the end may be completed by the next write
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+        };
+        self.incomplete = bytes.split_off(valid_len);
+        let string = std::str::from_utf8(&bytes).unwrap();
+        let mut encoded = std::mem::take(&mut self.encode_buffer);
+        encoded.clear();
+        self.transcoder.encode_str(string, &mut encoded)?;
+
+        let needs_flush = match self.buffer_mode {
+            BufferMode::None => true,
+            BufferMode::Line => string.contains('\n'),
+            BufferMode::Block => false,
+        };
         let w = self.writer_or_closed_err()?;
-        w.write_all(buf)?;
-        w.flush()?;
+        let mut result = w.write_all(&encoded);
+        if needs_flush {
+            result = result.and_then(|_| w.flush());
+        }
+        self.encode_buffer = encoded;
+        result?;
         Ok(buf.len())
     }
 
@@ -200,8 +610,17 @@ impl<W: Write> Write for WriterOutputPort<W> {
 
 impl<W: Write> OutputPortTrait for WriterOutputPort<W> {
     fn close(&mut self) -> io::Result<()> {
-        self.writer = None;
-        Ok(())
+        let result = match self.writer.take() {
+            Some(mut writer) => writer.flush(),
+            None => Ok(()),
+        };
+        match std::mem::take(&mut self.incomplete).is_empty() {
+            true => result,
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "incomplete utf-8 sequence written to port",
+            )),
+        }
     }
 
     fn is_closed(&self) -> bool {
@@ -219,7 +638,7 @@ pub struct StdinInputPort {
 impl StdinInputPort {
     pub fn new() -> Self {
         Self {
-            reader: PeekableBufReader::new(io::stdin()),
+            reader: PeekableBufReader::new(io::stdin(), Transcoder::default()),
         }
     }
 }
@@ -246,6 +665,15 @@ impl InputPortTrait for StdinInputPort {
     fn is_closed(&self) -> bool {
         false
     }
+    fn line(&self) -> io::Result<usize> {
+        Ok(self.reader.line())
+    }
+    fn column(&self) -> io::Result<usize> {
+        Ok(self.reader.column())
+    }
+    fn is_interactive(&self) -> bool {
+        true
+    }
 }
 
 impl fmt::Display for StdinInputPort {
@@ -265,9 +693,16 @@ pub struct FileInputPort {
 
 impl FileInputPort {
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_path_with_transcoder(path, Transcoder::default())
+    }
+
+    pub fn from_path_with_transcoder<P: AsRef<Path>>(
+        path: P,
+        transcoder: Transcoder,
+    ) -> io::Result<Self> {
         let file = File::open(path)?;
         Ok(Self {
-            reader: ReaderInputPort::new(file),
+            reader: ReaderInputPort::new(file, transcoder),
         })
     }
 }
@@ -310,6 +745,18 @@ impl InputPortTrait for FileInputPort {
     fn is_closed(&self) -> bool {
         self.reader.is_closed()
     }
+    fn line(&self) -> io::Result<usize> {
+        self.reader.line()
+    }
+    fn column(&self) -> io::Result<usize> {
+        self.reader.column()
+    }
+    fn position(&mut self) -> io::Result<u64> {
+        self.reader.position()
+    }
+    fn set_position(&mut self, position: u64) -> io::Result<()> {
+        self.reader.set_position(position)
+    }
 }
 
 impl fmt::Display for FileInputPort {
@@ -329,6 +776,62 @@ impl fmt::Display for FileInputPort {
 
 impl InputPortSuperTrait for FileInputPort {}
 
+// StringInputPort
+
+#[derive(Debug)]
+pub struct StringInputPort {
+    reader: ReaderInputPort<Cursor<Vec<u8>>>,
+}
+
+impl StringInputPort {
+    pub fn new(string: String) -> Self {
+        Self {
+            reader: ReaderInputPort::new(Cursor::new(string.into_bytes()), Transcoder::default()),
+        }
+    }
+}
+
+impl InputPortTrait for StringInputPort {
+    fn peek(&mut self) -> io::Result<char> {
+        self.reader.peek()
+    }
+    fn read_char(&mut self) -> io::Result<char> {
+        self.reader.read_char()
+    }
+    fn read_string(&mut self) -> io::Result<String> {
+        self.reader.read_string()
+    }
+    fn close(&mut self) -> io::Result<()> {
+        self.reader.close()
+    }
+    fn is_closed(&self) -> bool {
+        self.reader.is_closed()
+    }
+    fn line(&self) -> io::Result<usize> {
+        self.reader.line()
+    }
+    fn column(&self) -> io::Result<usize> {
+        self.reader.column()
+    }
+    fn position(&mut self) -> io::Result<u64> {
+        self.reader.position()
+    }
+    fn set_position(&mut self, position: u64) -> io::Result<()> {
+        self.reader.set_position(position)
+    }
+}
+
+impl fmt::Display for StringInputPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reader.is_closed() {
+            false => write!(f, "#<string input port>"),
+            true => write!(f, "#<closed string input port>"),
+        }
+    }
+}
+
+impl InputPortSuperTrait for StringInputPort {}
+
 // StdoutOutputPort
 
 #[derive(Debug)]
@@ -339,7 +842,7 @@ pub struct StdoutOutputPort {
 impl StdoutOutputPort {
     pub fn new() -> Self {
         Self {
-            writer: WriterOutputPort::new(io::stdout()),
+            writer: WriterOutputPort::new(io::stdout(), Transcoder::default(), BufferMode::Line),
         }
     }
 }
@@ -361,7 +864,7 @@ impl Write for StdoutOutputPort {
 
 impl OutputPortTrait for StdoutOutputPort {
     fn close(&mut self) -> io::Result<()> {
-        Ok(())
+        self.writer.flush()
     }
     fn is_closed(&self) -> bool {
         false
@@ -376,6 +879,53 @@ impl fmt::Display for StdoutOutputPort {
 
 impl OutputPortSuperTrait for StdoutOutputPort {}
 
+// StderrOutputPort
+
+#[derive(Debug)]
+pub struct StderrOutputPort {
+    writer: WriterOutputPort<Stderr>,
+}
+
+impl StderrOutputPort {
+    pub fn new() -> Self {
+        Self {
+            writer: WriterOutputPort::new(io::stderr(), Transcoder::default(), BufferMode::None),
+        }
+    }
+}
+
+impl PartialEq for StderrOutputPort {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Write for StderrOutputPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl OutputPortTrait for StderrOutputPort {
+    fn close(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for StderrOutputPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#<stderr output port>")
+    }
+}
+
+impl OutputPortSuperTrait for StderrOutputPort {}
+
 // FileOutputPort
 
 #[derive(Debug)]
@@ -385,11 +935,31 @@ pub struct FileOutputPort {
 
 impl FileOutputPort {
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = File::create(path)?;
+        Self::from_path_with_options(path, FileOpenMode::default(), Transcoder::default())
+    }
+
+    pub fn from_path_with_options<P: AsRef<Path>>(
+        path: P,
+        open_mode: FileOpenMode,
+        transcoder: Transcoder,
+    ) -> io::Result<Self> {
+        let mut options = OpenOptions::new();
+        match open_mode {
+            FileOpenMode::Truncate => options.write(true).create(true).truncate(true),
+            FileOpenMode::Append => options.append(true).create(true),
+            FileOpenMode::CreateExclusive => options.write(true).create_new(true),
+        };
+        let file = options.open(path)?;
         Ok(Self {
-            writer: WriterOutputPort::new(file),
+            writer: WriterOutputPort::new(file, transcoder, BufferMode::Block),
         })
     }
+
+    /// Changes buffering of the port, file ports are block buffered by default.
+    pub fn with_buffer_mode(mut self, buffer_mode: BufferMode) -> Self {
+        self.writer.buffer_mode = buffer_mode;
+        self
+    }
 }
 
 impl PartialEq for FileOutputPort {
@@ -448,3 +1018,303 @@ impl fmt::Display for FileOutputPort {
 }
 
 impl OutputPortSuperTrait for FileOutputPort {}
+
+// NullOutputPort
+
+/// Port that discards everything written to it.
+///
+/// Used as the current output port of engines, which are not allowed to write to stdout.
+#[derive(Debug, Default, PartialEq)]
+pub struct NullOutputPort;
+
+impl NullOutputPort {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Write for NullOutputPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputPortTrait for NullOutputPort {
+    fn close(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for NullOutputPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#<null output port>")
+    }
+}
+
+impl OutputPortSuperTrait for NullOutputPort {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn decode_all(bytes: &[u8], transcoder: Transcoder) -> io::Result<String> {
+        PeekableBufReader::new(Cursor::new(bytes.to_vec()), transcoder).read_string()
+    }
+
+    fn replacing(codec: Codec) -> Transcoder {
+        Transcoder::new(codec, ErrorHandlingMode::Replace)
+    }
+
+    #[test]
+    fn decode_utf8_multibyte() {
+        let source = "aé€😀";
+        let result = decode_all(source.as_bytes(), Transcoder::default()).unwrap();
+        assert_eq!(result, source);
+    }
+
+    #[test]
+    fn peek_utf8_multibyte() {
+        let mut reader =
+            PeekableBufReader::new(Cursor::new("λx".as_bytes()), Transcoder::default());
+        assert_eq!(reader.peek().unwrap(), 'λ');
+        assert_eq!(reader.read_char().unwrap(), 'λ');
+        assert_eq!(reader.read_char().unwrap(), 'x');
+        assert_eq!(
+            reader.read_char().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn decode_utf8_invalid_raises() {
+        let err = decode_all(b"a\xFFb", Transcoder::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn decode_utf8_invalid_replaces() {
+        // truncated sequence, overlong encoding and encoded surrogate
+        let result = decode_all(b"\xE2\x82a\xC0\xAFb\xED\xA0\x80", replacing(Codec::Utf8));
+        assert_eq!(
+            result.unwrap(),
+            "\u{FFFD}a\u{FFFD}\u{FFFD}b\u{FFFD}\u{FFFD}\u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn decode_utf8_truncated_at_eof() {
+        let result = decode_all(b"ab\xF0\x9F", replacing(Codec::Utf8));
+        assert_eq!(result.unwrap(), "ab\u{FFFD}");
+    }
+
+    #[test]
+    fn decode_latin1() {
+        let result = decode_all(
+            b"caf\xE9",
+            Transcoder::new(Codec::Latin1, Default::default()),
+        );
+        assert_eq!(result.unwrap(), "café");
+    }
+
+    #[test]
+    fn decode_utf16() {
+        let source = "h€😀";
+        let le = source
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let be = source
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decode_all(&le, Transcoder::new(Codec::Utf16Le, Default::default())).unwrap(),
+            source
+        );
+        assert_eq!(
+            decode_all(&be, Transcoder::new(Codec::Utf16Be, Default::default())).unwrap(),
+            source
+        );
+    }
+
+    #[test]
+    fn decode_utf16_unpaired_surrogate() {
+        // high surrogate followed by 'a'
+        let result = decode_all(&[0x3D, 0xD8, 0x61, 0x00], replacing(Codec::Utf16Le));
+        assert_eq!(result.unwrap(), "\u{FFFD}a");
+
+        let err = decode_all(
+            &[0x3D, 0xD8],
+            Transcoder::new(Codec::Utf16Le, Default::default()),
+        );
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encode_latin1() {
+        let mut out = Vec::new();
+        replacing(Codec::Latin1)
+            .encode_str("café €", &mut out)
+            .unwrap();
+        assert_eq!(out, b"caf\xE9 ?");
+
+        let err = Transcoder::new(Codec::Latin1, ErrorHandlingMode::Raise)
+            .encode_str("€", &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn write_utf16be() {
+        let mut port = WriterOutputPort::new(
+            Vec::new(),
+            Transcoder::new(Codec::Utf16Be, Default::default()),
+            BufferMode::None,
+        );
+        write!(port, "é").unwrap();
+        let written = port.writer.take().unwrap().into_inner().unwrap();
+        assert_eq!(written, vec![0x00, 0xE9]);
+    }
+
+    #[test]
+    fn write_split_utf8_sequence() {
+        let mut port = WriterOutputPort::new(Vec::new(), Transcoder::default(), BufferMode::None);
+        let bytes = "a€".as_bytes();
+        assert_eq!(port.write(&bytes[..2]).unwrap(), 2);
+        assert_eq!(buffered(&port), b"a");
+        assert_eq!(port.write(&bytes[2..]).unwrap(), 2);
+        assert_eq!(buffered(&port), "a€".as_bytes());
+
+        let err = port.write(b"\xFF").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        port.write_all(&bytes[1..3]).unwrap();
+        let err = port.close().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    fn buffered(port: &WriterOutputPort<Vec<u8>>) -> &[u8] {
+        port.get_inderlying().unwrap()
+    }
+
+    #[test]
+    fn write_line_buffered() {
+        let mut port = WriterOutputPort::new(Vec::new(), Transcoder::default(), BufferMode::Line);
+        write!(port, "abc").unwrap();
+        assert_eq!(buffered(&port), b"");
+        write!(port, "d\ne").unwrap();
+        assert_eq!(buffered(&port), b"abcd\ne");
+    }
+
+    #[test]
+    fn write_block_buffered() {
+        let mut port = WriterOutputPort::new(Vec::new(), Transcoder::default(), BufferMode::Block);
+        writeln!(port, "abc").unwrap();
+        assert_eq!(buffered(&port), b"");
+        port.flush().unwrap();
+        assert_eq!(buffered(&port), b"abc\n");
+    }
+
+    #[test]
+    fn write_unbuffered() {
+        let mut port = WriterOutputPort::new(Vec::new(), Transcoder::default(), BufferMode::None);
+        write!(port, "abc").unwrap();
+        assert_eq!(buffered(&port), b"abc");
+    }
+
+    fn read_n(port: &mut StringInputPort, n: usize) -> String {
+        (0..n).map(|_| port.read_char().unwrap()).collect()
+    }
+
+    #[test]
+    fn track_line_and_column() {
+        let mut port = StringInputPort::new("ab\nλd".to_string());
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (1, 1));
+        read_n(&mut port, 2);
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (1, 3));
+        port.peek().unwrap();
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (1, 3));
+        read_n(&mut port, 2);
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (2, 2));
+    }
+
+    #[test]
+    fn position_is_byte_offset() {
+        let mut port = StringInputPort::new("λx".to_string());
+        assert_eq!(port.position().unwrap(), 0);
+        port.peek().unwrap();
+        assert_eq!(port.position().unwrap(), 0);
+        read_n(&mut port, 1);
+        assert_eq!(port.position().unwrap(), 2);
+    }
+
+    #[test]
+    fn set_reported_position() {
+        let mut port = StringInputPort::new("ab\ncd\nef".to_string());
+        read_n(&mut port, 4);
+        let position = port.position().unwrap();
+        assert_eq!(read_n(&mut port, 4), "d\nef");
+
+        port.set_position(position).unwrap();
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (2, 2));
+        assert_eq!(read_n(&mut port, 1), "d");
+    }
+
+    #[test]
+    fn set_unreported_position() {
+        let mut port = StringInputPort::new("ab\nλd".to_string());
+        port.set_position(5).unwrap();
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (2, 2));
+        assert_eq!(read_n(&mut port, 1), "d");
+
+        // inside of the two-byte λ
+        let err = port.set_position(4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = port.set_position(100).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn set_invalid_position_keeps_position() {
+        let mut port = StringInputPort::new("ab\nλd".to_string());
+        read_n(&mut port, 3);
+        assert!(port.set_position(4).is_err());
+        assert!(port.set_position(100).is_err());
+        assert_eq!(port.position().unwrap(), 3);
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (2, 1));
+        assert_eq!(read_n(&mut port, 2), "λd");
+    }
+
+    #[test]
+    fn known_positions_are_bounded() {
+        let source = "a\n".repeat(MAX_KNOWN_POSITIONS * 2);
+        let mut port = StringInputPort::new(source);
+        for _ in 0..MAX_KNOWN_POSITIONS * 2 {
+            port.position().unwrap();
+            read_n(&mut port, 2);
+        }
+        assert_eq!(
+            port.reader.reader.as_ref().unwrap().known_positions.len(),
+            MAX_KNOWN_POSITIONS
+        );
+
+        // forgotten positions are found by counting lines from a known one before them
+        port.set_position(2).unwrap();
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (2, 1));
+        port.set_position(200).unwrap();
+        assert_eq!((port.line().unwrap(), port.column().unwrap()), (101, 1));
+    }
+
+    #[test]
+    fn stdin_position_unsupported() {
+        let err = StdinInputPort::new().position().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -4,18 +4,25 @@ use crate::{
     exprs,
     utils::debug,
 };
+use std::{fmt, rc::Rc};
 
 pub trait NamedProcedure {
-    fn name_stored(&self) -> Option<&str>;
+    fn name_stored(&self) -> Option<&Rc<str>>;
 
     fn name(&self) -> &str {
-        self.name_stored().unwrap_or("anon")
+        self.name_stored().map_or("anon", |name| name)
     }
 }
 
+/// Handle of a procedure, which can be stored in Rust
+/// and called later with [`Engine::call`](crate::Engine::call).
+///
+/// Cloned handles refer to the same procedure.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Procedure {
+    /// Procedure implemented in Rust.
     Atomic(AtomicProcedure),
+    /// Procedure defined in Scheme.
     Compound(CompoundProcedure),
 }
 
@@ -52,12 +59,33 @@ pub(crate) use proc_result_tailcall;
 /// The result is either a value or a tail call.
 pub type ProcedureFn = fn(args: Exprs, env: &mut EnvRef) -> ProcedureResult;
 
+/// The type of a procedure closure.
+///
+/// Same as [`ProcedureFn`], but can capture state of the host.
+/// State, which is changed by the closure, has to be behind interior mutability,
+/// e.g. [`Cell`](std::cell::Cell) or [`RefCell`](std::cell::RefCell).
+pub type ProcedureClosure = Rc<dyn Fn(Exprs, &mut EnvRef) -> ProcedureResult>;
+
 impl Procedure {
-    pub fn new_atomic(name: String, kind: ProcedureKind, proc: ProcedureFn, arity: Arity) -> Self {
+    pub(crate) fn new_atomic(
+        name: String,
+        kind: ProcedureKind,
+        proc: ProcedureFn,
+        arity: Arity,
+    ) -> Self {
         Procedure::Atomic(AtomicProcedure::new(name, kind, proc, arity))
     }
 
-    pub fn new_compound(
+    pub(crate) fn new_atomic_closure(
+        name: String,
+        kind: ProcedureKind,
+        proc: ProcedureClosure,
+        arity: Arity,
+    ) -> Self {
+        Procedure::Atomic(AtomicProcedure::new_closure(name, kind, proc, arity))
+    }
+
+    pub(crate) fn new_compound(
         name: Option<String>,
         params: ProcedureParams,
         body: Body,
@@ -70,6 +98,7 @@ impl Procedure {
         Procedure::Compound(CompoundProcedure::new(name, params, body, env))
     }
 
+    /// Checks if the procedure is a special form, which gets its arguments unevaluated.
     pub fn is_special_form(&self) -> bool {
         match self {
             Procedure::Atomic(proc) => proc.is_special_form(),
@@ -77,6 +106,7 @@ impl Procedure {
         }
     }
 
+    /// Returns the number of arguments of the procedure.
     pub fn arity(&self) -> Arity {
         match self {
             Procedure::Atomic(proc) => proc.arity(),
@@ -90,7 +120,7 @@ impl Procedure {
 }
 
 impl NamedProcedure for Procedure {
-    fn name_stored(&self) -> Option<&str> {
+    fn name_stored(&self) -> Option<&Rc<str>> {
         match self {
             Procedure::Atomic(proc) => proc.name_stored(),
             Procedure::Compound(proc) => proc.name_stored(),
@@ -151,21 +181,61 @@ impl std::fmt::Display for Arity {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+// functions are called directly, closures are called through a pointer to the closure
+#[derive(Clone)]
+enum AtomicFn {
+    Fn(ProcedureFn),
+    Closure(ProcedureClosure),
+}
+
+impl fmt::Debug for AtomicFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtomicFn::Fn(proc) => write!(f, "Fn({:p})", *proc),
+            AtomicFn::Closure(proc) => write!(f, "Closure({:p})", Rc::as_ptr(proc)),
+        }
+    }
+}
+
+impl PartialEq for AtomicFn {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (AtomicFn::Fn(proc1), AtomicFn::Fn(proc2)) => std::ptr::fn_addr_eq(*proc1, *proc2),
+            (AtomicFn::Closure(proc1), AtomicFn::Closure(proc2)) => Rc::ptr_eq(proc1, proc2),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct AtomicProcedure {
-    name: Option<String>,
+    name: Option<Rc<str>>,
     kind: ProcedureKind,
     arity: Arity,
-    proc: ProcedureFn,
+    proc: AtomicFn,
 }
 
 impl AtomicProcedure {
     pub fn new(name: String, kind: ProcedureKind, proc: ProcedureFn, arity: Arity) -> Self {
         AtomicProcedure {
-            name: Some(name),
+            name: Some(name.into()),
             kind,
             arity,
-            proc,
+            proc: AtomicFn::Fn(proc),
+        }
+    }
+
+    pub fn new_closure(
+        name: String,
+        kind: ProcedureKind,
+        proc: ProcedureClosure,
+        arity: Arity,
+    ) -> Self {
+        AtomicProcedure {
+            name: Some(name.into()),
+            kind,
+            arity,
+            proc: AtomicFn::Closure(proc),
         }
     }
 
@@ -173,8 +243,12 @@ impl AtomicProcedure {
         matches!(self.kind, ProcedureKind::SpecialForm)
     }
 
-    pub fn proc(&self) -> ProcedureFn {
-        self.proc
+    /// Calls the function or the closure of the procedure, arity is not checked.
+    pub fn call(&self, args: Exprs, env: &mut EnvRef) -> ProcedureResult {
+        match &self.proc {
+            AtomicFn::Fn(proc) => proc(args, env),
+            AtomicFn::Closure(proc) => proc(args, env),
+        }
     }
 
     pub fn arity(&self) -> Arity {
@@ -182,9 +256,18 @@ impl AtomicProcedure {
     }
 }
 
+impl PartialEq for AtomicProcedure {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.kind == other.kind
+            && self.arity == other.arity
+            && self.proc == other.proc
+    }
+}
+
 impl NamedProcedure for AtomicProcedure {
-    fn name_stored(&self) -> Option<&str> {
-        self.name.as_deref()
+    fn name_stored(&self) -> Option<&Rc<str>> {
+        self.name.as_ref()
     }
 }
 
@@ -228,7 +311,7 @@ impl From<Expr> for Body {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct CompoundProcedure {
-    name: Option<String>,
+    name: Option<Rc<str>>,
     pub params: ProcedureParams,
     pub body: Box<Body>,
     pub env: EnvRef,
@@ -237,7 +320,7 @@ pub struct CompoundProcedure {
 impl CompoundProcedure {
     pub fn new(name: Option<String>, params: ProcedureParams, body: Body, env: EnvRef) -> Self {
         CompoundProcedure {
-            name,
+            name: name.map(Rc::from),
             params,
             env,
             body: Box::new(body),
@@ -246,7 +329,7 @@ impl CompoundProcedure {
 }
 
 impl NamedProcedure for CompoundProcedure {
-    fn name_stored(&self) -> Option<&str> {
-        self.name.as_deref()
+    fn name_stored(&self) -> Option<&Rc<str>> {
+        self.name.as_ref()
     }
 }
@@ -0,0 +1,943 @@
+//! Conversions between [`Expr`] and Rust types, which implement `serde` traits.
+//!
+//! Data is represented same as with `#[derive(FromExpr, IntoExpr)]`:
+//! structs and maps are association lists `((field . value) ...)`, sequences and tuples are lists,
+//! `None` is `#f`, and variants of enums are lists tagged with their name, e.g. `(circle (radius . 1.0))`.
+//! Names of fields and variants are used as they are given by `serde`,
+//! so use `#[serde(rename_all = "kebab-case")]` to write them in Scheme style.
+//!
+//! [`Expr`] itself implements `Serialize` and `Deserialize` for its data values.
+//! Lists and pairs are serialized as sequences, and both symbols and strings as strings.
+//! Wrap it into [`AlistsAsMaps`] to serialize association lists as maps.
+//!
+//! # Examples
+//! ```
+//! use lispdm::serde::{from_expr, to_expr};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     workers: u32,
+//!     tags: Vec<String>,
+//! }
+//!
+//! let config = Config {
+//!     name: "main".to_string(),
+//!     workers: 4,
+//!     tags: vec!["a".to_string()],
+//! };
+//! let expr = to_expr(&config).unwrap();
+//! assert_eq!(expr.to_string(), r#"((name . "main") (workers . 4) (tags "a"))"#);
+//! assert_eq!(from_expr::<Config>(expr).unwrap(), config);
+//! ```
+
+use super::{
+    derive,
+    expr::{Expr, Exprs},
+};
+use crate::evaluator::{ErrorKind, LispDMError};
+use ::serde::{
+    de::{self, DeserializeOwned, IntoDeserializer},
+    forward_to_deserialize_any, ser, Deserialize, Serialize,
+};
+use std::{collections::HashSet, fmt};
+
+/// Error of a conversion between [`Expr`] and a Rust type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<Error> for LispDMError {
+    fn from(err: Error) -> Self {
+        LispDMError::new(ErrorKind::TypeMismatch(err.0))
+    }
+}
+
+/// Converts `value` into [`Expr`].
+pub fn to_expr<T: Serialize + ?Sized>(value: &T) -> Result<Expr, Error> {
+    value.serialize(Serializer)
+}
+
+/// Converts `expr` into `T`.
+pub fn from_expr<T: DeserializeOwned>(expr: Expr) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(expr))
+}
+
+// ========================================================================
+//                      Expr as serde data
+
+// values of entries of an association list with distinct symbol keys
+fn as_map(expr: &Expr) -> Option<Vec<(&str, Expr)>> {
+    let list = expr.as_list()?;
+    if list.is_empty() || !derive::is_alist(expr) {
+        return None;
+    }
+    let mut keys = HashSet::new();
+    list.iter()
+        .map(|entry| match derive::split_entry(entry)? {
+            (Expr::Symbol(key), value) if keys.insert(key.as_str()) => Some((key.as_str(), value)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Serialize for Expr {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_expr(self, false, serializer)
+    }
+}
+
+/// [`Expr`], which is serialized with association lists as maps.
+///
+/// Proper lists of entries `(key . value)` with distinct symbol keys are serialized as maps.
+/// Other lists can look like them, e.g. a list of variants `((circle 1.0) (square 2.0))`,
+/// so wrap only expressions, which are known to be built of association lists.
+///
+/// # Examples
+/// ```
+/// use lispdm::{serde::AlistsAsMaps, Engine, Expr};
+/// let mut engine = Engine::default();
+/// let expr = engine.eval::<Expr>("'((name . \"main\") (workers . 4))").unwrap().unwrap();
+/// assert_eq!(serde_json::to_string(&expr).unwrap(), r#"[["name","main"],["workers",4]]"#);
+/// assert_eq!(
+///     serde_json::to_string(&AlistsAsMaps(&expr)).unwrap(),
+///     r#"{"name":"main","workers":4}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct AlistsAsMaps<'a>(pub &'a Expr);
+
+impl Serialize for AlistsAsMaps<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_expr(self.0, true, serializer)
+    }
+}
+
+// element of a serialized list, which keeps the representation of association lists
+struct Element<'a> {
+    expr: &'a Expr,
+    maps: bool,
+}
+
+impl Serialize for Element<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_expr(self.expr, self.maps, serializer)
+    }
+}
+
+fn serialize_expr<S: ser::Serializer>(
+    expr: &Expr,
+    maps: bool,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use ser::{SerializeMap, SerializeSeq};
+
+    match expr {
+        Expr::Integer(integer) => serializer.serialize_i64(*integer),
+        Expr::Float(float) => serializer.serialize_f64(*float),
+        Expr::Symbol(symbol) => serializer.serialize_str(symbol),
+        Expr::String(string) => serializer.serialize_str(&string.borrow()),
+        Expr::Char(ch) => serializer.serialize_char(*ch),
+        Expr::Boolean(boolean) => serializer.serialize_bool(*boolean),
+        Expr::Void => serializer.serialize_unit(),
+        Expr::List(list) => {
+            if let Some(entries) = as_map(expr).filter(|_| maps) {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, &Element { expr: &value, maps })?;
+                }
+                return map.end();
+            }
+            // a pair `(a . b)` is a sequence of its two values
+            if list.is_dotted() && list.len() != 2 {
+                return Err(ser::Error::custom(format!(
+                    "dotted list can not be serialized: {}",
+                    expr
+                )));
+            }
+            let mut seq = serializer.serialize_seq(Some(list.len()))?;
+            for expr in list.iter() {
+                seq.serialize_element(&Element { expr, maps })?;
+            }
+            seq.end()
+        }
+        _ => Err(ser::Error::custom(format!(
+            "{} can not be serialized",
+            expr.kind()
+        ))),
+    }
+}
+
+impl<'de> Deserialize<'de> for Expr {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ExprVisitor)
+    }
+}
+
+struct ExprVisitor;
+
+impl<'de> de::Visitor<'de> for ExprVisitor {
+    type Value = Expr;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a data value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Expr, E> {
+        Ok(Expr::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Expr, E> {
+        Ok(Expr::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Expr, E> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Expr::Integer(v)),
+            Err(_) => Err(E::custom(format!("integer {} is too large", v))),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Expr, E> {
+        Ok(Expr::Float(v))
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<Expr, E> {
+        Ok(Expr::Char(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Expr, E> {
+        Ok(Expr::new_string(v))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Expr, E> {
+        Ok(Expr::new_string(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Expr, E> {
+        Ok(v.iter().map(|&b| b as i64).collect::<Vec<_>>().into())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Expr, E> {
+        Ok(Expr::Boolean(false))
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Expr, D::Error> {
+        Expr::deserialize(deserializer)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Expr, E> {
+        Ok(Expr::Void)
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Expr, D::Error> {
+        Expr::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Expr, A::Error> {
+        let mut exprs = Exprs::new();
+        while let Some(expr) = seq.next_element()? {
+            exprs.push_back(expr);
+        }
+        Ok(Expr::new_proper_list(exprs))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Expr, A::Error> {
+        let mut entries = Exprs::new();
+        while let Some((key, value)) = map.next_entry::<Expr, Expr>()? {
+            entries.push_back(entry(key, value));
+        }
+        Ok(Expr::new_proper_list(entries))
+    }
+}
+
+// string keys of maps are symbols, same as names of fields
+fn entry(key: Expr, value: Expr) -> Expr {
+    let key = match key {
+        Expr::String(string) => Expr::Symbol(string.borrow().clone()),
+        key => key,
+    };
+    Expr::new_dotted_list(Exprs::from([key, value]))
+}
+
+// ========================================================================
+//                      Serializer
+
+/// Serializer of Rust values into [`Expr`], see [`to_expr`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Expr;
+    type Error = Error;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeAlist;
+    type SerializeStruct = SerializeAlist;
+    type SerializeStructVariant = SerializeAlist;
+
+    fn serialize_bool(self, v: bool) -> Result<Expr, Error> {
+        Ok(Expr::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Expr, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Expr, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Expr, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Expr, Error> {
+        Ok(Expr::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Expr, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Expr, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Expr, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Expr, Error> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Expr::Integer(v)),
+            Err(_) => Err(Error(format!("integer {} is too large", v))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Expr, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Expr, Error> {
+        Ok(Expr::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Expr, Error> {
+        Ok(Expr::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Expr, Error> {
+        Ok(Expr::new_string(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Expr, Error> {
+        Ok(v.iter().map(|&b| b as i64).collect::<Vec<_>>().into())
+    }
+
+    fn serialize_none(self) -> Result<Expr, Error> {
+        Ok(Expr::Boolean(false))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Expr, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Expr, Error> {
+        Ok(Expr::Void)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Expr, Error> {
+        Ok(Expr::new_empty_list())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Expr, Error> {
+        Ok(derive::variant(variant, Exprs::new()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Expr, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Expr, Error> {
+        Ok(derive::variant(variant, Exprs::from([to_expr(value)?])))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeList, Error> {
+        Ok(SerializeList::new(None))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<SerializeList, Error> {
+        Ok(SerializeList::new(None))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<SerializeList, Error> {
+        Ok(SerializeList::new(None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeList, Error> {
+        Ok(SerializeList::new(Some(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeAlist, Error> {
+        Ok(SerializeAlist::new(None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<SerializeAlist, Error> {
+        Ok(SerializeAlist::new(None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeAlist, Error> {
+        Ok(SerializeAlist::new(Some(variant)))
+    }
+}
+
+/// Serializer of sequences and tuples into lists.
+#[derive(Debug)]
+pub struct SerializeList {
+    variant: Option<&'static str>,
+    items: Exprs,
+}
+
+impl SerializeList {
+    fn new(variant: Option<&'static str>) -> Self {
+        Self {
+            variant,
+            items: Exprs::new(),
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push_back(to_expr(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Expr, Error> {
+        Ok(match self.variant {
+            Some(variant) => derive::variant(variant, self.items),
+            None => Expr::new_proper_list(self.items),
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+/// Serializer of maps and structs into association lists.
+#[derive(Debug)]
+pub struct SerializeAlist {
+    variant: Option<&'static str>,
+    entries: Exprs,
+    key: Option<Expr>,
+}
+
+impl SerializeAlist {
+    fn new(variant: Option<&'static str>) -> Self {
+        Self {
+            variant,
+            entries: Exprs::new(),
+            key: None,
+        }
+    }
+
+    fn push_field<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<(), Error> {
+        self.entries.push_back(derive::field(name, to_expr(value)?));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Expr, Error> {
+        Ok(match self.variant {
+            Some(variant) => derive::variant(variant, self.entries),
+            None => Expr::new_proper_list(self.entries),
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeAlist {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(to_expr(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("value of a map is serialized before its key".to_string()))?;
+        self.entries.push_back(entry(key, to_expr(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeAlist {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeAlist {
+    type Ok = Expr;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Expr, Error> {
+        self.finish()
+    }
+}
+
+// ========================================================================
+//                      Deserializer
+
+/// Deserializer of Rust values from [`Expr`], see [`from_expr`].
+#[derive(Debug, Clone)]
+pub struct Deserializer {
+    expr: Expr,
+}
+
+impl Deserializer {
+    /// Creates a deserializer of `expr`.
+    pub fn new(expr: Expr) -> Self {
+        Self { expr }
+    }
+
+    fn mismatch(&self, expected: &str) -> Error {
+        Error(format!("expected {}, got {}", expected, self.expr.kind()))
+    }
+
+    fn into_items(self, expected: &str) -> Result<Exprs, Error> {
+        match &self.expr {
+            Expr::List(list) if list.is_proper() => Ok(list.iter().cloned().collect()),
+            _ => Err(self.mismatch(expected)),
+        }
+    }
+
+    fn into_entries(self) -> Result<Vec<(Expr, Expr)>, Error> {
+        if !derive::is_alist(&self.expr) {
+            return Err(self.mismatch("association list"));
+        }
+        let list = self.expr.as_list().unwrap();
+        Ok(list
+            .iter()
+            .map(|entry| {
+                let (key, value) = derive::split_entry(entry).unwrap();
+                (key.clone(), value)
+            })
+            .collect())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Expr {
+    type Deserializer = Deserializer;
+
+    fn into_deserializer(self) -> Deserializer {
+        Deserializer::new(self)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.expr {
+            Expr::Integer(integer) => visitor.visit_i64(integer),
+            Expr::Float(float) => visitor.visit_f64(float),
+            Expr::Symbol(symbol) => visitor.visit_string(symbol),
+            Expr::String(string) => visitor.visit_string(string.borrow().clone()),
+            Expr::Char(ch) => visitor.visit_char(ch),
+            Expr::Boolean(boolean) => visitor.visit_bool(boolean),
+            Expr::Void => visitor.visit_unit(),
+            Expr::List(_) if as_map(&self.expr).is_some() => self.deserialize_map(visitor),
+            Expr::List(_) => self.deserialize_seq(visitor),
+            _ => Err(Error(format!(
+                "{} can not be deserialized",
+                self.expr.kind()
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.expr {
+            Expr::Boolean(false) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.expr {
+            Expr::Void => visitor.visit_unit(),
+            _ => Err(self.mismatch("void")),
+        }
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.expr.as_list() {
+            Some(list) if list.is_empty() => visitor.visit_unit(),
+            _ => Err(self.mismatch("empty list")),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let items = self.into_items("list")?;
+        let mut seq = de::value::SeqDeserializer::new(items.into_iter());
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let entries = self.into_entries()?;
+        let mut map = de::value::MapDeserializer::new(entries.into_iter());
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // unit variants can also be written as symbols
+        if let Expr::Symbol(tag) = self.expr {
+            return visitor.visit_enum(EnumAccess {
+                tag,
+                rest: Expr::new_empty_list(),
+            });
+        }
+        match derive::split_variant(&self.expr) {
+            Some((tag, rest)) => visitor.visit_enum(EnumAccess { tag, rest }),
+            None => Err(self.mismatch("tagged list")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf identifier ignored_any
+    }
+}
+
+struct EnumAccess {
+    tag: String,
+    rest: Expr,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess {
+    type Error = Error;
+    type Variant = VariantAccess;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantAccess), Error> {
+        let tag = seed.deserialize(self.tag.into_deserializer())?;
+        Ok((tag, VariantAccess(Deserializer::new(self.rest))))
+    }
+}
+
+// values of a variant, which follow its tag
+struct VariantAccess(Deserializer);
+
+impl<'de> de::VariantAccess<'de> for VariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0.into_items("no values")?.len() {
+            0 => Ok(()),
+            len => Err(Error(format!("expected no values, got {}", len))),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        let mut items = self.0.into_items("value")?;
+        match (items.pop_front(), items.is_empty()) {
+            (Some(value), true) => seed.deserialize(Deserializer::new(value)),
+            _ => Err(Error(format!("expected 1 value, got {}", items.len()))),
+        }
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.0, visitor)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.0, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    enum Shape {
+        Circle { radius: f64 },
+        Polygon(Vec<(i64, i64)>),
+        Line((i64, i64), (i64, i64)),
+        Empty,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct Drawing {
+        title: String,
+        shapes: Vec<Shape>,
+        scale: Option<f64>,
+        layers: BTreeMap<String, u8>,
+    }
+
+    fn drawing() -> Drawing {
+        Drawing {
+            title: "sketch".to_string(),
+            shapes: vec![
+                Shape::Circle { radius: 1.5 },
+                Shape::Polygon(vec![(0, 0), (1, 1)]),
+                Shape::Line((0, 0), (2, 2)),
+                Shape::Empty,
+            ],
+            scale: None,
+            layers: BTreeMap::from([("base".to_string(), 0), ("top".to_string(), 1)]),
+        }
+    }
+
+    #[test]
+    fn serialize_into_expr() {
+        let expr = to_expr(&drawing()).unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "((title . \"sketch\") \
+             (shapes (circle (radius . 1.5)) (polygon ((0 0) (1 1))) (line (0 0) (2 2)) (empty)) \
+             (scale . #f) \
+             (layers (base . 0) (top . 1)))"
+        );
+        assert_eq!(from_expr::<Drawing>(expr).unwrap(), drawing());
+    }
+
+    #[test]
+    fn deserialize_from_expr() {
+        let expr = crate::parser::parse_str(
+            "((shapes empty (circle (radius . 2.0))) (title . sketch) (layers))",
+        )
+        .unwrap()
+        .pop_front()
+        .unwrap();
+        let drawing = from_expr::<Drawing>(expr).unwrap();
+        assert_eq!(drawing.title, "sketch");
+        assert_eq!(
+            drawing.shapes,
+            vec![Shape::Empty, Shape::Circle { radius: 2.0 }]
+        );
+        assert_eq!(drawing.scale, None);
+        assert!(drawing.layers.is_empty());
+
+        let err = from_expr::<Shape>(Expr::Integer(1)).unwrap_err();
+        assert_eq!(err.to_string(), "expected tagged list, got integer");
+        let err = from_expr::<Drawing>(Expr::new_proper_list(Exprs::new())).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `title`");
+    }
+
+    #[test]
+    fn expr_as_serde_data() {
+        let expr = to_expr(&drawing()).unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+        assert_eq!(
+            json,
+            r#"[["title","sketch"],["shapes",["circle",["radius",1.5]],["polygon",[[0,0],[1,1]]],["line",[0,0],[2,2]],["empty"]],["scale",false],["layers",["base",0],["top",1]]]"#
+        );
+        let layers = to_expr(&drawing().layers).unwrap();
+        let json = serde_json::to_string(&AlistsAsMaps(&layers)).unwrap();
+        assert_eq!(json, r#"{"base":0,"top":1}"#);
+
+        let expr: Expr =
+            serde_json::from_str(r#"{"name": "alice", "tags": [1, 2.5, null]}"#).unwrap();
+        assert_eq!(
+            expr.to_string(),
+            r#"((name . "alice") (tags 1 2.5 #<void>))"#
+        );
+        assert!(serde_json::to_string(&Expr::new_dotted_list(Exprs::from([
+            Expr::Integer(1),
+            Expr::Integer(2),
+            Expr::Integer(3)
+        ])))
+        .is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use super::{Expr, List, Pair};
+use crate::{parser::reads_as_symbol, utils::grow_stack};
+use core::fmt;
+use std::{collections::HashMap, rc::Rc};
+
+/// How [`Printer`] represents expressions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteMode {
+    /// Human-readable output of `display`, strings and chars are printed as they are.
+    Display,
+    /// Output of `write`, which can be read back by `read`.
+    Write,
+    /// Output of `write-shared`, every string that occurs more than once is labeled
+    /// with `#n=` and later occurrences are written as `#n#`.
+    Shared,
+    /// Output of `write-simple`.
+    ///
+    /// Same as other modes, it labels shared pairs, so writing cyclic data terminates.
+    Simple,
+}
+
+/// Formats an expression according to [`WriteMode`].
+///
+/// # Examples
+/// ```
+/// use lispdm::{Expr, Printer, WriteMode};
+/// let expr = Expr::from("say \"hi\"");
+/// assert_eq!(Printer::new(&expr, WriteMode::Write).to_string(), r#""say \"hi\"""#);
+/// assert_eq!(Printer::new(&expr, WriteMode::Display).to_string(), r#"say "hi""#);
+/// ```
+pub struct Printer<'a> {
+    expr: &'a Expr,
+    mode: WriteMode,
+}
+
+impl<'a> Printer<'a> {
+    /// Creates a printer of `expr`.
+    pub fn new(expr: &'a Expr, mode: WriteMode) -> Self {
+        Self { expr, mode }
+    }
+}
+
+impl fmt::Display for Printer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut labels = Labels::default();
+        labels.count(self.expr, self.mode == WriteMode::Shared);
+        labels.keep_shared();
+        write_expr(self.expr, self.mode, &mut labels, f)
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut labels = Labels::default();
+        labels.count_list(self, false);
+        labels.keep_shared();
+        write_list(self, WriteMode::Write, &mut labels, f)
+    }
+}
+
+// Lists own their elements, so the only structure that can be shared are strings,
+// which are mutable and referenced by pointer, and shared pairs, which cyclic data
+// are made of. Pairs that occur more than once are labeled in every mode, so writing
+// cyclic data terminates, strings are labeled only by `write-shared`.
+#[derive(Default)]
+struct Labels {
+    // occurrences of strings and pairs while counting them, then only the shared ones
+    // with their label number once the first occurrence is written
+    shared: HashMap<*const (), Option<usize>>,
+    counts: HashMap<*const (), usize>,
+    next_label: usize,
+}
+
+impl Labels {
+    fn count(&mut self, expr: &Expr, strings: bool) {
+        match expr {
+            Expr::String(string) if strings => {
+                *self.counts.entry(Rc::as_ptr(string).cast()).or_default() += 1
+            }
+            Expr::List(list) => grow_stack(|| self.count_list(list, strings)),
+            Expr::Pair(pair) => {
+                let count = self.counts.entry(pair.as_ptr()).or_default();
+                *count += 1;
+                // contents are counted once, so counting cyclic data terminates
+                if *count == 1 {
+                    grow_stack(|| {
+                        self.count(pair.car(), strings);
+                        self.count(pair.cdr(), strings);
+                    })
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn count_list(&mut self, list: &List, strings: bool) {
+        for expr in list.but_last().chain(list.last()) {
+            self.count(expr, strings);
+        }
+    }
+
+    fn keep_shared(&mut self) {
+        self.shared = std::mem::take(&mut self.counts)
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(ptr, _)| (ptr, None))
+            .collect();
+    }
+
+    fn is_shared(&self, ptr: *const ()) -> bool {
+        self.shared.contains_key(&ptr)
+    }
+
+    // writes the label of a shared value, returns `true` if the value was written before,
+    // so it is referred to by its label instead
+    fn write_label(
+        &mut self,
+        ptr: *const (),
+        f: &mut fmt::Formatter<'_>,
+    ) -> Result<bool, fmt::Error> {
+        match self.shared.get_mut(&ptr) {
+            Some(Some(label)) => {
+                write!(f, "#{}#", label)?;
+                Ok(true)
+            }
+            Some(label @ None) => {
+                *label = Some(self.next_label);
+                write!(f, "#{}=", self.next_label)?;
+                self.next_label += 1;
+                Ok(false)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+fn write_expr(
+    expr: &Expr,
+    mode: WriteMode,
+    labels: &mut Labels,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let is_display = mode == WriteMode::Display;
+    match expr {
+        Expr::Void => write!(f, "#<void>"),
+        Expr::Integer(int) => write!(f, "{}", int),
+        Expr::Float(float) => write!(f, "{}", format_float(*float)),
+        Expr::Boolean(bool) => write!(f, "{}", if *bool { "#t" } else { "#f" }),
+        Expr::Symbol(symbol) if is_display => write!(f, "{}", symbol),
+        Expr::Symbol(symbol) => write_symbol(symbol, f),
+        Expr::String(string) if is_display => write!(f, "{}", string.borrow()),
+        Expr::String(string) => match labels.write_label(Rc::as_ptr(string).cast(), f)? {
+            true => Ok(()),
+            false => write_string(&string.borrow(), f),
+        },
+        Expr::Char(ch) if is_display => write!(f, "{}", ch),
+        Expr::Char(ch) => write_char(*ch, f),
+        Expr::List(list) => grow_stack(|| write_list(list, mode, labels, f)),
+        Expr::Pair(pair) => grow_stack(|| write_pair(pair, mode, labels, f)),
+        Expr::Procedure(proc) => write!(f, "{}", proc),
+        Expr::InputPort(port) => write!(f, "{}", port.borrow()),
+        Expr::OutputPort(port) => write!(f, "{}", port.borrow()),
+        Expr::Foreign(foreign) => write!(f, "{}", foreign),
+    }
+}
+
+fn write_list(
+    list: &List,
+    mode: WriteMode,
+    labels: &mut Labels,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    write!(f, "(")?;
+    for (i, expr) in list.but_last().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write_expr(expr, mode, labels, f)?;
+    }
+    if let Some(expr) = list.last() {
+        write_rest(expr, mode, labels, f)?;
+    }
+    write!(f, ")")
+}
+
+fn write_pair(
+    pair: &Pair,
+    mode: WriteMode,
+    labels: &mut Labels,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    if labels.write_label(pair.as_ptr(), f)? {
+        return Ok(());
+    }
+    write!(f, "(")?;
+    write_expr(pair.car(), mode, labels, f)?;
+    write_rest(pair.cdr(), mode, labels, f)?;
+    write!(f, ")")
+}
+
+// writes the rest of a list after its first items, pairs that are not labeled continue the list
+// and other values are written as the tail of a dotted list
+fn write_rest(
+    mut rest: &Expr,
+    mode: WriteMode,
+    labels: &mut Labels,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    loop {
+        match rest {
+            Expr::List(list) => {
+                for expr in list.but_last() {
+                    write!(f, " ")?;
+                    write_expr(expr, mode, labels, f)?;
+                }
+                match list.last() {
+                    Some(last) => rest = last,
+                    None => return Ok(()),
+                }
+            }
+            Expr::Pair(pair) if !labels.is_shared(pair.as_ptr()) => {
+                write!(f, " ")?;
+                write_expr(pair.car(), mode, labels, f)?;
+                rest = pair.cdr();
+            }
+            _ => {
+                write!(f, " . ")?;
+                return write_expr(rest, mode, labels, f);
+            }
+        }
+    }
+}
+
+/// Formats a float so it is never confused with an integer and reads back to the same value.
+pub(crate) fn format_float(float: f64) -> String {
+    if float.is_nan() {
+        "+nan.0".to_string()
+    } else if float.is_infinite() {
+        if float > 0.0 { "+inf.0" } else { "-inf.0" }.to_string()
+    } else {
+        // debug formatting is the shortest representation that round-trips
+        // and always has a fractional part or an exponent
+        format!("{:?}", float)
+    }
+}
+
+fn char_name(ch: char) -> Option<&'static str> {
+    match ch {
+        '\x07' => Some("alarm"),
+        '\x08' => Some("backspace"),
+        '\x7F' => Some("delete"),
+        '\x1B' => Some("escape"),
+        '\n' => Some("newline"),
+        '\0' => Some("null"),
+        '\r' => Some("return"),
+        ' ' => Some("space"),
+        '\t' => Some("tab"),
+        _ => None,
+    }
+}
+
+fn write_char(ch: char, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match char_name(ch) {
+        Some(name) => write!(f, "#\\{}", name),
+        None if ch.is_control() => write!(f, "#\\x{:x}", ch as u32),
+        None => write!(f, "#\\{}", ch),
+    }
+}
+
+// writes characters that have to be escaped inside of strings and |symbols|
+fn write_escaped(text: &str, delimiter: char, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for ch in text.chars() {
+        match ch {
+            '\\' => write!(f, "\\\\")?,
+            '\x07' => write!(f, "\\a")?,
+            '\x08' => write!(f, "\\b")?,
+            '\t' => write!(f, "\\t")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            _ if ch == delimiter => write!(f, "\\{}", ch)?,
+            _ if ch.is_control() => write!(f, "\\x{:x};", ch as u32)?,
+            _ => write!(f, "{}", ch)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_string(string: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "\"")?;
+    write_escaped(string, '"', f)?;
+    write!(f, "\"")
+}
+
+fn write_symbol(symbol: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if symbol != "." && reads_as_symbol(symbol) {
+        return write!(f, "{}", symbol);
+    }
+    write!(f, "|")?;
+    write_escaped(symbol, '|', f)?;
+    write!(f, "|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exprs;
+
+    fn written(expr: &Expr) -> String {
+        Printer::new(expr, WriteMode::Write).to_string()
+    }
+
+    fn displayed(expr: &Expr) -> String {
+        Printer::new(expr, WriteMode::Display).to_string()
+    }
+
+    #[test]
+    fn write_floats() {
+        assert_eq!(written(&Expr::Float(1.0)), "1.0");
+        assert_eq!(written(&Expr::Float(-0.5)), "-0.5");
+        assert_eq!(written(&Expr::Float(1e21)), "1e21");
+        assert_eq!(written(&Expr::Float(f64::INFINITY)), "+inf.0");
+        assert_eq!(written(&Expr::Float(f64::NEG_INFINITY)), "-inf.0");
+        assert_eq!(written(&Expr::Float(f64::NAN)), "+nan.0");
+    }
+
+    #[test]
+    fn write_chars() {
+        assert_eq!(written(&Expr::Char('a')), "#\\a");
+        assert_eq!(written(&Expr::Char(' ')), "#\\space");
+        assert_eq!(written(&Expr::Char('\n')), "#\\newline");
+        assert_eq!(written(&Expr::Char('\u{1}')), "#\\x1");
+        assert_eq!(displayed(&Expr::Char('a')), "a");
+    }
+
+    #[test]
+    fn write_strings() {
+        let expr = Expr::from("a \"quoted\"\n\\ string\u{1}");
+        assert_eq!(written(&expr), r#""a \"quoted\"\n\\ string\x1;""#);
+        assert_eq!(displayed(&expr), "a \"quoted\"\n\\ string\u{1}");
+    }
+
+    #[test]
+    fn write_symbols() {
+        assert_eq!(written(&Expr::new_symbol("abc")), "abc");
+        assert_eq!(written(&Expr::new_symbol("hello world")), "|hello world|");
+        assert_eq!(written(&Expr::new_symbol("1")), "|1|");
+        assert_eq!(written(&Expr::new_symbol("")), "||");
+        assert_eq!(written(&Expr::new_symbol("a b|c")), "|a b\\|c|");
+        assert_eq!(displayed(&Expr::new_symbol("hello world")), "hello world");
+    }
+
+    #[test]
+    fn display_nested() {
+        let expr = Expr::new_dotted_list(exprs![
+            Expr::from("a"),
+            Expr::Char('b'),
+            Expr::new_proper_list(exprs![Expr::Float(2.0)]),
+            Expr::from("c")
+        ]);
+        assert_eq!(written(&expr), "(\"a\" #\\b (2.0) . \"c\")");
+        assert_eq!(displayed(&expr), "(a b (2.0) . c)");
+    }
+
+    #[test]
+    fn write_shared_strings() {
+        let shared = Expr::from("x");
+        let expr = Expr::new_proper_list(exprs![
+            shared.clone(),
+            Expr::from("y"),
+            Expr::new_proper_list(exprs![shared.clone()]),
+            shared
+        ]);
+        let printed = |mode| Printer::new(&expr, mode).to_string();
+        assert_eq!(printed(WriteMode::Shared), r#"(#0="x" "y" (#0#) #0#)"#);
+        assert_eq!(printed(WriteMode::Write), r#"("x" "y" ("x") "x")"#);
+        assert_eq!(printed(WriteMode::Simple), r#"("x" "y" ("x") "x")"#);
+    }
+
+    #[test]
+    fn write_cyclic_pairs() {
+        let pair = Pair::new_uninit();
+        pair.init(Expr::new_symbol("a"), Expr::Pair(pair.clone()));
+        let expr = Expr::Pair(pair);
+        assert_eq!(written(&expr), "#0=(a . #0#)");
+
+        let pair = Pair::new_uninit();
+        let rest = Expr::new_dotted_list(exprs![Expr::from("b"), Expr::Pair(pair.clone())]);
+        pair.init(Expr::new_symbol("a"), rest);
+        let expr = Expr::new_proper_list(exprs![Expr::Pair(pair.clone()), Expr::Pair(pair)]);
+        let printed = |mode| Printer::new(&expr, mode).to_string();
+        assert_eq!(printed(WriteMode::Write), r#"(#0=(a "b" . #0#) #0#)"#);
+        assert_eq!(printed(WriteMode::Simple), r#"(#0=(a "b" . #0#) #0#)"#);
+        assert_eq!(printed(WriteMode::Display), "(#0=(a b . #0#) #0#)");
+
+        // pairs which occur once are written as lists
+        let pair = Pair::new_uninit();
+        pair.init(Expr::new_symbol("a"), Expr::new_empty_list());
+        assert_eq!(written(&Expr::Pair(pair)), "(a)");
+    }
+
+    #[test]
+    fn written_reads_back() {
+        let exprs = [
+            Expr::from("tab\t \"quoted\" \\ \u{1}"),
+            Expr::new_symbol("with space"),
+            Expr::new_symbol("semi;colon"),
+            Expr::new_symbol(""),
+            Expr::Char('('),
+            Expr::Char('\u{1}'),
+            Expr::Char(' '),
+            Expr::Float(f64::INFINITY),
+            Expr::Float(1e21),
+            Expr::Boolean(false),
+        ];
+        for expr in exprs {
+            let read = crate::parser::parse_str(&written(&expr)).unwrap();
+            assert_eq!(read, vec![expr]);
+        }
+    }
+}
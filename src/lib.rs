@@ -7,19 +7,65 @@
 //! let result = engine.eval::<i64>("(+ 1 2)").unwrap();
 //! assert_eq!(result, Ok(3));
 //! ```
+#[cfg(feature = "send")]
+mod actor;
 mod evaluator;
 mod expr;
 mod parser;
+#[cfg(feature = "send")]
+mod send;
 mod utils;
 
-pub use evaluator::EnvRef;
-use evaluator::EvalError;
-use expr::Procedure;
+#[cfg(feature = "send")]
+pub use actor::{Actor, ActorHandle, Mailbox};
+pub use evaluator::{
+    Backtrace, Capability, EnvRef, ErrorKind, Frame, InterruptHandle, Limits, LispDMError,
+};
 pub use expr::{
-    Arity, Expr, Exprs, FromExpr, FromExprResult, List, ProcedureFn, ProcedureKind,
-    ProcedureResult, ProcedureReturn,
+    Arity, Callee, Expr, Exprs, Foreign, ForeignObject, ForeignRef, FromExpr, FromExprResult,
+    IntoArgs, IntoProcedure, IntoProcedureValue, List, Pair, Printer, Procedure, ProcedureClosure,
+    ProcedureFn, ProcedureKind, ProcedureResult, ProcedureReturn, WriteMode,
 };
-use parser::ParseError;
+pub use parser::Span;
+#[cfg(feature = "send")]
+pub use send::{SendEngine, SendError, SendErrorKind};
+
+/// Derives [`FromExpr`](trait@FromExpr) and conversion into [`Expr`] for structs and enums.
+#[cfg(feature = "derive")]
+pub use lispdm_macros::{FromExpr, IntoExpr};
+
+#[cfg(feature = "serde")]
+pub use expr::serde;
+
+/// Reads Scheme code at compile time into [`Exprs`], which are evaluated with [`Engine::eval_exprs`].
+///
+/// Syntax errors are reported as compile errors at the token, where reading failed.
+/// Rust expressions are interpolated with `#{expr}` and converted into [`Expr`].
+///
+/// Code, which is not made of valid Rust tokens, e.g. `'(1 2)` or `#\a`,
+/// is written in a raw string literal instead.
+///
+/// # Examples
+/// ```
+/// use lispdm::{lisp, Engine};
+/// let mut engine = Engine::default();
+/// let n = 4;
+/// let code = lisp! {
+///     (define (square x) (* x x))
+///     (square #{n})
+/// };
+/// assert_eq!(engine.eval_exprs::<i64>(code).unwrap(), Ok(16));
+///
+/// let code = lisp!(r"(map (lambda (c) (char->integer c)) '(#\a #\b))");
+/// assert_eq!(engine.eval_exprs::<Vec<i64>>(code).unwrap(), Ok(vec![97, 98]));
+/// ```
+#[cfg(feature = "macros")]
+pub use lispdm_macros::lisp;
+
+// used by the code generated by derive macros
+#[doc(hidden)]
+pub use expr::derive as __private;
+use std::{cell::RefCell, collections::HashSet, path::PathBuf, rc::Rc};
 
 /// Prelude of LispDM.
 ///
@@ -28,38 +74,6 @@ use parser::ParseError;
 /// Loaded by default when creating a new instance of [`Engine`].
 pub const PRELUDE: &str = include_str!("./prelude.scm");
 
-#[derive(Debug)]
-/// Error type that represents all possible errors in LispDM.
-pub enum LispDMError {
-    /// Error occurred while parsing source code.
-    ParseError(String),
-    /// Error occurred while evaluating expressions.
-    EvalError(String),
-}
-
-impl std::error::Error for LispDMError {}
-
-impl std::fmt::Display for LispDMError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::ParseError(err) => write!(f, "parse error: {}", err),
-            Self::EvalError(err) => write!(f, "eval error: {}", err),
-        }
-    }
-}
-
-impl From<ParseError> for LispDMError {
-    fn from(err: ParseError) -> Self {
-        Self::ParseError(err.to_string())
-    }
-}
-
-impl From<EvalError> for LispDMError {
-    fn from(err: EvalError) -> Self {
-        Self::EvalError(err.to_string())
-    }
-}
-
 /// Provides the main functionality of LispDM.
 ///
 /// Holds the environment and provides methods interacting with it.
@@ -69,7 +83,7 @@ pub struct Engine {
 
 impl Engine {
     fn load_prelude(&mut self) {
-        self.eval::<()>(PRELUDE)
+        self.eval_source::<()>(PRELUDE, "prelude.scm")
             .expect("failed to load prelude!")
             .unwrap();
     }
@@ -115,9 +129,197 @@ impl Engine {
     /// ```
     pub fn eval<R: FromExpr>(&mut self, src: &str) -> Result<FromExprResult<R>, LispDMError> {
         let ast = parser::parse_str(src).map_err(LispDMError::from)?;
-        evaluator::eval_exprs(ast, &mut self.root_env)
-            .map(R::from_expr)
-            .map_err(LispDMError::from)
+        self.eval_ast(ast)
+    }
+
+    /// Evaluates source code read from `file`, same as [`eval`](#method.eval).
+    ///
+    /// Locations of errors include the name of the file.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::Engine;
+    /// let mut engine = Engine::default();
+    /// let err = engine.eval_source::<()>("(car '())", "foo.scm").unwrap_err();
+    /// assert!(err.to_string().starts_with("foo.scm:1:1: "));
+    /// ```
+    pub fn eval_source<R: FromExpr>(
+        &mut self,
+        src: &str,
+        file: &str,
+    ) -> Result<FromExprResult<R>, LispDMError> {
+        let ast = parser::parse_str_with_file(src, file).map_err(LispDMError::from)?;
+        self.eval_ast(ast)
+    }
+
+    /// Evaluates expressions, which are already read, e.g. by `lisp!`, same as [`eval`](#method.eval).
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Engine, Expr, Exprs};
+    /// let mut engine = Engine::default();
+    /// let exprs = Exprs::from([Expr::new_proper_list(Exprs::from([
+    ///     Expr::new_symbol("+"),
+    ///     Expr::Integer(1),
+    ///     Expr::Integer(2),
+    /// ]))]);
+    /// assert_eq!(engine.eval_exprs::<i64>(exprs).unwrap(), Ok(3));
+    /// ```
+    pub fn eval_exprs<R: FromExpr>(
+        &mut self,
+        exprs: Exprs,
+    ) -> Result<FromExprResult<R>, LispDMError> {
+        self.eval_ast(exprs)
+    }
+
+    /// Evaluates the given source code same as [`eval`](#method.eval), but bounded by `limits`.
+    ///
+    /// If evaluation runs out of fuel, fails with [`ErrorKind::OutOfFuel`].
+    /// If it does not finish before the deadline, fails with [`ErrorKind::DeadlineExceeded`].
+    /// Definitions evaluated before the limit was hit are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Engine, ErrorKind, Limits};
+    /// use std::time::Duration;
+    /// let mut engine = Engine::default();
+    ///
+    /// let limits = Limits::new().with_fuel(10_000);
+    /// let err = engine
+    ///     .eval_with_limits::<()>("(let loop () (loop))", limits)
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), &ErrorKind::OutOfFuel);
+    ///
+    /// let limits = Limits::new().with_timeout(Duration::from_millis(10));
+    /// let err = engine
+    ///     .eval_with_limits::<()>("(let loop () (loop))", limits)
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), &ErrorKind::DeadlineExceeded);
+    /// ```
+    pub fn eval_with_limits<R: FromExpr>(
+        &mut self,
+        src: &str,
+        limits: Limits,
+    ) -> Result<FromExprResult<R>, LispDMError> {
+        let runtime = self.root_env.runtime();
+        runtime.set_limits(limits);
+        let result = self.eval(src);
+        runtime.set_limits(Limits::default());
+        result
+    }
+
+    /// Calls a procedure with `args` and returns the result, same as [`eval`](#method.eval).
+    ///
+    /// Procedure is either a name, which is looked up in the root environment,
+    /// or a [`Procedure`] handle, e.g. a callback returned by an earlier evaluation.
+    /// Arguments are converted with [`IntoArgs`], usually from a tuple of values,
+    /// which can be converted into [`Expr`]. Tail calls of the procedure are evaluated
+    /// same as in [`eval`](#method.eval), so the result is the final value.
+    ///
+    /// Calling a special form fails with [`ErrorKind::TypeMismatch`],
+    /// because its arguments can not be evaluated.
+    ///
+    /// # Examples
+    /// Call a procedure by name:
+    /// ```
+    /// use lispdm::Engine;
+    /// let mut engine = Engine::default();
+    /// engine.eval::<()>("(define (add a b) (+ a b))").unwrap().unwrap();
+    /// let result = engine.call::<i64, _, _>("add", (1, 2)).unwrap();
+    /// assert_eq!(result, Ok(3));
+    /// ```
+    ///
+    /// Store a procedure and call it later:
+    /// ```
+    /// use lispdm::{Engine, Expr, Procedure};
+    /// let mut engine = Engine::default();
+    /// let callback = engine
+    ///     .eval::<Procedure>("(lambda (name) (string-append \"hello, \" name))")
+    ///     .unwrap()
+    ///     .unwrap();
+    /// let result = engine.call::<Expr, _, _>(&callback, ("world",)).unwrap();
+    /// assert_eq!(result, Ok(Expr::new_string("hello, world".to_string())));
+    /// ```
+    pub fn call<R: FromExpr, C: Callee, A: IntoArgs>(
+        &mut self,
+        callee: C,
+        args: A,
+    ) -> Result<FromExprResult<R>, LispDMError> {
+        let proc = callee.into_procedure(&self.root_env)?;
+        if proc.is_special_form() {
+            return Err(LispDMError::new(ErrorKind::TypeMismatch(format!(
+                "expected procedure, got {}",
+                proc
+            ))));
+        }
+        let args = args.into_args();
+        self.run(|env| evaluator::apply_to_value(&proc, args, env))
+    }
+
+    fn eval_ast<R: FromExpr>(&mut self, ast: Exprs) -> Result<FromExprResult<R>, LispDMError> {
+        self.run(|env| evaluator::eval_exprs(ast, env))
+    }
+
+    fn run<R: FromExpr>(
+        &mut self,
+        eval: impl FnOnce(&mut EnvRef) -> Result<Expr, LispDMError>,
+    ) -> Result<FromExprResult<R>, LispDMError> {
+        // only a running evaluation can be interrupted
+        self.root_env.runtime().clear_interrupt();
+        let result = eval(&mut self.root_env);
+        if let Err(err) = &result {
+            if let ErrorKind::Exit(_) = err.kind() {
+                // output written before `exit` is not lost if the host exits the process
+                let _ = self.root_env.runtime().flush_output_ports();
+            }
+        }
+        self.flush_standard_ports();
+        result.map(R::from_expr)
+    }
+
+    // writes buffered output of the current output and error ports,
+    // so it is not interleaved with whatever the caller prints next
+    fn flush_standard_ports(&self) {
+        // errors are ignored, same as for `print!` to a closed stdout
+        let _ = self.root_env.current_output_port().borrow_mut().flush();
+        let _ = self.root_env.current_error_port().borrow_mut().flush();
+    }
+
+    /// Returns the maximum depth of nested evaluations.
+    ///
+    /// Defaults to 10000.
+    pub fn max_depth(&self) -> usize {
+        self.root_env.runtime().max_depth()
+    }
+
+    /// Sets the maximum depth of nested evaluations.
+    ///
+    /// Each call of a procedure, which is not in a tail position, and each evaluation of an argument
+    /// is nested in the evaluation of the enclosing form. Evaluation nested deeper than `max_depth`
+    /// fails with [`ErrorKind::RecursionDepthExceeded`], and the engine can still be used after it.
+    ///
+    /// Nested evaluations are not limited by the native stack, so larger limits only cost memory.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Engine, ErrorKind};
+    /// let mut engine = Engine::default();
+    /// engine.set_max_depth(100);
+    /// engine.eval::<()>("(define (f n) (if (= n 0) 0 (+ 1 (f (- n 1)))))").unwrap();
+    ///
+    /// let err = engine.eval::<i64>("(f 1000)").unwrap_err();
+    /// assert_eq!(err.kind(), &ErrorKind::RecursionDepthExceeded(100));
+    /// assert_eq!(engine.eval::<i64>("(f 10)").unwrap(), Ok(10));
+    /// ```
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.root_env.runtime().set_max_depth(max_depth);
+    }
+
+    /// Returns a handle, which interrupts evaluation of the engine.
+    ///
+    /// Handle can be cloned and sent to other threads, e.g. to a signal handler.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.root_env.runtime().interrupt_handle()
     }
 
     /// Returns reference to the root environment.
@@ -145,16 +347,21 @@ impl Engine {
     /// Registers a new procedure in the root environment.
     /// Can be normal procedure or a special form.
     ///
+    /// Procedure gets its arguments unconverted, see [`register`](#method.register)
+    /// to register a function with typed arguments.
+    ///
     /// # Examples
     /// Register a normal procedure:
     /// ```
-    /// use lispdm::{Engine, EnvRef, Expr, Exprs, ProcedureKind, Arity, ProcedureResult,
-    /// ProcedureReturn};
+    /// use lispdm::{Engine, EnvRef, ErrorKind, Expr, Exprs, ProcedureKind, Arity,
+    /// ProcedureResult, ProcedureReturn};
     ///
     /// fn sum3(args: Exprs, _: &mut EnvRef) -> ProcedureResult {
     ///    let mut sum = 0;
     ///    for arg in args {
-    ///        sum += arg.into::<i64>().map_err(|_| "expected integer")?;
+    ///        sum += arg
+    ///            .into::<i64>()
+    ///            .map_err(|_| ErrorKind::TypeMismatch("expected integer".to_string()))?;
     ///    }
     ///    Ok(ProcedureReturn::Value(Expr::Integer(sum)))
     /// }
@@ -172,8 +379,12 @@ impl Engine {
     /// assert_eq!(result, 6);
     ///
     /// // Error: wrong arity
-    /// let result = engine.eval::<i64>("(sum3 1 2)");
-    /// assert!(result.is_err());
+    /// let err = engine.eval::<i64>("(sum3 1 2)").unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::Arity { got: 2, .. }));
+    ///
+    /// // Error: returned by the procedure
+    /// let err = engine.eval::<i64>("(sum3 1 2 \"3\")").unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::TypeMismatch(_)));
     pub fn register_fn<S: ToString>(
         &mut self,
         name: S,
@@ -186,6 +397,128 @@ impl Engine {
             Procedure::new_atomic(name.to_string(), kind, proc, arity),
         );
     }
+
+    /// Registers a Rust function or closure with typed arguments as a new procedure
+    /// in the root environment.
+    ///
+    /// Arguments are converted with [`FromExpr`] and the result with [`IntoProcedureValue`],
+    /// arity is the number of arguments of the function. See [`IntoProcedure`].
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Engine, ErrorKind};
+    ///
+    /// let mut engine = Engine::default();
+    /// engine.register("add", |a: i64, b: i64| a + b);
+    /// engine.register("checked-div", |a: i64, b: i64| {
+    ///     a.checked_div(b).ok_or("division by zero")
+    /// });
+    ///
+    /// assert_eq!(engine.eval::<i64>("(add 1 2)").unwrap(), Ok(3));
+    /// assert_eq!(engine.eval::<i64>("(checked-div 7 2)").unwrap(), Ok(3));
+    ///
+    /// let err = engine.eval::<i64>("(add 1 \"2\")").unwrap_err();
+    /// assert_eq!(
+    ///     err.kind(),
+    ///     &ErrorKind::TypeMismatch("expected integer as argument 2 of add, got string".to_string())
+    /// );
+    /// let err = engine.eval::<i64>("(checked-div 1 0)").unwrap_err();
+    /// assert_eq!(err.kind(), &ErrorKind::Runtime("division by zero".to_string()));
+    /// ```
+    pub fn register<S: ToString, Args, F: IntoProcedure<Args>>(&mut self, name: S, proc: F) {
+        let name = name.to_string();
+        let arity = proc.arity();
+        let proc = proc.into_closure(&name);
+        self.root_env.add(
+            name.clone(),
+            Procedure::new_atomic_closure(name, ProcedureKind::Procedure, proc, arity),
+        );
+    }
+
+    /// Registers a closure as a new procedure in the root environment,
+    /// same as [`register_fn`](#method.register_fn).
+    ///
+    /// Closure can capture state of the host, e.g. a connection or a counter.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Arity, Engine, Expr, ProcedureKind, ProcedureReturn};
+    /// use std::{cell::Cell, rc::Rc};
+    ///
+    /// let counter = Rc::new(Cell::new(0));
+    /// let mut engine = Engine::default();
+    /// let calls = counter.clone();
+    /// engine.register_closure("tick", ProcedureKind::Procedure, Arity::Exact(0), move |_, _| {
+    ///     calls.set(calls.get() + 1);
+    ///     Ok(ProcedureReturn::Value(Expr::Integer(calls.get())))
+    /// });
+    ///
+    /// let result = engine.eval::<i64>("(tick) (tick)").unwrap().unwrap();
+    /// assert_eq!(result, 2);
+    /// assert_eq!(counter.get(), 2);
+    /// ```
+    pub fn register_closure<S, F>(&mut self, name: S, kind: ProcedureKind, arity: Arity, proc: F)
+    where
+        S: ToString,
+        F: Fn(Exprs, &mut EnvRef) -> ProcedureResult + 'static,
+    {
+        self.root_env.add(
+            name.to_string(),
+            Procedure::new_atomic_closure(name.to_string(), kind, Rc::new(proc), arity),
+        );
+    }
+
+    /// Registers a closure, which mutates its state, as a new procedure in the root environment,
+    /// same as [`register_closure`](#method.register_closure).
+    ///
+    /// Closure is kept in a [`RefCell`], so a call of the procedure from inside of itself
+    /// fails with an error.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{Arity, Engine, Expr, ProcedureKind, ProcedureReturn};
+    ///
+    /// let mut log = Vec::new();
+    /// let mut engine = Engine::default();
+    /// engine.register_closure_mut("log", ProcedureKind::Procedure, Arity::Exact(1), move |args, _| {
+    ///     log.extend(args);
+    ///     Ok(ProcedureReturn::Value(Expr::Integer(log.len() as i64)))
+    /// });
+    ///
+    /// let result = engine.eval::<i64>("(log 'a) (log 'b)").unwrap().unwrap();
+    /// assert_eq!(result, 2);
+    /// ```
+    pub fn register_closure_mut<S, F>(
+        &mut self,
+        name: S,
+        kind: ProcedureKind,
+        arity: Arity,
+        proc: F,
+    ) where
+        S: ToString,
+        F: FnMut(Exprs, &mut EnvRef) -> ProcedureResult + 'static,
+    {
+        let name = name.to_string();
+        let proc = RefCell::new(proc);
+        let proc_name = name.clone();
+        self.register_closure(name, kind, arity, move |args, env| {
+            let mut proc = proc.try_borrow_mut().map_err(|_| {
+                LispDMError::from(format!(
+                    "{} can not be called from inside of itself",
+                    proc_name
+                ))
+            })?;
+            proc(args, env)
+        });
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // environments may form reference cycles through closures,
+        // so ports bound in them are not guaranteed to be dropped and flushed
+        let _ = self.root_env.runtime().flush_output_ports();
+    }
 }
 
 impl Default for Engine {
@@ -197,3 +530,89 @@ impl Default for Engine {
         engine
     }
 }
+
+/// Builds an [`Engine`] with selected capabilities, e.g. to run untrusted scripts.
+///
+/// Builder starts without capabilities, so procedures accessing files, environment variables
+/// or the process are not defined, standard input is empty and standard output is discarded.
+/// [`Engine::default`] has all of the capabilities.
+///
+/// # Examples
+/// Sandbox, which can only read files from a directory:
+/// ```
+/// use lispdm::{Capability, EngineBuilder, ErrorKind};
+/// let dir = std::env::temp_dir().join("lispdm_engine_builder_example");
+/// std::fs::create_dir_all(&dir).unwrap();
+///
+/// let mut engine = EngineBuilder::new()
+///     .allow(Capability::FsRead)
+///     .root_dir(&dir)
+///     .build();
+///
+/// let err = engine.eval::<()>("(delete-file \"data.txt\")").unwrap_err();
+/// assert!(matches!(err.kind(), ErrorKind::UnboundVariable(_)));
+///
+/// let err = engine.eval::<()>("(load \"../secret.scm\")").unwrap_err();
+/// assert!(matches!(err.kind(), ErrorKind::PermissionDenied(_)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EngineBuilder {
+    capabilities: HashSet<Capability>,
+    root_dir: Option<PathBuf>,
+    prelude: bool,
+}
+
+impl EngineBuilder {
+    /// Creates a builder of an engine without capabilities.
+    pub fn new() -> Self {
+        Self {
+            capabilities: HashSet::new(),
+            root_dir: None,
+            prelude: true,
+        }
+    }
+
+    /// Allows scripts to use `capability`.
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+
+    /// Allows scripts to use all capabilities.
+    pub fn allow_all(mut self) -> Self {
+        self.capabilities.extend(Capability::ALL);
+        self
+    }
+
+    /// Confines files accessed by scripts to `root_dir`.
+    ///
+    /// Relative paths are resolved against `root_dir`. Accessing a file outside of it,
+    /// including through `..` or a symlink, fails with [`ErrorKind::PermissionDenied`].
+    pub fn root_dir<P: Into<PathBuf>>(mut self, root_dir: P) -> Self {
+        self.root_dir = Some(root_dir.into());
+        self
+    }
+
+    /// Does not load the prelude, same as [`Engine::new_without_prelude`].
+    pub fn without_prelude(mut self) -> Self {
+        self.prelude = false;
+        self
+    }
+
+    /// Creates the engine.
+    pub fn build(self) -> Engine {
+        let root_env = evaluator::new_sandboxed_root_env(&self.capabilities, self.root_dir);
+        let mut engine = Engine { root_env };
+        if self.prelude {
+            engine.load_prelude();
+        }
+
+        engine
+    }
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
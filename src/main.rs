@@ -1,6 +1,8 @@
 mod repl;
 
-use lispdm::{Engine, Expr};
+use std::process::ExitCode;
+
+use lispdm::{Engine, ErrorKind, Expr, LispDMError};
 
 fn print_help() {
     println!("LispDM {}", env!("CARGO_PKG_VERSION"));
@@ -12,39 +14,64 @@ fn print_help() {
     println!("    -e, --eval     Evaluates given string");
 }
 
-fn main() {
+/// Prints error with its backtrace to stderr.
+fn report_error(err: &LispDMError) {
+    eprintln!("Error: {}", err);
+    if !err.backtrace().is_empty() {
+        eprint!("{}", err.backtrace());
+    }
+}
+
+/// Returns exit code of the process, if `err` is a request to exit.
+///
+/// Emergency exit terminates the process immediately, without flushing output ports.
+fn exit_code(err: &LispDMError) -> Option<ExitCode> {
+    match err.kind() {
+        // exit codes are truncated to a byte, same as by the operating system
+        ErrorKind::Exit(status) => Some(ExitCode::from(*status as u8)),
+        ErrorKind::EmergencyExit(status) => std::process::exit(*status),
+        _ => None,
+    }
+}
+
+/// Exits with the code requested by the script, or reports `err` and fails.
+fn handle_error(err: LispDMError) -> ExitCode {
+    exit_code(&err).unwrap_or_else(|| {
+        report_error(&err);
+        ExitCode::FAILURE
+    })
+}
+
+fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().skip(1).collect();
     let mut engine = Engine::default();
 
     if args.is_empty() {
         // no args - start REPL
-        repl::start(engine);
+        repl::start(engine)
     } else {
         // check for flags
         match args[0].as_str() {
             // print help
             "-h" | "--help" => {
                 print_help();
-                std::process::exit(0);
+                ExitCode::SUCCESS
             }
             // evaluate given string
             "-e" | "--eval" => {
                 let src = match args.get(1) {
                     Some(src) => src,
                     None => {
-                        println!("Expected string to evaluate for -e flag");
-                        std::process::exit(1);
+                        eprintln!("Expected string to evaluate for -e flag");
+                        return ExitCode::FAILURE;
                     }
                 };
                 match engine.eval::<Expr>(src) {
                     Ok(val) => {
                         println!("{}", val.unwrap());
-                        std::process::exit(0);
-                    }
-                    Err(err) => {
-                        println!("Error: {}", err);
-                        std::process::exit(1);
+                        ExitCode::SUCCESS
                     }
+                    Err(err) => handle_error(err),
                 }
             }
             // no flags - interpret file
@@ -53,13 +80,13 @@ fn main() {
                 let src = match std::fs::read_to_string(filename) {
                     Ok(src) => src,
                     Err(err) => {
-                        println!("Error: failed to read a file '{}': {}", filename, err);
-                        std::process::exit(1);
+                        eprintln!("Error: failed to read a file '{}': {}", filename, err);
+                        return ExitCode::FAILURE;
                     }
                 };
-                if let Err(err) = engine.eval::<()>(&src) {
-                    println!("Error: {}", err);
-                    std::process::exit(1);
+                match engine.eval_source::<()>(&src, filename) {
+                    Ok(_) => ExitCode::SUCCESS,
+                    Err(err) => handle_error(err),
                 }
             }
         }
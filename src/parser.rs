@@ -0,0 +1,184 @@
+use crate::{
+    expr::{Expr, Exprs, List, Pair},
+    utils::grow_stack,
+};
+use lispdm_parser::Datum;
+use std::collections::HashMap;
+
+pub(crate) use lispdm_parser::reads_as_symbol;
+pub use lispdm_parser::{ParseError, Span};
+
+pub fn parse_str(string: &str) -> Result<Exprs, ParseError> {
+    lispdm_parser::parse_str(string).map(into_exprs)
+}
+
+/// Parses source code read from `file`, so list forms and errors are located in it.
+pub fn parse_str_with_file(string: &str, file: &str) -> Result<Exprs, ParseError> {
+    lispdm_parser::parse_str_with_file(string, file).map(into_exprs)
+}
+
+fn into_exprs(data: Vec<Datum>) -> Exprs {
+    let mut exprs: Exprs = data
+        .into_iter()
+        // datum labels are not shared between top-level data
+        .map(|datum| into_expr(datum, &mut HashMap::new()))
+        .collect();
+    if exprs.is_empty() {
+        exprs.push_back(Expr::Void);
+    }
+    exprs
+}
+
+fn into_expr(datum: Datum, labels: &mut HashMap<u64, Expr>) -> Expr {
+    // nested lists are converted recursively, so deep nesting grows the stack on the heap
+    grow_stack(|| match datum {
+        Datum::Boolean(boolean) => Expr::Boolean(boolean),
+        Datum::Integer(int) => Expr::Integer(int),
+        Datum::Float(float) => Expr::Float(float),
+        Datum::Char(char) => Expr::Char(char),
+        Datum::String(string) => Expr::new_string(string),
+        Datum::Symbol(symbol) => Expr::Symbol(symbol),
+        Datum::List(mut list) => {
+            let items = std::mem::take(&mut list.items)
+                .into_iter()
+                .map(|datum| into_expr(datum, labels))
+                .collect();
+            let tail = list.tail.take().map(|tail| into_expr(*tail, labels));
+            Expr::List(List::new(items, tail).with_span(list.span.take()))
+        }
+        Datum::Labeled(label, datum) if refers_to(&datum, label) => {
+            into_cyclic_expr(label, *datum, labels)
+        }
+        Datum::Labeled(label, datum) => {
+            let expr = into_expr(*datum, labels);
+            labels.insert(label, expr.clone());
+            expr
+        }
+        // labeled data are values, so a reference is a copy,
+        // which still shares strings with the labeled datum;
+        // the parser checks that references follow their labels
+        Datum::Reference(label) => labels[&label].clone(),
+    })
+}
+
+// lists own their elements, so a datum referring to its own label is built around a shared pair,
+// which is referred to before its contents are converted
+fn into_cyclic_expr(label: u64, mut datum: Datum, labels: &mut HashMap<u64, Expr>) -> Expr {
+    let pair = Pair::new_uninit();
+    labels.insert(label, Expr::Pair(pair.clone()));
+    // labels of the same datum, e.g. `#0=#1=(a . #0#)`, refer to the same pair
+    while let Datum::Labeled(label, inner) = datum {
+        labels.insert(label, Expr::Pair(pair.clone()));
+        datum = *inner;
+    }
+    let mut list = match datum {
+        Datum::List(list) if !list.items.is_empty() => list,
+        // only lists contain references, and the parser rejects labels of only a reference
+        _ => unreachable!("cyclic datum is a list"),
+    };
+    let mut items = std::mem::take(&mut list.items)
+        .into_iter()
+        .map(|datum| into_expr(datum, labels));
+    let car = items.next().unwrap();
+    let rest: Exprs = items.collect();
+    let tail = list.tail.take().map(|tail| into_expr(*tail, labels));
+    let cdr = match tail {
+        Some(tail) if rest.is_empty() => tail,
+        tail => Expr::List(List::new(rest, tail)),
+    };
+    pair.init(car, cdr);
+    Expr::Pair(pair)
+}
+
+// checks if `datum` refers to `label`, references to a label are only inside of its datum
+fn refers_to(datum: &Datum, label: u64) -> bool {
+    let mut data = vec![datum];
+    while let Some(datum) = data.pop() {
+        match datum {
+            Datum::Reference(reference) if *reference == label => return true,
+            Datum::List(list) => data.extend(list.items.iter().chain(list.tail.as_deref())),
+            Datum::Labeled(_, datum) => data.push(datum),
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exprs;
+
+    #[test]
+    fn parse_datum_labels() {
+        let parsed = parse_str("'(#0=(a \"b\") #0#)").unwrap();
+        let labeled = Expr::new_proper_list(exprs![Expr::new_symbol("a"), Expr::new_string("b")]);
+        assert_eq!(
+            parsed,
+            vec![Expr::new_proper_list(exprs![
+                Expr::new_symbol("quote"),
+                Expr::new_proper_list(exprs![labeled.clone(), labeled]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_datum_labels_share_strings() {
+        let parsed = parse_str("(#0=\"a\" #0#)").unwrap();
+        let list = parsed[0].clone().into_list().unwrap().into_exprs();
+        let first = list[0].clone().into_string().unwrap();
+        let second = list[1].clone().into_string().unwrap();
+        assert!(std::rc::Rc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn parse_dotted_list_with_labeled_tail() {
+        let parsed = parse_str("(a . #0=(b . c))").unwrap();
+        assert_eq!(
+            parsed,
+            vec![Expr::new_dotted_list(exprs![
+                Expr::new_symbol("a"),
+                Expr::new_symbol("b"),
+                Expr::new_symbol("c"),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_cyclic_datum_labels() {
+        let parsed = parse_str("#0=(a b . #0#)").unwrap();
+        let pair = match &parsed[0] {
+            Expr::Pair(pair) => pair.clone(),
+            expr => panic!("expected pair, got {:?}", expr),
+        };
+        assert_eq!(pair.car(), &Expr::new_symbol("a"));
+        let rest = pair.cdr().as_list().unwrap();
+        assert_eq!(rest.car(), Some(&Expr::new_symbol("b")));
+        assert_eq!(rest.last(), Some(&Expr::Pair(pair.clone())));
+
+        let parsed = parse_str("#0=#1=(a . #1#)").unwrap();
+        let pair = match &parsed[0] {
+            Expr::Pair(pair) => pair.clone(),
+            expr => panic!("expected pair, got {:?}", expr),
+        };
+        assert_eq!(pair.cdr(), &parsed[0]);
+    }
+
+    #[test]
+    fn parse_void() {
+        assert_eq!(parse_str("; comment").unwrap(), vec![Expr::Void]);
+    }
+
+    #[test]
+    fn parse_list_spans() {
+        let parsed = parse_str_with_file("(a\n  (b))", "foo.scm").unwrap();
+        let outer = parsed[0].clone().into_list().unwrap();
+        let span = outer.span().unwrap();
+        assert_eq!(
+            (span.file(), span.line(), span.column()),
+            (Some("foo.scm"), 1, 1)
+        );
+        let inner = outer.iter().nth(1).unwrap().as_list().unwrap();
+        assert_eq!(inner.span().unwrap().to_string(), "foo.scm:2:3");
+    }
+}
@@ -1,4 +0,0 @@
-mod lexer;
-mod parser;
-
-pub use parser::*;
@@ -45,11 +45,6 @@
 
 (define (null? x) (equal? x '()))
 
-(define (list? x)
-  (if (pair? x)
-      (list? (cdr x))
-      (null? x)))
-
 (define (length ls)
   (define (length* ls acc)
     (if (null? ls)
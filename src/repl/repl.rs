@@ -1,5 +1,6 @@
 use std::ops::Deref;
 use std::path::Path;
+use std::process::ExitCode;
 
 use lispdm::{Engine, Expr};
 use rustyline::history::FileHistory;
@@ -15,9 +16,14 @@ struct InputValidator {
     highlighter: MatchingBracketHighlighter,
 }
 
-pub fn start(mut engine: Engine) {
+pub fn start(mut engine: Engine) -> ExitCode {
     println!("LispDM v0.0.1");
     println!("Use (exit), or Ctrl-D to exit REPL");
+    println!("Use Ctrl-C to interrupt evaluation");
+
+    // prompt reads Ctrl-C as input, so the handler is called only while evaluating
+    let interrupt = engine.interrupt_handle();
+    ctrlc::set_handler(move || interrupt.interrupt()).expect("Failed to set Ctrl-C handler");
 
     let expanded_path = shellexpand::tilde("~/.lispdm_history");
     let history_path = Path::new(expanded_path.deref());
@@ -49,7 +55,10 @@ pub fn start(mut engine: Engine) {
                     Ok(expr) => {
                         println!("{}", expr.unwrap());
                     }
-                    Err(err) => println!("Error: {}", err),
+                    Err(err) => match crate::exit_code(&err) {
+                        Some(code) => return code,
+                        None => crate::report_error(&err),
+                    },
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -58,11 +67,11 @@ pub fn start(mut engine: Engine) {
             }
             Err(ReadlineError::Eof) => {
                 println!("CTRL-D");
-                break;
+                return ExitCode::SUCCESS;
             }
             Err(err) => {
-                println!("Error: {:?}", err);
-                break;
+                eprintln!("Error: {:?}", err);
+                return ExitCode::FAILURE;
             }
         }
     }
@@ -0,0 +1,425 @@
+//! Engine, which can be sent between threads.
+//!
+//! Expressions and environments are shared with `Rc`, so an [`Engine`] stays on the thread,
+//! where it was created. [`SendEngine`] runs an engine on its own thread
+//! and is a handle to it, which can be moved to and shared between other threads.
+
+use crate::{
+    expr::{Printer, WriteMode},
+    Arity, Engine, ErrorKind, FromExpr, InterruptHandle, IntoArgs, IntoProcedure, LispDMError,
+};
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc,
+    thread::{self, JoinHandle},
+};
+
+type Job = Box<dyn FnOnce(&mut Engine) + Send>;
+
+/// Handle to an [`Engine`], which runs on its own thread.
+///
+/// Implements [`Send`] and [`Sync`], so it can be handed to a worker pool or shared in an [`Arc`](std::sync::Arc).
+/// Jobs are evaluated one by one in the order they were sent, and wait for the result.
+/// Only values, which implement [`Send`], are passed to and from the engine,
+/// so expressions and procedures stay on its thread.
+///
+/// A panic in a job is resumed on the thread, which sent it, and the engine keeps running.
+/// The thread of the engine is stopped, when the handle is dropped.
+///
+/// # Examples
+/// ```
+/// use lispdm::SendEngine;
+/// use std::{sync::Arc, thread};
+///
+/// let engine = Arc::new(SendEngine::new());
+/// engine.eval::<()>("(define (square x) (* x x))").unwrap();
+///
+/// let workers: Vec<_> = (1..=4)
+///     .map(|n| {
+///         let engine = Arc::clone(&engine);
+///         thread::spawn(move || engine.call::<i64, _>("square", (n,)).unwrap())
+///     })
+///     .collect();
+/// let squares: Vec<i64> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+/// assert_eq!(squares, vec![1, 4, 9, 16]);
+/// ```
+pub struct SendEngine {
+    jobs: Option<mpsc::Sender<Job>>,
+    interrupt_handle: InterruptHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SendEngine {
+    /// Runs [`Engine::default`] on a new thread.
+    pub fn new() -> Self {
+        Self::spawn(Engine::default)
+    }
+
+    /// Runs the engine created by `init` on a new thread.
+    ///
+    /// `init` is called on the new thread, so it can register procedures,
+    /// which do not implement [`Send`].
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::{EngineBuilder, SendEngine};
+    /// let engine = SendEngine::spawn(|| EngineBuilder::new().without_prelude().build());
+    /// assert_eq!(engine.eval::<i64>("(+ 1 2)"), Ok(3));
+    /// ```
+    pub fn spawn<F: FnOnce() -> Engine + Send + 'static>(init: F) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (handle_sender, handle_receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("lispdm-engine".to_string())
+            .spawn(move || {
+                let mut engine = init();
+                let _ = handle_sender.send(engine.interrupt_handle());
+                for job in receiver {
+                    job(&mut engine);
+                }
+            })
+            .expect("failed to spawn thread of the engine");
+        let interrupt_handle = match handle_receiver.recv() {
+            Ok(interrupt_handle) => interrupt_handle,
+            // `init` panicked
+            Err(_) => match thread.join() {
+                Err(payload) => panic::resume_unwind(payload),
+                Ok(()) => unreachable!("engine thread stopped before it was created"),
+            },
+        };
+        Self {
+            jobs: Some(jobs),
+            interrupt_handle,
+            thread: Some(thread),
+        }
+    }
+
+    /// Runs `f` with the engine on its thread and returns the result.
+    ///
+    /// # Examples
+    /// ```
+    /// use lispdm::SendEngine;
+    /// let engine = SendEngine::new();
+    /// let depth = engine.with(|engine| {
+    ///     engine.set_max_depth(100);
+    ///     engine.max_depth()
+    /// });
+    /// assert_eq!(depth, 100);
+    /// ```
+    pub fn with<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Engine) -> T + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<thread::Result<T>>();
+        let job: Job = Box::new(move |engine| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(engine)));
+            let _ = sender.send(result);
+        });
+        self.jobs
+            .as_ref()
+            .expect("engine is running until dropped")
+            .send(job)
+            .expect("thread of the engine stopped");
+        match receiver.recv().expect("thread of the engine stopped") {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Evaluates the given source code and converts the result, same as [`Engine::eval`].
+    ///
+    /// Fails with [`SendError`], if evaluation failed or the result can not be converted into `R`.
+    pub fn eval<R: FromExpr + Send + 'static>(&self, src: &str) -> Result<R, SendError> {
+        let src = src.to_string();
+        self.with(move |engine| SendError::convert(engine.eval::<R>(&src)))
+    }
+
+    /// Calls a procedure by name with `args`, same as [`Engine::call`].
+    pub fn call<R, A>(&self, name: &str, args: A) -> Result<R, SendError>
+    where
+        R: FromExpr + Send + 'static,
+        A: IntoArgs + Send + 'static,
+    {
+        let name = name.to_string();
+        self.with(move |engine| SendError::convert(engine.call::<R, _, _>(name.as_str(), args)))
+    }
+
+    /// Registers a Rust function as a procedure, same as [`Engine::register`].
+    pub fn register<Args, F>(&self, name: &str, proc: F)
+    where
+        F: IntoProcedure<Args> + Send + 'static,
+    {
+        let name = name.to_string();
+        self.with(move |engine| engine.register(name, proc));
+    }
+
+    /// Returns a handle, which interrupts the running evaluation, see [`Engine::interrupt_handle`].
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt_handle.clone()
+    }
+}
+
+impl Default for SendEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SendEngine {
+    fn drop(&mut self) {
+        // the thread finishes the running job and stops, when there are no more jobs
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Debug for SendEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendEngine").finish_non_exhaustive()
+    }
+}
+
+/// Error of [`SendEngine`], which can be sent between threads.
+///
+/// Holds a copy of the kind of the error and its message,
+/// because errors of evaluation can hold expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendError {
+    kind: SendErrorKind,
+    message: String,
+}
+
+/// Kind of [`SendError`], same as [`ErrorKind`] with raised objects written as text.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SendErrorKind {
+    /// Source code can not be parsed.
+    Parse(String),
+    /// Variable is not bound in the environment.
+    UnboundVariable(String),
+    /// Procedure is called with a wrong number of arguments.
+    Arity {
+        /// Name of the called procedure.
+        procedure: String,
+        /// Number of arguments the procedure accepts.
+        expected: Arity,
+        /// Number of passed arguments.
+        got: usize,
+    },
+    /// Value of an unexpected type is passed to a procedure or a special form,
+    /// or the result can not be converted into the requested type.
+    TypeMismatch(String),
+    /// Reading or writing a file or a port failed.
+    Io(String),
+    /// Script is not allowed to access a resource, e.g. a file outside of the root directory.
+    PermissionDenied(String),
+    /// Object raised with `raise`, which was not handled, written same as with `write`.
+    Raise(String),
+    /// Evaluations are nested deeper than the limit, e.g. by a non-tail recursion.
+    RecursionDepthExceeded(usize),
+    /// Evaluation spent all of its fuel.
+    OutOfFuel,
+    /// Evaluation did not finish before its deadline.
+    DeadlineExceeded,
+    /// Evaluation was interrupted with [`InterruptHandle`].
+    Interrupted,
+    /// Program requested to exit with `exit`, carries the exit status.
+    Exit(i32),
+    /// Program requested to exit with `emergency-exit`, carries the exit status.
+    EmergencyExit(i32),
+    /// Any other error.
+    Runtime(String),
+}
+
+impl From<&ErrorKind> for SendErrorKind {
+    fn from(kind: &ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Parse(err) => SendErrorKind::Parse(err.clone()),
+            ErrorKind::UnboundVariable(name) => SendErrorKind::UnboundVariable(name.clone()),
+            ErrorKind::Arity {
+                procedure,
+                expected,
+                got,
+            } => SendErrorKind::Arity {
+                procedure: procedure.clone(),
+                expected: *expected,
+                got: *got,
+            },
+            ErrorKind::TypeMismatch(err) => SendErrorKind::TypeMismatch(err.clone()),
+            ErrorKind::Io(err) => SendErrorKind::Io(err.clone()),
+            ErrorKind::PermissionDenied(err) => SendErrorKind::PermissionDenied(err.clone()),
+            ErrorKind::Raise(obj) => {
+                SendErrorKind::Raise(Printer::new(obj, WriteMode::Write).to_string())
+            }
+            ErrorKind::RecursionDepthExceeded(limit) => {
+                SendErrorKind::RecursionDepthExceeded(*limit)
+            }
+            ErrorKind::OutOfFuel => SendErrorKind::OutOfFuel,
+            ErrorKind::DeadlineExceeded => SendErrorKind::DeadlineExceeded,
+            ErrorKind::Interrupted => SendErrorKind::Interrupted,
+            ErrorKind::Exit(status) => SendErrorKind::Exit(*status),
+            ErrorKind::EmergencyExit(status) => SendErrorKind::EmergencyExit(*status),
+            ErrorKind::Runtime(err) => SendErrorKind::Runtime(err.clone()),
+        }
+    }
+}
+
+impl SendError {
+    fn convert<R: FromExpr>(
+        result: Result<Result<R, crate::Expr>, LispDMError>,
+    ) -> Result<R, SendError> {
+        match result {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(expr)) => {
+                let message = R::describe_mismatch(&expr);
+                Err(SendError {
+                    kind: SendErrorKind::TypeMismatch(message.clone()),
+                    message,
+                })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> &SendErrorKind {
+        &self.kind
+    }
+
+    /// Returns the message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<LispDMError> for SendError {
+    fn from(err: LispDMError) -> Self {
+        SendError {
+            kind: err.kind().into(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SendError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, Limits};
+    use std::{sync::Arc, time::Duration};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_engine_is_send_and_sync() {
+        assert_send_sync::<SendEngine>();
+        assert_send_sync::<SendError>();
+    }
+
+    #[test]
+    fn send_engine_keeps_state() {
+        let engine = SendEngine::new();
+        engine.eval::<()>("(define counter 0)").unwrap();
+        let engine = thread::spawn(move || {
+            engine.eval::<()>("(set! counter (+ counter 1))").unwrap();
+            engine
+        })
+        .join()
+        .unwrap();
+        assert_eq!(engine.eval::<i64>("counter"), Ok(1));
+        assert_eq!(
+            engine.eval::<Vec<String>>("(list \"a\" 'b)"),
+            Ok(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn send_engine_reports_errors() {
+        let engine = SendEngine::new();
+        let err = engine.eval::<i64>("(car '())").unwrap_err();
+        assert!(err.message().contains("car"), "{}", err);
+        assert_eq!(
+            err.kind(),
+            &SendErrorKind::Runtime("expected non-empty list for car".to_string())
+        );
+        let err = engine.eval::<i64>("\"a\"").unwrap_err();
+        assert_eq!(err.message(), "expected integer, got string");
+        assert_eq!(
+            err.kind(),
+            &SendErrorKind::TypeMismatch("expected integer, got string".to_string())
+        );
+        let err = engine
+            .call::<i64, _>("undefined-procedure", ())
+            .unwrap_err();
+        assert_eq!(err.message(), "unbound variable: undefined-procedure");
+        assert_eq!(
+            err.kind(),
+            &SendErrorKind::UnboundVariable("undefined-procedure".to_string())
+        );
+        let err = engine.eval::<()>("(raise (list 'oops \"a\"))").unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &SendErrorKind::Raise("(oops \"a\")".to_string())
+        );
+        let err = engine.eval::<()>("(exit 3)").unwrap_err();
+        assert_eq!(err.kind(), &SendErrorKind::Exit(3));
+    }
+
+    #[test]
+    fn send_engine_registers_procedures() {
+        let engine = SendEngine::new();
+        let total = Arc::new(std::sync::atomic::AtomicI64::new(0));
+        let counter = Arc::clone(&total);
+        engine.register("add-total", move |n: i64| {
+            counter.fetch_add(n, std::sync::atomic::Ordering::Relaxed)
+        });
+        engine.eval::<i64>("(add-total 2) (add-total 3)").unwrap();
+        assert_eq!(total.load(std::sync::atomic::Ordering::Relaxed), 5);
+
+        // limits and other methods of the engine are used through `with`
+        let out_of_fuel = engine.with(|engine| {
+            let limits = Limits::new().with_fuel(1000);
+            let result = engine.eval_with_limits::<()>("(let loop () (loop))", limits);
+            matches!(result, Err(err) if err.kind() == &ErrorKind::OutOfFuel)
+        });
+        assert!(out_of_fuel);
+    }
+
+    #[test]
+    fn send_engine_resumes_panics() {
+        let engine = SendEngine::new();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            engine.with(|_| panic!("job panicked"));
+        }));
+        assert!(result.is_err());
+        assert_eq!(engine.eval::<i64>("(+ 1 2)"), Ok(3));
+    }
+
+    #[test]
+    fn send_engine_is_interrupted() {
+        let engine = Arc::new(SendEngine::new());
+        let interrupt_handle = engine.interrupt_handle();
+        let worker = {
+            let engine = Arc::clone(&engine);
+            thread::spawn(move || engine.eval::<()>("(let loop () (loop))"))
+        };
+        // evaluation clears interrupts sent before it started
+        while !worker.is_finished() {
+            interrupt_handle.interrupt();
+            thread::sleep(Duration::from_millis(10));
+        }
+        let err = worker.join().unwrap().unwrap_err();
+        assert!(err.message().ends_with("evaluation interrupted"), "{}", err);
+    }
+}
@@ -1,2 +1,13 @@
 mod macros;
 pub(crate) use macros::*;
+
+// when less stack than this is left, recursion continues on a new stack segment
+const STACK_RED_ZONE: usize = 128 * 1024;
+// size of stack segments allocated on the heap
+const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+// runs `f` on a new stack segment allocated on the heap if the native stack is almost exhausted,
+// so recursion over deeply nested expressions does not overflow it
+pub(crate) fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
+}
@@ -1,4 +1,8 @@
-use lispdm::{exprs, Engine, Expr};
+use lispdm::{
+    exprs, Arity, Capability, Engine, EngineBuilder, EnvRef, ErrorKind, Expr, Exprs, Foreign,
+    ForeignObject, ForeignRef, Limits, Procedure, ProcedureKind, ProcedureResult,
+};
+use std::time::Duration;
 
 macro_rules! assert_absent_in_env {
     ($env:expr, $name:expr) => {
@@ -929,6 +933,1127 @@ fn eval_macro_recursive() {
     assert_eq!(result, 1);
 }
 
+#[test]
+fn eval_read_utf8_file() {
+    let path = std::env::temp_dir().join("lispdm_read_utf8_file.txt");
+    std::fs::write(&path, "λé€😀").unwrap();
+    let source = format!(
+        "(define port (open-input-file {:?}))
+            (define first (read-char port))
+            (list first (read-string port))",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(&source).unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        result,
+        Expr::new_proper_list(exprs![
+            Expr::Char('λ'),
+            Expr::new_string("é€😀".to_string())
+        ])
+    );
+}
+
+#[test]
+fn eval_transcode_file() {
+    let path = std::env::temp_dir().join("lispdm_transcode_file.txt");
+    let source = format!(
+        "(define out (open-output-file {path:?} 'latin-1))
+            (write-string \"café\" out)
+            (close-output-port out)
+            (read-string (open-input-file {path:?} 'latin-1))",
+        path = path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(&source).unwrap().unwrap();
+    let written = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, b"caf\xE9");
+    assert_eq!(result, Expr::new_string("café".to_string()));
+}
+
+#[test]
+fn eval_open_file_unknown_option() {
+    let source = "(open-input-file \"file.txt\" 'utf-7)";
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source);
+    assert!(result.is_err());
+}
+
+#[test]
+fn eval_with_error_to_file() {
+    let path = std::env::temp_dir().join("lispdm_with_error_to_file.txt");
+    let source = format!(
+        "(with-error-to-file {:?}
+              (lambda ()
+                (write-string \"oops\" (current-error-port))
+                (flush-output-port (current-error-port))
+                (output-port? (current-error-port))))",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    let result = engine.eval::<bool>(&source).unwrap().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(result);
+    assert_eq!(written, "oops");
+}
+
+#[test]
+fn eval_file_output_port_flushed_on_close() {
+    let path = std::env::temp_dir().join("lispdm_flushed_on_close.txt");
+    let source = format!(
+        "(define port (open-output-file {:?} 'block-buffered))
+            (write-string \"abc\" port)",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    engine.eval::<()>(&source).unwrap().unwrap();
+    let before_close = std::fs::read_to_string(&path).unwrap();
+    engine
+        .eval::<()>("(close-output-port port)")
+        .unwrap()
+        .unwrap();
+    let after_close = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(before_close, "");
+    assert_eq!(after_close, "abc");
+}
+
+#[test]
+fn eval_file_output_port_closed_after_thunk() {
+    let path = std::env::temp_dir().join("lispdm_closed_after_thunk.txt");
+    let path = path.to_str().unwrap();
+    let mut engine = Engine::default();
+    for proc in ["with-output-to-file", "call-with-output-file"] {
+        let source = format!("({} {:?} (lambda () (display \"abc\")))", proc, path);
+        engine.eval::<()>(&source).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "abc", "{}", proc);
+    }
+    let source = format!(
+        "(with-error-to-file {:?} (lambda () (display \"err\" (current-error-port)) 1))",
+        path
+    );
+    assert_eq!(engine.eval::<i64>(&source), Ok(Ok(1)));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "err");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn eval_file_input_port_closed_after_thunk() {
+    let path = std::env::temp_dir().join("lispdm_input_closed_after_thunk.txt");
+    std::fs::write(&path, "abc").unwrap();
+    let mut engine = Engine::default();
+    for proc in ["with-input-from-file", "call-with-input-file"] {
+        let source = format!(
+            "(define port #f)
+            ({} {:?} (lambda () (set! port (current-input-port)) (read-char)))",
+            proc,
+            path.to_str().unwrap()
+        );
+        assert_eq!(engine.eval::<char>(&source), Ok(Ok('a')), "{}", proc);
+        let err = engine.eval::<char>("(read-char port)").unwrap_err();
+        assert!(
+            err.to_string().contains("port is closed"),
+            "{}: {}",
+            proc,
+            err
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn eval_prompt_flushed_before_reading_stdin() {
+    use std::io::{Read, Write};
+    use std::process::{Command, Stdio};
+
+    // the prompt is built at runtime, so it is not found in the debug log of evaluation
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lispdm"))
+        .args([
+            "-e",
+            "(display (list->string (map integer->char '(110 97 109 101 63))))
+             (read-char)",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0; 4096];
+        while let Ok(len @ 1..) = stdout.read(&mut buf) {
+            let _ = sender.send(buf[..len].to_vec());
+        }
+    });
+
+    let mut output = Vec::new();
+    let prompted = loop {
+        match receiver.recv_timeout(Duration::from_secs(10)) {
+            Ok(chunk) => output.extend(chunk),
+            Err(_) => break false,
+        }
+        if String::from_utf8_lossy(&output)
+            .lines()
+            .any(|line| line.starts_with("name?"))
+        {
+            break true;
+        }
+    };
+    child.stdin.take().unwrap().write_all(b"x\n").unwrap();
+    child.wait().unwrap();
+    assert!(prompted, "prompt is not written before reading stdin");
+}
+
+#[test]
+fn eval_file_output_port_flushed_on_engine_drop() {
+    let path = std::env::temp_dir().join("lispdm_flushed_on_engine_drop.txt");
+    let source = format!(
+        "(define port (open-output-file {:?}))
+            (define (write-line str) (write-string str port) (newline port))
+            (write-line \"abc\")",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    engine.eval::<()>(&source).unwrap().unwrap();
+    drop(engine);
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "abc\n");
+}
+
+#[test]
+fn eval_open_input_file_buffering_option() {
+    let source = "(open-input-file \"file.txt\" 'line-buffered)";
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source);
+    assert!(result.is_err());
+}
+
+#[test]
+fn eval_open_output_file_append() {
+    let path = std::env::temp_dir().join("lispdm_open_output_file_append.txt");
+    std::fs::write(&path, "abc\n").unwrap();
+    let source = format!(
+        "(define port (open-output-file {:?} 'append))
+            (write-string \"def\" port)
+            (close-output-port port)",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    engine.eval::<()>(&source).unwrap().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "abc\ndef");
+}
+
+#[test]
+fn eval_open_output_file_exclusive() {
+    let path = std::env::temp_dir().join("lispdm_open_output_file_exclusive.txt");
+    let _ = std::fs::remove_file(&path);
+    let source = format!(
+        "(close-output-port (open-output-file {:?} 'exclusive))",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    let created = engine.eval::<()>(&source);
+    let existing = engine.eval::<()>(&source);
+    std::fs::remove_file(&path).unwrap();
+    assert!(created.is_ok());
+    assert!(matches!(existing.unwrap_err().kind(), ErrorKind::Io(_)));
+}
+
+#[test]
+fn eval_open_output_file_conflicting_options() {
+    let path = std::env::temp_dir().join("lispdm_open_output_file_conflicting.txt");
+    let path = path.to_str().unwrap();
+    let mut engine = Engine::default();
+    let cases = [
+        ("'append 'truncate", "append and truncate"),
+        ("'utf-8 'latin-1", "utf-8 and latin-1"),
+        ("'unbuffered 'line-buffered", "unbuffered and line-buffered"),
+    ];
+    for (options, names) in cases {
+        let source = format!("(open-output-file {:?} {})", path, options);
+        let err = engine.eval::<()>(&source).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ErrorKind::Runtime(format!("conflicting open-output-file options: {}", names))
+        );
+    }
+    let source = format!(
+        "(close-output-port (open-output-file {:?} 'append 'append))",
+        path
+    );
+    assert!(engine.eval::<()>(&source).is_ok());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn eval_port_line_and_column() {
+    let source = "
+            (define port (open-input-string \"(a
+ b)\"))
+            (read-char port)
+            (read-char port)
+            (read-char port)
+            (list (port-line port) (port-column port))
+        ";
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source).unwrap().unwrap();
+    assert_eq!(
+        result,
+        Expr::new_proper_list(exprs![Expr::Integer(2), Expr::Integer(1)])
+    );
+}
+
+#[test]
+fn eval_set_port_position() {
+    let source = "
+            (define port (open-input-string \"abc\"))
+            (read-char port)
+            (define mark (port-position port))
+            (read-char port)
+            (read-char port)
+            (set-port-position! port mark)
+            (read-char port)
+        ";
+    let mut engine = Engine::default();
+    let result = engine.eval::<char>(source).unwrap().unwrap();
+    assert_eq!(result, 'b');
+}
+
+#[test]
+fn eval_write_and_display() {
+    let path = std::env::temp_dir().join("lispdm_write_and_display.txt");
+    let source = format!(
+        "(define datum (list \"a b\" #\\c 1.0 (string->symbol \"d e\")))
+            (with-output-to-file {:?}
+              (lambda ()
+                (write datum)
+                (newline)
+                (display datum)
+                (newline)
+                (write-simple 2.5)
+                (write-shared #\\space)))",
+        path.to_str().unwrap()
+    );
+    let mut engine = Engine::default();
+    engine.eval::<()>(&source).unwrap().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        written,
+        "(\"a b\" #\\c 1.0 |d e|)\n(a b c 1.0 d e)\n2.5#\\space"
+    );
+}
+
+#[test]
+fn eval_number_to_string_float() {
+    let source = "(number->string 1.0)";
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source).unwrap().unwrap();
+    assert_eq!(result, Expr::new_string("1.0".to_string()));
+}
+
+#[test]
+fn eval_write_shared_labels() {
+    let source = "
+            (define datum '(#0=\"y\" #0#))
+            (list (equal? datum '(\"y\" \"y\")) (eq? (car datum) (cadr datum)))
+        ";
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source).unwrap().unwrap();
+    assert_eq!(
+        result,
+        Expr::new_proper_list(exprs![Expr::Boolean(true), Expr::Boolean(true)])
+    );
+
+    let path = std::env::temp_dir().join("lispdm_write_shared_labels.txt");
+    let source = format!(
+        "(define s \"x\")
+            (with-output-to-file {:?}
+              (lambda () (write-shared (list s \"y\" s))))",
+        path.to_str().unwrap()
+    );
+    engine.eval::<()>(&source).unwrap().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "(#0=\"x\" \"y\" #0#)");
+}
+
+#[test]
+fn eval_cyclic_datum_label() {
+    // labels referring to their own data are read as shared pairs
+    let source = "
+            (define x '#0=(a . #0#))
+            (define y '#1=(a b #1#))
+            (list (pair? x) (list? x) (eq? x (cdr x)) (car (cdr (cdr x)))
+                  (equal? x '#2=(a a . #2#)) (equal? x '(a a a))
+                  (equal? x '#3=(a b . #3#)) (eq? y (caddr y)))
+        ";
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source).unwrap().unwrap();
+    assert_eq!(
+        result,
+        Expr::new_proper_list(exprs![
+            Expr::Boolean(true),
+            Expr::Boolean(false),
+            Expr::Boolean(true),
+            Expr::new_symbol("a"),
+            Expr::Boolean(true),
+            Expr::Boolean(false),
+            Expr::Boolean(false),
+            Expr::Boolean(true),
+        ])
+    );
+
+    let path = std::env::temp_dir().join("lispdm_cyclic_datum_label.txt");
+    let source = format!(
+        "(with-output-to-file {:?}
+              (lambda ()
+                (write x)
+                (write-shared y)
+                (display '#0=(\"b\" c . #0#))))",
+        path.to_str().unwrap()
+    );
+    engine.eval::<()>(&source).unwrap().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "#0=(a . #0#)#0=(a b #0#)#0=(b c . #0#)");
+
+    // non-cyclic references to a label are copies, so `equal?` terminates on them
+    let result = engine.eval::<bool>("(let ((x '(#0=(a b) #0# #0#))) (equal? (car x) (caddr x)))");
+    assert_eq!(result, Ok(Ok(true)));
+
+    let err = engine.eval::<Expr>("'#0=#0#").unwrap_err();
+    assert!(
+        matches!(err.kind(), ErrorKind::Parse(message) if message.contains("labels only a reference to itself")),
+        "{}",
+        err
+    );
+    let err = engine.eval::<Expr>("#0=(a . #0#)").unwrap_err();
+    assert!(
+        err.to_string().contains("cyclic list cannot be evaluated"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn eval_lexical_syntax() {
+    let source = r#"
+            #| block comment #| nested |# |#
+            (define |my var| "say \"hi\"\x21;\n")
+            #;(define |my var| "commented out")
+            (list |my var| #\x41 #true #false (string-length "a\
+                b"))
+        "#;
+    let mut engine = Engine::default();
+    let result = engine.eval::<Expr>(source).unwrap().unwrap();
+    assert_eq!(
+        result,
+        Expr::new_proper_list(exprs![
+            Expr::new_string("say \"hi\"!\n".to_string()),
+            Expr::Char('A'),
+            Expr::Boolean(true),
+            Expr::Boolean(false),
+            Expr::Integer(2)
+        ])
+    );
+}
+
+#[test]
+fn eval_error_locations() {
+    let source = "(define (f x)\n  (car x))\n(display\n   (+ 1 bar))";
+    let mut engine = Engine::default();
+    let err = engine.eval_source::<()>(source, "foo.scm").unwrap_err();
+    assert_eq!(err.to_string(), "foo.scm:4:4: unbound variable: bar");
+
+    let err = engine.eval_source::<()>("(f 1)", "bar.scm").unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!(
+        (span.file(), span.line(), span.column()),
+        (Some("foo.scm"), 2, 3)
+    );
+
+    let err = engine.eval::<()>("(f\n '(1 2)))").unwrap_err();
+    assert!(err.to_string().starts_with("2:9: parse error: "));
+}
+
+#[test]
+fn eval_error_backtrace() {
+    let source =
+        "(define (f x)\n  (car x))\n(define (g x)\n  (+ 1 (f x)))\n(define (h x) (g x))\n(h 1)";
+    let mut engine = Engine::default();
+    let err = engine.eval_source::<()>(source, "foo.scm").unwrap_err();
+    let frames: Vec<_> = err
+        .backtrace()
+        .frames()
+        .iter()
+        .map(|frame| {
+            (
+                frame.name(),
+                frame.span().unwrap().to_string(),
+                frame.is_tail_call(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            ("car", "foo.scm:2:3".to_string(), false),
+            ("f", "foo.scm:4:8".to_string(), false),
+            ("g", "foo.scm:5:15".to_string(), true),
+            ("h", "foo.scm:6:1".to_string(), false),
+        ]
+    );
+
+    // errors not raised by procedures have no backtrace
+    let err = engine.eval::<()>("undefined-var").unwrap_err();
+    assert!(err.backtrace().is_empty());
+}
+
+#[test]
+fn eval_error_kinds() {
+    fn checked_sqrt(mut args: Exprs, _: &mut EnvRef) -> ProcedureResult {
+        match args.pop_front().unwrap() {
+            Expr::Integer(n) if n >= 0 => Ok(lispdm::ProcedureReturn::Value(Expr::Float(
+                (n as f64).sqrt(),
+            ))),
+            other => Err(ErrorKind::TypeMismatch(format!(
+                "expected non-negative integer, got {}",
+                other.kind()
+            ))
+            .into()),
+        }
+    }
+
+    let mut engine = Engine::default();
+    engine.register_fn(
+        "checked-sqrt",
+        ProcedureKind::Procedure,
+        Arity::Exact(1),
+        checked_sqrt,
+    );
+
+    let err = engine.eval::<()>("undefined-var").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::UnboundVariable("undefined-var".to_string())
+    );
+
+    let err = engine.eval::<()>("(car '(1) '(2))").unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::Arity { procedure, expected: Arity::Exact(1), got: 2 } if procedure == "car"
+    ));
+
+    for procedure in ["file-exists?", "delete-file", "get-environment-variable"] {
+        let err = engine.eval::<()>(&format!("({})", procedure)).unwrap_err();
+        assert!(
+            matches!(
+                err.kind(),
+                ErrorKind::Arity { procedure: name, expected: Arity::Exact(1), got: 0 } if name == procedure
+            ),
+            "{}",
+            err
+        );
+    }
+
+    let err = engine.eval::<()>("(car 1)").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TypeMismatch(_)));
+
+    let err = engine.eval::<()>("(raise (list 'oops 42))").unwrap_err();
+    assert_eq!(
+        err.into_kind(),
+        ErrorKind::Raise(Expr::new_proper_list(exprs![
+            Expr::Symbol("oops".to_string()),
+            Expr::Integer(42)
+        ]))
+    );
+
+    let err = engine.eval::<()>("(checked-sqrt -1)").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TypeMismatch(_)));
+    assert_eq!(err.span().map(|span| span.column()), Some(1));
+    assert_eq!(err.backtrace().frames()[0].name(), "checked-sqrt");
+    assert_eq!(engine.eval::<f64>("(checked-sqrt 4)").unwrap(), Ok(2.0));
+
+    let err = engine.eval::<()>("(f").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Parse(_)));
+}
+
+#[test]
+fn eval_recursion_depth_limit() {
+    let mut engine = Engine::default();
+    engine
+        .eval::<()>("(define (f n) (if (= n 0) 0 (+ 1 (f (- n 1)))))")
+        .unwrap()
+        .unwrap();
+
+    // deeper than the native stack of a test thread allows
+    assert_eq!(engine.eval::<i64>("(f 5000)").unwrap(), Ok(5000));
+    let err = engine.eval::<i64>("(f 100000)").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::RecursionDepthExceeded(engine.max_depth())
+    );
+    assert!(err
+        .to_string()
+        .ends_with("maximum recursion depth exceeded (limit is 10000)"));
+
+    engine.set_max_depth(50);
+    assert!(engine.eval::<i64>("(f 100)").is_err());
+    // tail calls are not nested
+    assert_eq!(
+        engine
+            .eval::<i64>("(let loop ((i 0)) (if (= i 1000) i (loop (+ i 1))))")
+            .unwrap(),
+        Ok(1000)
+    );
+}
+
+#[test]
+fn eval_deeply_nested_datum() {
+    let mut engine = Engine::default();
+    let nested = |depth: usize| format!("{}{}", "(a ".repeat(depth), ")".repeat(depth));
+
+    // reading, macro expansion, printing and dropping deep data do not overflow the native stack
+    engine.set_max_depth(20_000);
+    let result = engine.eval::<Expr>(&format!("(quote {})", nested(10_000)));
+    assert_eq!(
+        result.unwrap().unwrap().to_string(),
+        nested(10_000).replace(" )", ")")
+    );
+    let result = engine.eval::<bool>(&format!("(equal? '{0} '{0})", nested(10_000)));
+    assert_eq!(result.unwrap(), Ok(true));
+
+    // macros are expanded in nested lists, so their depth is limited
+    engine.set_max_depth(10_000);
+    let err = engine
+        .eval::<()>(&format!("(quote {})", nested(100_000)))
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::RecursionDepthExceeded(engine.max_depth())
+    );
+}
+
+#[test]
+fn eval_with_limits() {
+    let mut engine = Engine::default();
+    let infinite_loop = "(let loop ((i 0)) (loop (+ i 1)))";
+
+    let err = engine
+        .eval_with_limits::<()>(infinite_loop, Limits::new().with_fuel(10_000))
+        .unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::OutOfFuel);
+
+    let err = engine
+        .eval_with_limits::<()>("(do ((i 0 (+ i 1))) (#f))", Limits::new().with_fuel(1000))
+        .unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::OutOfFuel);
+
+    let started = std::time::Instant::now();
+    let err = engine
+        .eval_with_limits::<()>(
+            infinite_loop,
+            Limits::new().with_timeout(Duration::from_millis(50)),
+        )
+        .unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::DeadlineExceeded);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // primitives building values of a requested size spend a step per element
+    for expr in [
+        "(make-string 100000000 #\\a)",
+        "(make-list 100000000)",
+        "(define s (make-string 1000)) (let loop ((s s)) (loop (string-append s s)))",
+    ] {
+        let err = engine
+            .eval_with_limits::<()>(expr, Limits::new().with_fuel(10_000))
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::OutOfFuel, "{}", expr);
+    }
+    let err = engine.eval::<()>("(make-string -1)").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "1:1: runtime error: make-string expected a non-negative integer as its first argument"
+    );
+
+    // enough fuel to finish
+    let result = engine
+        .eval_with_limits::<i64>(
+            "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1))))) (fact 10)",
+            Limits::new().with_fuel(10_000),
+        )
+        .unwrap();
+    assert_eq!(result, Ok(3628800));
+
+    // limits are not kept after evaluation
+    assert_eq!(
+        engine
+            .eval::<i64>("(let loop ((i 0)) (if (= i 5000) i (loop (+ i 1))))")
+            .unwrap(),
+        Ok(5000)
+    );
+}
+
+#[test]
+fn eval_interrupted() {
+    let mut engine = Engine::default();
+    engine.eval::<()>("(define x 1)").unwrap().unwrap();
+
+    // interrupts requested before evaluation are ignored
+    engine.interrupt_handle().interrupt();
+    assert_eq!(engine.eval::<i64>("x").unwrap(), Ok(1));
+
+    let handle = engine.interrupt_handle();
+    let interrupter = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+    let err = engine
+        .eval::<()>("(let loop ((i 0)) (set! x i) (loop (+ i 1)))")
+        .unwrap_err();
+    interrupter.join().unwrap();
+    assert_eq!(err.kind(), &ErrorKind::Interrupted);
+
+    // environment is kept
+    assert!(engine.eval::<i64>("x").unwrap().unwrap() > 1);
+}
+
+#[test]
+fn eval_sandboxed_engine() {
+    let mut engine = EngineBuilder::new().build();
+    for name in [
+        "load",
+        "open-input-file",
+        "open-output-file",
+        "delete-file",
+        "get-environment-variable",
+        "command-line",
+        "exit",
+    ] {
+        assert_absent_in_env!(engine.env(), name);
+    }
+    // output is discarded and input is empty
+    assert_eq!(
+        engine.eval::<Expr>("(display \"hidden\")").unwrap(),
+        Ok(Expr::Void)
+    );
+    assert!(engine.eval::<Expr>("(read-char)").is_err());
+    assert_eq!(engine.eval::<i64>("(+ 1 2)").unwrap(), Ok(3));
+
+    let dir = std::env::temp_dir().join("lispdm_sandboxed_engine");
+    let root = dir.join("root");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+    let mut engine = EngineBuilder::new()
+        .allow(Capability::FsRead)
+        .allow(Capability::FsWrite)
+        .root_dir(&root)
+        .build();
+    assert!(engine.env().has("load"));
+    assert_absent_in_env!(engine.env(), "exit");
+
+    let result = engine.eval::<Expr>(
+        "(with-output-to-file \"data.txt\" (lambda () (write-string \"inside\")))
+         (with-input-from-file \"./sub/../data.txt\" read-string)",
+    );
+    assert_eq!(result.unwrap(), Ok(Expr::new_string("inside".to_string())));
+    assert!(root.join("data.txt").exists());
+
+    let secret = dir.join("secret.txt");
+    for path in ["../secret.txt", secret.to_str().unwrap(), "~/.bashrc"] {
+        let err = engine
+            .eval::<()>(&format!("(open-input-file {:?})", path))
+            .unwrap_err();
+        assert!(
+            matches!(err.kind(), ErrorKind::PermissionDenied(_)),
+            "{}",
+            path
+        );
+    }
+    let err = engine
+        .eval::<()>("(delete-file \"../secret.txt\")")
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::PermissionDenied(_)));
+    assert!(secret.exists());
+
+    #[cfg(unix)]
+    {
+        let link = root.join("link");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        let err = engine
+            .eval::<()>("(open-input-file \"link/secret.txt\")")
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::PermissionDenied(_)));
+
+        // dangling symlinks are not followed when the file is created
+        let dangling = root.join("dangling");
+        let target = dir.join("outside.txt");
+        let _ = std::fs::remove_file(&dangling);
+        std::os::unix::fs::symlink(&target, &dangling).unwrap();
+        for source in [
+            "(call-with-output-file \"dangling\" (lambda () (display \"x\")))",
+            "(open-output-file \"dangling\")",
+            "(open-output-file \"sub/../dangling\" 'append)",
+        ] {
+            let err = engine.eval::<()>(source).unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::PermissionDenied(_)),
+                "{}",
+                source
+            );
+        }
+        assert!(!target.exists());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn eval_exit() {
+    let mut engine = Engine::default();
+    engine.eval::<()>("(define log '())").unwrap().unwrap();
+    let source = "
+        (dynamic-wind
+          (lambda () (set! log (cons 'before log)))
+          (lambda () (exit 3) (set! log (cons 'unreachable log)))
+          (lambda () (set! log (cons 'after log))))";
+    let err = engine.eval::<()>(source).unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::Exit(3));
+    assert_eq!(
+        engine.eval::<Expr>("log").unwrap(),
+        Ok(Expr::new_proper_list(exprs![
+            Expr::Symbol("after".to_string()),
+            Expr::Symbol("before".to_string())
+        ]))
+    );
+
+    let err = engine
+        .eval::<()>(
+            "(dynamic-wind (lambda () 1) (lambda () (emergency-exit)) (lambda () (set! log '())))",
+        )
+        .unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::EmergencyExit(0));
+    assert_eq!(engine.eval::<i64>("(length log)").unwrap(), Ok(2));
+
+    // errors of `after` do not replace errors of `thunk`
+    let err = engine
+        .eval::<()>("(dynamic-wind (lambda () 1) (lambda () (exit 3)) (lambda () (car '())))")
+        .unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::Exit(3));
+    let err = engine
+        .eval::<()>("(dynamic-wind (lambda () 1) (lambda () (raise 'body)) (lambda () (car '())))")
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::Raise(Expr::Symbol("body".to_string()))
+    );
+    let err = engine
+        .eval::<()>("(dynamic-wind (lambda () 1) (lambda () 2) (lambda () (raise 'after)))")
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::Raise(Expr::Symbol("after".to_string()))
+    );
+
+    assert_eq!(
+        engine.eval::<()>("(exit)").unwrap_err().kind(),
+        &ErrorKind::Exit(0)
+    );
+    assert_eq!(
+        engine.eval::<()>("(exit #t)").unwrap_err().kind(),
+        &ErrorKind::Exit(0)
+    );
+    assert_eq!(
+        engine.eval::<()>("(exit #f)").unwrap_err().kind(),
+        &ErrorKind::Exit(1)
+    );
+
+    // engine can be used after exit
+    assert_eq!(
+        engine
+            .eval::<i64>("(dynamic-wind (lambda () 1) (lambda () 2) (lambda () 3))")
+            .unwrap(),
+        Ok(2)
+    );
+}
+
+#[test]
+fn eval_registered_closures() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let store = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::default();
+
+    let pushed = store.clone();
+    engine.register_closure(
+        "push!",
+        ProcedureKind::Procedure,
+        Arity::Any,
+        move |args, _| {
+            pushed.borrow_mut().extend(args);
+            Ok(lispdm::ProcedureReturn::Value(Expr::Integer(
+                pushed.borrow().len() as i64,
+            )))
+        },
+    );
+    let mut total = 0;
+    engine.register_closure_mut(
+        "add-to-total!",
+        ProcedureKind::Procedure,
+        Arity::Exact(1),
+        move |mut args, _| {
+            total += args.pop_front().unwrap().into::<i64>().unwrap_or(0);
+            Ok(lispdm::ProcedureReturn::Value(Expr::Integer(total)))
+        },
+    );
+
+    let result = engine
+        .eval::<i64>("(push! 1 2) (for-each push! '(3 4)) (push! 5)")
+        .unwrap();
+    assert_eq!(result, Ok(5));
+    assert_eq!(
+        *store.borrow(),
+        vec![
+            Expr::Integer(1),
+            Expr::Integer(2),
+            Expr::Integer(3),
+            Expr::Integer(4),
+            Expr::Integer(5)
+        ]
+    );
+
+    let result = engine
+        .eval::<i64>("(add-to-total! 10) (add-to-total! 32)")
+        .unwrap();
+    assert_eq!(result, Ok(42));
+    assert_eq!(engine.eval::<bool>("(eq? push! push!)").unwrap(), Ok(true));
+
+    let err = engine.eval::<i64>("(add-to-total! 1 2)").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Arity { .. }));
+}
+
+#[test]
+fn eval_registered_typed_functions() {
+    use std::{cell::RefCell, rc::Rc};
+
+    fn mean(numbers: Vec<f64>) -> Result<f64, ErrorKind> {
+        if numbers.is_empty() {
+            return Err(ErrorKind::Runtime("mean of an empty list".to_string()));
+        }
+        Ok(numbers.iter().sum::<f64>() / numbers.len() as f64)
+    }
+
+    let greetings = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::default();
+    engine.register("answer", || 42);
+    engine.register("mean", mean);
+    engine.register("even?", |n: i64| n % 2 == 0);
+    engine.register("string-repeat", |s: Rc<RefCell<String>>, n: i64| {
+        s.borrow().repeat(n as usize)
+    });
+    let greeted = greetings.clone();
+    engine.register("greet!", move |name: String| {
+        greeted.borrow_mut().push(name);
+    });
+
+    assert_eq!(engine.eval::<i64>("(answer)").unwrap(), Ok(42));
+    assert_eq!(
+        engine.eval::<f64>("(mean '(1.0 2.0 4.5))").unwrap(),
+        Ok(2.5)
+    );
+    assert_eq!(engine.eval::<bool>("(even? 4)").unwrap(), Ok(true));
+    assert_eq!(
+        engine.eval::<Expr>("(string-repeat \"ab\" 3)").unwrap(),
+        Ok(Expr::new_string("ababab".to_string()))
+    );
+    assert_eq!(engine.eval::<()>("(greet! 'alice)").unwrap(), Ok(()));
+    assert_eq!(*greetings.borrow(), vec!["alice".to_string()]);
+
+    let err = engine.eval::<f64>("(mean '())").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::Runtime("mean of an empty list".to_string())
+    );
+    let err = engine.eval::<bool>("(even? 1 2)").unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::Arity {
+            expected: Arity::Exact(1),
+            got: 2,
+            ..
+        }
+    ));
+    let err = engine.eval::<Expr>("(string-repeat 'ab 3)").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected string as argument 1 of string-repeat, got symbol".to_string()
+        )
+    );
+    assert_eq!(err.backtrace().frames()[0].name(), "string-repeat");
+}
+
+#[test]
+fn eval_foreign_objects() {
+    use std::{any::Any, cell::RefCell, fmt};
+
+    struct Counter(RefCell<i64>);
+
+    #[derive(PartialEq)]
+    struct Color(u8, u8, u8);
+
+    impl ForeignObject for Color {
+        fn fmt_foreign(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "#<color {:02x}{:02x}{:02x}>", self.0, self.1, self.2)
+        }
+
+        fn equal_foreign(&self, other: &dyn Any) -> bool {
+            other.downcast_ref::<Color>() == Some(self)
+        }
+    }
+
+    let mut engine = Engine::default();
+    engine.register("make-counter", || Foreign::new(Counter(RefCell::new(0))));
+    engine.register("counter-next!", |counter: ForeignRef<Counter>| {
+        *counter.0.borrow_mut() += 1;
+        *counter.0.borrow()
+    });
+    engine.register("rgb", |r: i64, g: i64, b: i64| {
+        Foreign::with_hooks(Color(r as u8, g as u8, b as u8))
+    });
+
+    assert_eq!(
+        engine
+            .eval::<i64>("(define c (make-counter)) (counter-next! c) (counter-next! c)")
+            .unwrap(),
+        Ok(2)
+    );
+    let counter = engine.eval::<ForeignRef<Counter>>("c").unwrap().unwrap();
+    assert_eq!(*counter.0.borrow(), 2);
+    assert_eq!(
+        engine.eval::<Expr>("c").unwrap().unwrap().to_string(),
+        "#<foreign Counter>"
+    );
+    assert_eq!(engine.eval::<bool>("(eq? c c)").unwrap(), Ok(true));
+    assert_eq!(
+        engine
+            .eval::<bool>("(equal? (make-counter) (make-counter))")
+            .unwrap(),
+        Ok(false)
+    );
+
+    assert_eq!(
+        engine
+            .eval::<bool>("(equal? (rgb 1 2 3) (rgb 1 2 3))")
+            .unwrap(),
+        Ok(true)
+    );
+    assert_eq!(
+        engine
+            .eval::<bool>("(eqv? (rgb 1 2 3) (rgb 1 2 3))")
+            .unwrap(),
+        Ok(false)
+    );
+    assert_eq!(
+        engine
+            .eval::<bool>("(equal? (list (rgb 1 2 3)) (list (rgb 1 2 3)))")
+            .unwrap(),
+        Ok(true)
+    );
+    assert_eq!(
+        engine
+            .eval::<Expr>("(list (rgb 255 0 16))")
+            .unwrap()
+            .unwrap()
+            .to_string(),
+        "(#<color ff0010>)"
+    );
+
+    let err = engine
+        .eval::<i64>("(counter-next! (rgb 0 0 0))")
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch(
+            "expected Counter as argument 1 of counter-next!, got foreign".to_string()
+        )
+    );
+}
+
+#[test]
+fn eval_call_procedures() {
+    let mut engine = Engine::default();
+    engine
+        .eval::<()>(
+            "(define (add a b) (+ a b))
+             (define (count-down n) (if (= n 0) 'done (count-down (- n 1))))
+             (define (make-counter)
+               (let ((count 0))
+                 (lambda () (set! count (+ count 1)) count)))
+             (define answer 42)",
+        )
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(engine.call::<i64, _, _>("add", (1, 2)).unwrap(), Ok(3));
+    assert_eq!(engine.call::<f64, _, _>("+", (1.5, 2, 3)).unwrap(), Ok(6.5));
+    assert_eq!(
+        engine
+            .call::<Vec<i64>, _, _>("list", exprs![Expr::Integer(1), Expr::Integer(2)])
+            .unwrap(),
+        Ok(vec![1, 2])
+    );
+    assert_eq!(
+        engine.call::<String, _, _>("count-down", (10000,)).unwrap(),
+        Ok("done".to_string())
+    );
+
+    let counter = engine
+        .call::<Procedure, _, _>("make-counter", ())
+        .unwrap()
+        .unwrap();
+    assert_eq!(engine.call::<i64, _, _>(&counter, ()).unwrap(), Ok(1));
+    assert_eq!(
+        engine.call::<i64, _, _>(counter.clone(), ()).unwrap(),
+        Ok(2)
+    );
+    assert_eq!(counter.arity(), Arity::Exact(0));
+
+    let err = engine.call::<i64, _, _>("missing", ()).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::UnboundVariable("missing".to_string())
+    );
+    let err = engine.call::<i64, _, _>("answer", ()).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch("expected procedure as answer, got integer".to_string())
+    );
+    let err = engine.call::<i64, _, _>("if", (true, 1, 2)).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::TypeMismatch("expected procedure, got #<special form 'if'>".to_string())
+    );
+    let err = engine.call::<i64, _, _>("add", (1,)).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::Arity {
+            expected: Arity::Exact(2),
+            got: 1,
+            ..
+        }
+    ));
+}
+
 // ========================================================================
 //                      proper tail call tests
 // use `cargo test --features test_tailcall` to run these tests